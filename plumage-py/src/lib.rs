@@ -0,0 +1,312 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Python bindings for Plumage.
+
+use ::plumage::{Color, Dimensions, Float, Generator, Spread};
+use numpy::{PyArray1, PyArray3, PyArrayMethods};
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use std::fs::File;
+use std::io::BufWriter;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+/// Parameters for generating an image. Missing fields have their default
+/// values, except the start color and seed, which are random.
+#[pyclass(module = "plumage")]
+#[derive(Clone)]
+struct Params(::plumage::Params);
+
+impl Params {
+    /// Wraps `params`, or raises `ValueError` if they're invalid.
+    fn validated(params: ::plumage::Params) -> PyResult<Self> {
+        params.validate().map_err(|e| {
+            PyValueError::new_err(format!("invalid params: {e}"))
+        })?;
+        Ok(Self(params))
+    }
+}
+
+#[pymethods]
+impl Params {
+    #[new]
+    #[pyo3(signature = (
+        *,
+        size = None,
+        spread = None,
+        shape = "square",
+        distance_power = None,
+        count_power = None,
+        random_power = None,
+        random_max = None,
+        gamma = None,
+        start_color = None,
+        seed = None,
+        fast_math = None,
+        precise_averaging = None,
+        uniform_averaging = None,
+        antialias_spread = None,
+        extended_range = None,
+        preset = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        size: Option<&str>,
+        spread: Option<usize>,
+        shape: &str,
+        distance_power: Option<Float>,
+        count_power: Option<Float>,
+        random_power: Option<Float>,
+        random_max: Option<Float>,
+        gamma: Option<Float>,
+        start_color: Option<&str>,
+        seed: Option<&str>,
+        fast_math: Option<bool>,
+        precise_averaging: Option<bool>,
+        uniform_averaging: Option<bool>,
+        antialias_spread: Option<bool>,
+        extended_range: Option<bool>,
+        preset: Option<&str>,
+    ) -> PyResult<Self> {
+        let mut builder = match preset {
+            Some(name) => ::plumage::Params::preset(name).ok_or_else(|| {
+                PyValueError::new_err(format!("unknown preset: {name}"))
+            })?,
+            None => ::plumage::Params::builder(),
+        };
+        if let Some(size) = size {
+            let size: Dimensions = size.parse().map_err(|_| {
+                PyValueError::new_err(format!("invalid size: {size}"))
+            })?;
+            builder = builder.dimensions(size);
+        }
+        if let Some(size) = spread {
+            builder = builder.spread(match shape {
+                "square" => Spread::Square {
+                    width: size,
+                },
+                "quarter_circle" => Spread::QuarterCircle {
+                    radius: size,
+                },
+                _ => {
+                    let e = format!("invalid shape: {shape}");
+                    return Err(PyValueError::new_err(e));
+                }
+            });
+        }
+        if let Some(n) = distance_power {
+            builder = builder.distance_power(n);
+        }
+        if let Some(n) = count_power {
+            builder = builder.count_power(n);
+        }
+        if let Some(n) = random_power {
+            builder = builder.random_power(n);
+        }
+        if let Some(n) = random_max {
+            builder = builder.random_max(n);
+        }
+        if let Some(n) = gamma {
+            builder = builder.gamma(n);
+        }
+        if let Some(color) = start_color {
+            let parsed = Color::from_hex(color).or_else(|| {
+                Color::from_name(color)
+            });
+            builder = builder.start_color(parsed.ok_or_else(|| {
+                PyValueError::new_err(format!("invalid color: {color}"))
+            })?);
+        }
+        if let Some(seed) = seed {
+            builder = builder.seed(::plumage::seed_from_hex(seed).ok_or_else(|| {
+                PyValueError::new_err("seed must be 64 hex digits")
+            })?);
+        }
+        if let Some(b) = fast_math {
+            builder = builder.fast_math(b);
+        }
+        if let Some(b) = precise_averaging {
+            builder = builder.precise_averaging(b);
+        }
+        if let Some(b) = uniform_averaging {
+            builder = builder.uniform_averaging(b);
+        }
+        if let Some(b) = antialias_spread {
+            builder = builder.antialias_spread(b);
+        }
+        if let Some(b) = extended_range {
+            builder = builder.extended_range(b);
+        }
+        Self::validated(builder.build())
+    }
+
+    /// Reads params in the format of `.params` files.
+    #[staticmethod]
+    fn from_ron(text: &str) -> PyResult<Self> {
+        let params: ::plumage::Params = ron::from_str(text)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Self::validated(params)
+    }
+
+    /// The params in the format of `.params` files.
+    fn to_ron(&self) -> PyResult<String> {
+        let config = ron::ser::PrettyConfig::new().depth_limit(1);
+        ron::ser::to_string_pretty(&self.0, config)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        let ron = ron::to_string(&self.0)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(format!("Params.from_ron({ron:?})"))
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.0.dimensions.width
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.0.dimensions.height
+    }
+
+    #[getter]
+    fn spread(&self) -> usize {
+        match self.0.spread {
+            Spread::Square {
+                width,
+            } => width,
+            Spread::QuarterCircle {
+                radius,
+            } => radius,
+        }
+    }
+
+    #[getter]
+    fn shape(&self) -> &'static str {
+        match self.0.spread {
+            Spread::Square {
+                ..
+            } => "square",
+            Spread::QuarterCircle {
+                ..
+            } => "quarter_circle",
+        }
+    }
+
+    #[getter]
+    fn distance_power(&self) -> Float {
+        self.0.distance_power
+    }
+
+    #[getter]
+    fn count_power(&self) -> Float {
+        self.0.count_power
+    }
+
+    #[getter]
+    fn random_power(&self) -> Float {
+        self.0.random_power
+    }
+
+    #[getter]
+    fn random_max(&self) -> Float {
+        self.0.random_max
+    }
+
+    #[getter]
+    fn gamma(&self) -> Float {
+        self.0.gamma
+    }
+
+    /// The start color as a hex string, like `"#ff8000"`.
+    #[getter]
+    fn start_color(&self) -> String {
+        format!("#{:06x}", self.0.start_color.to_u32())
+    }
+
+    /// The seed as 64 hex digits.
+    #[getter]
+    fn seed(&self) -> String {
+        ::plumage::seed_to_hex(&self.0.seed)
+    }
+}
+
+/// Creates a generator for `params` that uses `threads` threads, or the
+/// number of CPUs if not given.
+fn generator(params: &Params, threads: Option<usize>) -> Generator {
+    let threads = threads
+        .and_then(NonZeroUsize::new)
+        .or_else(|| std::thread::available_parallelism().ok())
+        .unwrap_or(NonZeroUsize::MIN);
+    Generator::new(params.0.clone()).threads(threads)
+}
+
+/// Generates an image and writes it to `path` as a bitmap.
+#[pyfunction]
+#[pyo3(signature = (params, path, *, threads = None))]
+fn generate_to_file(
+    py: Python<'_>,
+    params: &Params,
+    path: PathBuf,
+    threads: Option<usize>,
+) -> PyResult<()> {
+    let generator = generator(params, threads);
+    py.detach(|| {
+        let mut writer = BufWriter::new(File::create(&path)?);
+        generator.generate(&mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()
+    })
+    .map_err(|e| PyOSError::new_err(e.to_string()))
+}
+
+/// Generates an image and returns it as a NumPy array of shape
+/// `(height, width, 3)`, with red, green, and blue components between 0
+/// and 1.
+#[pyfunction]
+#[pyo3(signature = (params, *, threads = None))]
+fn generate_to_numpy<'py>(
+    py: Python<'py>,
+    params: &Params,
+    threads: Option<usize>,
+) -> PyResult<Bound<'py, PyArray3<Float>>> {
+    let generator = generator(params, threads);
+    // Repeats, borders, and expanding rotations make the image larger than
+    // `params.dimensions`.
+    let (dim, data) = py.detach(|| {
+        let pixmap = generator.into_pixmap();
+        let dim = pixmap.dimensions();
+        let mut data = Vec::with_capacity(dim.count() * 3);
+        for (_, color) in pixmap.enumerate_pixels() {
+            data.extend([color.red, color.green, color.blue]);
+        }
+        (dim, data)
+    });
+    PyArray1::from_vec(py, data).reshape([dim.height, dim.width, 3])
+}
+
+#[pymodule]
+fn plumage(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Params>()?;
+    m.add_function(wrap_pyfunction!(generate_to_file, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_to_numpy, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,91 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::Params;
+
+/// What this build of the library supports, so front-ends can adapt to
+/// the installed version. Returned by [`capabilities`].
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// The version of the library.
+    pub version: &'static str,
+    /// The version of the params format, [`Params::VERSION`].
+    pub params_version: u32,
+    /// The image formats that [`Generator`](crate::Generator) can write.
+    pub formats: &'static [&'static str],
+    /// The shapes of [`Spread`](crate::Spread), as named in params.
+    pub spreads: &'static [&'static str],
+    /// The passes applied after filling, as named in
+    /// [`Timings::passes`](crate::Timings::passes).
+    pub passes: &'static [&'static str],
+    /// The Cargo features that were enabled.
+    pub features: &'static [&'static str],
+}
+
+/// The Cargo features that were enabled.
+const FEATURES: &[&str] = &[
+    #[cfg(feature = "std")]
+    "std",
+    #[cfg(feature = "libm")]
+    "libm",
+    #[cfg(feature = "f64")]
+    "f64",
+    #[cfg(feature = "bytemuck")]
+    "bytemuck",
+    #[cfg(feature = "image")]
+    "image",
+    #[cfg(feature = "palette")]
+    "palette",
+    #[cfg(feature = "ndarray")]
+    "ndarray",
+    #[cfg(feature = "tokio")]
+    "tokio",
+    #[cfg(feature = "rayon")]
+    "rayon",
+    #[cfg(feature = "yaml")]
+    "yaml",
+    #[cfg(feature = "wasm")]
+    "wasm",
+    #[cfg(feature = "window")]
+    "window",
+    #[cfg(feature = "tui")]
+    "tui",
+    #[cfg(feature = "plugins")]
+    "plugins",
+    #[cfg(feature = "gui")]
+    "gui",
+    #[cfg(feature = "cli-panic")]
+    "cli-panic",
+];
+
+/// Reports what this build of the library supports. The presets are listed
+/// by [`Params::preset_names`].
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        params_version: Params::VERSION,
+        formats: &["bmp"],
+        spreads: &["Square", "QuarterCircle"],
+        passes: &[
+            "orient", "gamma", "duotone", "rotate", "repeat", "border",
+        ],
+        features: FEATURES,
+    }
+}
@@ -0,0 +1,242 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::atomic::{self, AtomicFile};
+use super::{base, check_pixels, cpus, interrupt, parse_value};
+use super::{resolve_max_pixels, write_image};
+use plumage::{Float, Generator, Params, Spread};
+use ron::ser::PrettyConfig;
+use serde::Serialize;
+use std::io::{self, BufWriter, Write};
+use std::mem;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+/// Options for `plumage animate`.
+struct Options {
+    from: PathBuf,
+    to: PathBuf,
+    /// The name of the frames, or `-` to write them to stdout.
+    name: String,
+    frames: NonZeroUsize,
+    threads: Option<NonZeroUsize>,
+    max_pixels: usize,
+    quiet: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut positional = Vec::new();
+    let mut frames = NonZeroUsize::new(60).unwrap();
+    let mut threads = None;
+    let mut quiet = false;
+    let mut max_pixels = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-f" | "--frames" => frames = parse_value(&arg, args.next()),
+            "-j" | "--jobs" => threads = Some(parse_value(&arg, args.next())),
+            "-q" | "--quiet" => quiet = true,
+            "--max-pixels" => {
+                max_pixels = Some(parse_value(&arg, args.next()));
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                args_error!("unrecognized option: {arg}");
+            }
+            _ if positional.len() < 3 => positional.push(arg),
+            _ => {
+                args_error!("unexpected argument: {arg}");
+            }
+        }
+    }
+    let Ok([from, to, name]) = <[String; 3]>::try_from(positional) else {
+        args_error!("expected <from>, <to>, and <name>");
+    };
+    Options {
+        from: from.into(),
+        to: to.into(),
+        name,
+        frames,
+        threads,
+        max_pixels: resolve_max_pixels(max_pixels),
+        quiet,
+    }
+}
+
+/// Reads the params file at `path` and its bases.
+fn read_params(path: &Path) -> Params {
+    let (params, _) = base::read(path)
+        .and_then(|layers| base::resolve(&layers))
+        .unwrap_or_else(|e| {
+            let path = path.display();
+            error_exit!(Params, "error reading params from {path}: {e}");
+        });
+    params
+}
+
+/// The params of the frame `t` of the way from `from` to `to`. The seed
+/// and options are taken from `from`.
+fn interpolate(from: &Params, to: &Params, t: Float) -> Params {
+    let lerp = |a: Float, b: Float| a + (b - a) * t;
+    let lerp_size = |a: usize, b: usize| {
+        lerp(a as Float, b as Float).round().max(1.0) as usize
+    };
+    let mut params = from.clone();
+    params.spread = match (from.spread, to.spread) {
+        (
+            Spread::Square {
+                width: a,
+            },
+            Spread::Square {
+                width: b,
+            },
+        ) => Spread::Square {
+            width: lerp_size(a, b),
+        },
+        (
+            Spread::QuarterCircle {
+                radius: a,
+            },
+            Spread::QuarterCircle {
+                radius: b,
+            },
+        ) => Spread::QuarterCircle {
+            radius: lerp_size(a, b),
+        },
+        _ => unreachable!("spread shapes differ"),
+    };
+    params.distance_power = lerp(from.distance_power, to.distance_power);
+    params.count_power = lerp(from.count_power, to.count_power);
+    params.random_power = lerp(from.random_power, to.random_power);
+    params.random_max = lerp(from.random_max, to.random_max);
+    params.gamma = lerp(from.gamma, to.gamma);
+    params.start_color = from.start_color.lerp(to.start_color, t);
+    params
+}
+
+/// A frame in the manifest.
+#[derive(Serialize)]
+struct Frame<'a> {
+    /// The name of the frame's file, which is in the same directory as the
+    /// manifest.
+    file: String,
+    params: &'a Params,
+}
+
+/// Writes the manifest of frames to `path`.
+fn write_manifest(path: &Path, frames: &[Frame<'_>]) -> io::Result<()> {
+    let mut writer = BufWriter::new(AtomicFile::create(path)?);
+    let pretty = PrettyConfig::new().depth_limit(2);
+    ron::ser::to_writer_pretty(&mut writer, frames, pretty)
+        .map_err(io::Error::other)?;
+    writeln!(writer)?;
+    writer.into_inner().map_err(|e| e.into_error())?.commit()
+}
+
+/// Renders frames that interpolate between two params files. The frames are
+/// written as `<name>-0001.bmp`, etc., along with `<name>.frames`, which
+/// lists each frame and its params, or are written to stdout one after
+/// another if `<name>` is `-`.
+pub fn animate(args: impl Iterator<Item = String>) {
+    let options = parse_args(args);
+    let from = read_params(&options.from);
+    let to = read_params(&options.to);
+    let (a, b) = (from.dimensions, to.dimensions);
+    if (a.width, a.height) != (b.width, b.height) {
+        error_exit!(Params, "params must have the same dimensions");
+    }
+    if mem::discriminant(&from.spread) != mem::discriminant(&to.spread) {
+        error_exit!(Params, "params must have the same spread shape");
+    }
+    check_pixels(from.output_dimensions(), options.max_pixels);
+
+    let count = options.frames.get();
+    let threads = options.threads.or(from.threads).unwrap_or_else(cpus);
+    let params: Vec<_> = (0..count)
+        .map(|i| {
+            // The last frame is `to`.
+            let t = i as Float / (count - 1).max(1) as Float;
+            interpolate(&from, &to, t)
+        })
+        .collect();
+    for (i, params) in params.iter().enumerate() {
+        if let Err(e) = params.validate() {
+            error_exit!(Params, "invalid params for frame {}: {e}", i + 1);
+        }
+    }
+
+    interrupt::install();
+    let generator = |params: &Params| {
+        Generator::new(params.clone())
+            .threads(threads)
+            .interrupt_flag(interrupt::flag())
+    };
+    if options.name == "-" {
+        let mut stdout = BufWriter::new(io::stdout().lock());
+        for params in &params {
+            generator(params).generate(&mut stdout).unwrap_or_else(|e| {
+                error_exit!(Generate, "error writing frame: {e}");
+            });
+            if interrupt::interrupted() {
+                break;
+            }
+        }
+        stdout.flush().unwrap_or_else(|e| {
+            error_exit!(Generate, "error writing frame: {e}");
+        });
+        if interrupt::interrupted() {
+            atomic::remove_pending();
+        exit(130);
+        }
+        return;
+    }
+
+    let width = count.to_string().len().max(4);
+    let mut frames = Vec::with_capacity(count);
+    for (i, params) in params.iter().enumerate() {
+        let file = format!("{}-{:0width$}.bmp", options.name, i + 1);
+        let output = AtomicFile::create(Path::new(&file));
+        output
+            .and_then(|output| {
+                write_image(output, generator(params), false, Vec::new())
+            })
+            .unwrap_or_else(|e| {
+                error_exit!(Generate, "error writing {file}: {e}");
+            });
+        if !options.quiet {
+            println!("{file}");
+        }
+        let file = Path::new(&file).file_name().unwrap_or_default();
+        frames.push(Frame {
+            file: file.to_string_lossy().into_owned(),
+            params,
+        });
+        if interrupt::interrupted() {
+            break;
+        }
+    }
+    let manifest = PathBuf::from(format!("{}.frames", options.name));
+    write_manifest(&manifest, &frames).unwrap_or_else(|e| {
+        error_exit!(Io, "could not write {}: {e}", manifest.display());
+    });
+    if interrupt::interrupted() {
+        eprintln!("interrupted; the last frame is partial");
+        atomic::remove_pending();
+        exit(130);
+    }
+}
@@ -0,0 +1,164 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::format::Format;
+use plumage::Params;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The maximum number of nested base params files, which catches cycles.
+const MAX_DEPTH: usize = 16;
+
+/// A params file, which may be the base of another.
+pub struct Layer {
+    pub format: Format,
+    pub text: String,
+    /// The path of the file, if it wasn't read from stdin.
+    pub path: Option<PathBuf>,
+}
+
+/// Loads the params in `text` and the chain of base params files it names,
+/// returning them from the outermost base to `text` itself. `path` is the
+/// path of `text`, if it was read from a file, and bases are relative to
+/// the directory of the file that names them. If `base` is given, it's used
+/// instead of the base named in `text`, and is relative to the current
+/// directory.
+pub fn load(
+    text: String,
+    format: Format,
+    path: Option<&Path>,
+    base: Option<PathBuf>,
+) -> Result<Vec<Layer>, String> {
+    let parent = |path: &Path| path.parent().map(Path::to_owned);
+    let mut dir = PathBuf::new();
+    if base.is_none() {
+        dir = path.and_then(parent).unwrap_or_default();
+    }
+    let mut base = base.or_else(|| format.base(&text).map(PathBuf::from));
+    let mut layers = vec![Layer {
+        format,
+        text,
+        path: path.map(Path::to_owned),
+    }];
+    while let Some(relative) = base {
+        if layers.len() > MAX_DEPTH {
+            return Err("too many nested base params files".into());
+        }
+        let path = dir.join(relative);
+        let text = fs::read_to_string(&path).map_err(|e| {
+            format!("error reading base params {}: {e}", path.display())
+        })?;
+        let format = Format::from_path(&path);
+        base = format.base(&text).map(PathBuf::from);
+        dir = parent(&path).unwrap_or_default();
+        layers.push(Layer {
+            format,
+            text,
+            path: Some(path),
+        });
+    }
+    layers.reverse();
+    Ok(layers)
+}
+
+/// Reads the params file at `path` and its bases, as with [`load`].
+pub fn read(path: &Path) -> Result<Vec<Layer>, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    load(text, Format::from_path(path), Some(path), None)
+}
+
+/// Deserializes each layer, with the fields given in each overriding those
+/// of its bases. Returns the params and the names of all fields given in
+/// any layer. Errors in bases include their paths.
+pub fn resolve(layers: &[Layer]) -> Result<(Params, Vec<String>), String> {
+    let mut resolved: Option<Params> = None;
+    let mut all_fields = Vec::new();
+    for (i, layer) in layers.iter().enumerate() {
+        let is_base = i + 1 < layers.len();
+        let params = layer.format.parse(&layer.text).map_err(|e| {
+            match &layer.path {
+                Some(path) if is_base => format!("{}: {e}", path.display()),
+                _ => e,
+            }
+        })?;
+        let fields = layer.format.given_fields(&layer.text);
+        resolved = Some(match resolved {
+            Some(mut base) => {
+                override_fields(&mut base, params, &fields);
+                base
+            }
+            None => params,
+        });
+        all_fields.extend(fields);
+    }
+    let params = resolved.expect("no params layers");
+    Ok((params, all_fields))
+}
+
+/// Sets the fields of `params` named in `fields` to those in `other`.
+fn override_fields(
+    params: &mut Params,
+    mut other: Params,
+    fields: &[String],
+) {
+    for field in fields {
+        match field.as_str() {
+            "version" => params.version = other.version,
+            "dimensions" => params.dimensions = other.dimensions,
+            "spread" => params.spread = other.spread,
+            "distance_power" => params.distance_power = other.distance_power,
+            "count_power" => params.count_power = other.count_power,
+            "random_power" => {
+                params.random_power = other.random_power;
+                params.expressions.random_power =
+                    other.expressions.random_power.take();
+            }
+            "random_max" => {
+                params.random_max = other.random_max;
+                params.expressions.random_max =
+                    other.expressions.random_max.take();
+            }
+            "gamma" => {
+                params.gamma = other.gamma;
+                params.expressions.gamma = other.expressions.gamma.take();
+            }
+            "start_color" => params.start_color = other.start_color,
+            "seed" => params.seed = other.seed,
+            "fast_math" => params.fast_math = other.fast_math,
+            "precise_averaging" => {
+                params.precise_averaging = other.precise_averaging;
+            }
+            "uniform_averaging" => {
+                params.uniform_averaging = other.uniform_averaging;
+            }
+            "antialias_spread" => {
+                params.antialias_spread = other.antialias_spread;
+            }
+            "extended_range" => params.extended_range = other.extended_range,
+            "start_corner" => params.start_corner = other.start_corner,
+            "direction" => params.direction = other.direction,
+            "rotation" => params.rotation = other.rotation,
+            "repeat" => params.repeat = other.repeat,
+            "border" => params.border = other.border,
+            "duotone" => params.duotone = other.duotone,
+            "threads" => params.threads = other.threads,
+            _ => {}
+        }
+    }
+}
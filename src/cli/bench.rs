@@ -0,0 +1,172 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{base, cpus, parse_value};
+use plumage::{Dimensions, Generator, Params, Spread, Timings};
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Options for `plumage bench`.
+struct Options {
+    path: PathBuf,
+    runs: NonZeroUsize,
+    sizes: Vec<Dimensions>,
+    spreads: Vec<usize>,
+    threads: Option<NonZeroUsize>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut path = None;
+    let mut runs = NonZeroUsize::new(3).unwrap();
+    let mut sizes = Vec::new();
+    let mut spreads = Vec::new();
+    let mut threads = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-r" | "--runs" => runs = parse_value(&arg, args.next()),
+            "-s" | "--size" => sizes.push(parse_value(&arg, args.next())),
+            "--spread" => spreads.push(parse_value(&arg, args.next())),
+            "-j" | "--jobs" => threads = Some(parse_value(&arg, args.next())),
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                args_error!("unrecognized option: {arg}");
+            }
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => {
+                args_error!("unexpected argument: {arg}");
+            }
+        }
+    }
+    let Some(path) = path else {
+        args_error!("missing params file");
+    };
+    Options {
+        path,
+        runs,
+        sizes,
+        spreads,
+        threads,
+    }
+}
+
+/// Sets the size of `spread`, keeping its shape.
+fn resize_spread(spread: Spread, size: usize) -> Spread {
+    match spread {
+        Spread::Square {
+            ..
+        } => Spread::Square {
+            width: size,
+        },
+        Spread::QuarterCircle {
+            ..
+        } => Spread::QuarterCircle {
+            radius: size,
+        },
+    }
+}
+
+/// Describes the size and spread of `params`.
+fn describe(params: &Params) -> String {
+    let dim = params.dimensions;
+    let spread = match params.spread {
+        Spread::Square {
+            width,
+        } => format!("square spread {width}"),
+        Spread::QuarterCircle {
+            radius,
+        } => format!("quarter-circle spread {radius}"),
+    };
+    format!("{}x{}, {spread}", dim.width, dim.height)
+}
+
+/// Prints the mean and minimum of each stage across `runs`, and the mean
+/// number of pixels generated per second.
+fn report(params: &Params, runs: &[Timings]) {
+    let mean = |times: &[Duration]| {
+        times.iter().sum::<Duration>() / times.len() as u32
+    };
+    let mut stages: Vec<(&str, Vec<_>)> =
+        vec![("fill", runs.iter().map(|t| t.fill).collect())];
+    for (i, (name, _)) in runs[0].passes.iter().enumerate() {
+        stages.push((name, runs.iter().map(|t| t.passes[i].1).collect()));
+    }
+    stages.push(("quantize", runs.iter().map(|t| t.quantize).collect()));
+    stages.push(("write", runs.iter().map(|t| t.write).collect()));
+    stages.push(("total", runs.iter().map(Timings::total).collect()));
+
+    println!("{}: {} runs", describe(params), runs.len());
+    for (name, times) in &stages {
+        let min = times.iter().min().copied().unwrap_or_default();
+        println!(
+            "  {name:<9} mean {:8.3}s  min {:8.3}s",
+            mean(times).as_secs_f64(),
+            min.as_secs_f64(),
+        );
+    }
+    let totals: Vec<_> = runs.iter().map(Timings::total).collect();
+    let pixels = params.dimensions.count() as f64;
+    let rate = pixels / mean(&totals).as_secs_f64() / 1e6;
+    println!("  {rate:.2} megapixels/s");
+}
+
+/// Renders a params file repeatedly, optionally with each combination of
+/// the given sizes and spreads, and reports how long each stage took.
+pub fn bench(args: impl Iterator<Item = String>) {
+    let options = parse_args(args);
+    let layers = base::read(&options.path).unwrap_or_else(|e| {
+        error_exit!(Params, "error reading params: {e}");
+    });
+    let (params, _) = base::resolve(&layers).unwrap_or_else(|e| {
+        error_exit!(Params, "error reading params: {e}");
+    });
+    let threads = options.threads.or(params.threads).unwrap_or_else(cpus);
+    let sizes = match options.sizes.as_slice() {
+        [] => vec![params.dimensions],
+        sizes => sizes.to_vec(),
+    };
+    let spreads = match options.spreads.as_slice() {
+        [] => vec![params.spread],
+        spreads => {
+            spreads.iter().map(|&n| resize_spread(params.spread, n)).collect()
+        }
+    };
+
+    for &dimensions in &sizes {
+        for &spread in &spreads {
+            let mut params = params.clone();
+            params.dimensions = dimensions;
+            params.spread = spread;
+            if let Err(e) = params.validate() {
+                let params = describe(&params);
+                error_exit!(Params, "invalid params ({params}): {e}");
+            }
+            let runs: Vec<_> = (0..options.runs.get())
+                .map(|_| {
+                    let generator =
+                        Generator::new(params.clone()).threads(threads);
+                    generator.generate(io::sink()).unwrap_or_else(|e| {
+                        error_exit!(Generate, "error generating image: {e}");
+                    })
+                })
+                .collect();
+            report(&params, &runs);
+        }
+    }
+}
@@ -0,0 +1,362 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::base::{self, Layer};
+use super::error::Kind;
+use super::format::Format;
+use plumage::{Color, Duotone, Expr, Fit, Float, Params, ParamsError};
+use plumage::{Spread, KNOWN_ANSWERS};
+use ron::ser::PrettyConfig;
+use std::fs::File;
+use std::io::{self, Write};
+use std::mem;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+/// Comments written above each field by `plumage init`.
+const FIELD_COMMENTS: &[(&str, &str)] = &[
+    ("dimensions", "The image size. `\"1920x1080\"` and `\"4k\"` work too."),
+    ("spread", "Square(width: <n>) or QuarterCircle(radius: <n>)."),
+    ("distance_power", "More negative values favor closer pixels."),
+    ("count_power", "1 is a true average; higher darkens, lower brightens."),
+    ("random_power", "Higher values make large color changes rarer."),
+    ("random_max", "The largest change to each color component."),
+    ("gamma", "Values below 1 lighten the image."),
+    ("fast_math", "Faster, but the output differs slightly."),
+    ("precise_averaging", "Avoids banding with large spreads."),
+    ("uniform_averaging", "Much faster with large spreads."),
+    ("antialias_spread", "Smooths the edge of QuarterCircle spreads."),
+    ("extended_range", "Lets colors brighten past white while filling."),
+    ("start_corner", "TopLeft, TopRight, BottomLeft, or BottomRight."),
+    ("direction", "Horizontal (rows first) or Vertical (columns first)."),
+    ("rotation", "Counterclockwise. `fit` is Crop or Expand."),
+    ("repeat", "Copies of the image across and down."),
+    ("border", "Add `inner_color: Some(...)` to fade. `fit`: Crop or Expand."),
+    ("start_color", "Random unless specified. CSS color names work too."),
+    ("seed", "Random unless specified."),
+];
+
+/// Fields that are commented out by `plumage init`, because they're random
+/// unless specified.
+const RANDOM_FIELDS: &[&str] = &["start_color", "seed"];
+
+/// Writes a params file with every field present, so there's something to
+/// edit. Fails if the file already exists.
+pub fn init(mut args: impl Iterator<Item = String>) {
+    let path = PathBuf::from(args.next().unwrap_or_else(|| "params".into()));
+    if let Some(arg) = args.next() {
+        args_error!("unexpected argument: {arg}");
+    }
+
+    let params = Params::builder().build();
+    let pretty = PrettyConfig::new().depth_limit(1);
+    let text = ron::ser::to_string_pretty(&params, pretty)
+        .expect("could not serialize params");
+    let mut out = String::new();
+    for line in text.lines() {
+        let field = line.trim_start().split(':').next().unwrap_or_default();
+        if let Some((_, comment)) =
+            FIELD_COMMENTS.iter().find(|(name, _)| *name == field)
+        {
+            out += &format!("    // {comment}\n");
+        }
+        if RANDOM_FIELDS.contains(&field) {
+            out += &format!("    //{}\n", line.trim_start());
+        } else {
+            out += &format!("{line}\n");
+        }
+        if field == "border" {
+            out += "    // Maps the image onto inks. Add \
+                `midtones: Some(...)` for a tritone.\n    //duotone: \
+                Some((shadows: \"navy\", highlights: \"peachpuff\")),\n";
+            out += "    // The maximum number of threads. Doesn't affect the \
+                image.\n    //threads: 4,\n";
+        }
+    }
+
+    let mut file = File::create_new(&path).unwrap_or_else(|e| {
+        if e.kind() == io::ErrorKind::AlreadyExists {
+            error_exit!(Io, "{} already exists", path.display());
+        }
+        error_exit!(Io, "could not create {}: {e}", path.display());
+    });
+    file.write_all(out.as_bytes()).unwrap_or_else(|e| {
+        error_exit!(Io, "could not write to {}: {e}", path.display());
+    });
+    println!("{}", path.display());
+}
+
+/// Formats a number of bytes, like `23.7 MiB`.
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{size:.1} {unit}")
+}
+
+/// The size of the bitmap generated from `params`, and the memory needed
+/// to generate it, in bytes. The params must be valid.
+pub fn estimate_sizes(params: &Params) -> (usize, usize) {
+    // Bitmap rows are padded to a multiple of 4 bytes.
+    let output = params.output_dimensions();
+    let row = (output.width * 3).div_ceil(4) * 4;
+    let file = row * output.height + 14 + 40;
+    // Rotating or repeating the image makes a copy of it.
+    let copied = !params.rotation.is_identity()
+        || !params.repeat.is_identity()
+        || params.border.fit == Fit::Expand;
+    let dim = params.dimensions;
+    let pixels = if !copied {
+        dim.count()
+    } else {
+        dim.count() + output.count()
+    };
+    (file, pixels * mem::size_of::<Color>())
+}
+
+/// Prints a summary of the params in `path`.
+fn print_info(path: &Path) -> Result<(), String> {
+    let (params, fields) = base::resolve(&base::read(path)?)?;
+    let given = |field: &str| fields.iter().any(|f| f == field);
+
+    let dim = params.dimensions;
+    let megapixels = dim.width as f64 * dim.height as f64 / 1e6;
+    let spread = match params.spread {
+        Spread::Square {
+            width,
+        } => format!("square, width {width}"),
+        Spread::QuarterCircle {
+            radius,
+        } => format!("quarter circle, radius {radius}"),
+    };
+    let start_color = if given("start_color") {
+        format!("#{:06x}", params.start_color.to_u32())
+    } else {
+        "random".into()
+    };
+    let seed: String = if given("seed") {
+        plumage::seed_to_hex(&params.seed)
+    } else {
+        "random".into()
+    };
+
+    println!("{}", path.display());
+    println!("  version:           {}", params.version);
+    println!(
+        "  dimensions:        {}x{} ({megapixels:.1} megapixels)",
+        dim.width, dim.height,
+    );
+    println!("  spread:            {spread}");
+    println!("  distance_power:    {}", params.distance_power);
+    println!("  count_power:       {}", params.count_power);
+    let exprs = &params.expressions;
+    let value = |n: Float, expr: &Option<Expr>| match expr {
+        Some(expr) => format!("\"{expr}\""),
+        None => n.to_string(),
+    };
+    let random_power = value(params.random_power, &exprs.random_power);
+    let random_max = value(params.random_max, &exprs.random_max);
+    println!("  random_power:      {random_power}");
+    println!("  random_max:        {random_max}");
+    println!("  gamma:             {}", value(params.gamma, &exprs.gamma));
+    println!("  start_color:       {start_color}");
+    println!("  seed:              {seed}");
+    println!("  fast_math:         {}", params.fast_math);
+    println!("  precise_averaging: {}", params.precise_averaging);
+    println!("  uniform_averaging: {}", params.uniform_averaging);
+    println!("  antialias_spread:  {}", params.antialias_spread);
+    println!("  extended_range:    {}", params.extended_range);
+    println!("  start_corner:      {:?}", params.start_corner);
+    println!("  direction:         {:?}", params.direction);
+    let rotation = params.rotation;
+    println!("  rotation:          {} ({:?})", rotation.degrees, rotation.fit);
+    let repeat = params.repeat;
+    println!("  repeat:            {}x{}", repeat.columns, repeat.rows);
+    let border = params.border;
+    let color = |color: Color| format!("#{:06x}", color.to_u32());
+    let colors = match border.inner_color {
+        Some(inner) => format!("{} to {}", color(border.color), color(inner)),
+        None => color(border.color),
+    };
+    println!(
+        "  border:            {}, {colors} ({:?})",
+        border.width, border.fit,
+    );
+    if let Some(duotone) = params.duotone {
+        let colors: Vec<_> = duotone.colors().map(color).collect();
+        println!("  duotone:           {}", colors.join(", "));
+    }
+    if let Some(threads) = params.threads {
+        println!("  threads:           {threads}");
+    }
+    println!("  params_hash:       {}", params.canonicalize().hash());
+    match params.validate() {
+        Ok(()) => {
+            let (file, memory) = estimate_sizes(&params);
+            println!("  bitmap size:       {}", format_bytes(file));
+            println!("  memory needed:     {}", format_bytes(memory));
+        }
+        Err(e) => println!("  invalid:           {e}"),
+    }
+    Ok(())
+}
+
+/// Prints a summary of each params file given, with defaults filled in.
+pub fn info(args: impl Iterator<Item = String>) {
+    let paths: Vec<PathBuf> = args.map(PathBuf::from).collect();
+    if paths.is_empty() {
+        args_error!("missing params file");
+    }
+    let mut failed = false;
+    for (i, path) in paths.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        if let Err(e) = print_info(path) {
+            eprintln!("error: could not read {}: {e}", path.display());
+            failed = true;
+        }
+    }
+    if failed {
+        exit(Kind::Params as i32);
+    }
+}
+
+/// Checks that this build reproduces each of [`KNOWN_ANSWERS`], exiting with
+/// an error if any don't.
+pub fn selftest(mut args: impl Iterator<Item = String>) {
+    if let Some(arg) = args.next() {
+        args_error!("unexpected argument: {arg}");
+    }
+    let mut failed = false;
+    for answer in KNOWN_ANSWERS {
+        match answer.check() {
+            Ok(()) => println!("{}: ok", answer.name),
+            Err(e) => {
+                eprintln!("error: {e}");
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        exit(Kind::Generate as i32);
+    }
+}
+
+/// The names of all fields in [`Params`].
+fn known_fields() -> Vec<String> {
+    // Every field is serialized when `threads` and `duotone` are set.
+    let params = Params::builder()
+        .threads(NonZeroUsize::MIN)
+        .duotone(Duotone {
+            shadows: Color::BLACK,
+            midtones: None,
+            highlights: Color::BLACK,
+        })
+        .build();
+    let text = ron::to_string(&params).expect("could not serialize params");
+    Format::Ron.given_fields(&text)
+}
+
+/// Messages for the unknown fields in each layer. Messages about bases
+/// include their paths.
+pub fn unknown_fields(layers: &[Layer]) -> Vec<String> {
+    let mut known = known_fields();
+    known.push("base".into());
+    let mut problems = Vec::new();
+    for (i, layer) in layers.iter().enumerate() {
+        let fields = layer.format.given_fields(&layer.text);
+        let unknown = fields.iter().filter(|field| !known.contains(field));
+        let is_base = i + 1 < layers.len();
+        problems.extend(unknown.map(|field| match &layer.path {
+            Some(path) if is_base => {
+                format!("{}: unknown field `{field}`", path.display())
+            }
+            _ => format!("unknown field `{field}`"),
+        }));
+    }
+    problems
+}
+
+/// Checks the params in `path`, returning a message for each problem.
+fn check_file(path: &Path, strict: bool) -> Vec<String> {
+    let (params, fields, mut problems) = match base::read(path) {
+        Ok(layers) => match base::resolve(&layers) {
+            Ok((params, fields)) => (params, fields, unknown_fields(&layers)),
+            Err(e) => return vec![e],
+        },
+        Err(e) => return vec![e],
+    };
+    if strict {
+        problems.extend(
+            ["start_color", "seed"]
+                .into_iter()
+                .filter(|field| !fields.iter().any(|f| f == field))
+                .map(|field| ParamsError::Unspecified(field).to_string()),
+        );
+    }
+    if let Err(e) = params.validate() {
+        problems.push(e.to_string());
+    }
+    problems
+}
+
+/// Checks that each params file given is valid, exiting with an error if
+/// any aren't.
+pub fn check(args: impl Iterator<Item = String>) {
+    let mut strict = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--strict" => strict = true,
+            s if s.starts_with('-') => {
+                args_error!("unrecognized option: {s}");
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        args_error!("missing params file");
+    }
+    let mut failed = false;
+    for path in &paths {
+        let problems = check_file(path, strict);
+        for problem in &problems {
+            eprintln!("error: {}: {problem}", path.display());
+        }
+        if problems.is_empty() {
+            println!("{}: ok", path.display());
+        }
+        failed |= !problems.is_empty();
+    }
+    if failed {
+        exit(Kind::Params as i32);
+    }
+}
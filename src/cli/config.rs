@@ -0,0 +1,101 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use ron::extensions::Extensions;
+use ron::Options;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+/// Per-user settings, read from `plumage/config` in the config directory.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The directory in which images given by name are written.
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+    /// The largest image, in pixels, that may be generated.
+    #[serde(default)]
+    pub max_pixels: Option<usize>,
+    /// The file to which each generated image is logged.
+    #[serde(default)]
+    pub log: Option<PathBuf>,
+    /// The directory in which `plumage wallpaper` writes wallpapers.
+    #[serde(default)]
+    pub wallpaper_dir: Option<PathBuf>,
+    /// How many wallpapers `plumage wallpaper` keeps.
+    #[serde(default)]
+    pub wallpaper_keep: Option<NonZeroUsize>,
+}
+
+impl Config {
+    /// Reads the config file, if it exists.
+    pub fn load() -> Self {
+        let Some(path) = config_path("config") else {
+            return Self::default();
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Self::default();
+            }
+            Err(e) => error_exit!(Io, "error reading {}: {e}", path.display()),
+        };
+        // Allow `output_dir: "path"` instead of `output_dir: Some("path")`.
+        let options = Options::default()
+            .with_default_extension(Extensions::IMPLICIT_SOME);
+        options.from_str(&text).unwrap_or_else(|e| {
+            error_exit!(Usage, "error reading {}: {e}", path.display());
+        })
+    }
+}
+
+/// The path of the file `name` in Plumage's config directory:
+/// `$XDG_CONFIG_HOME/plumage`, or `~/.config/plumage` if that isn't set.
+pub fn config_path(name: &str) -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| {
+            let home = env::var_os("HOME").filter(|home| !home.is_empty());
+            Some(PathBuf::from(home?).join(".config"))
+        })?;
+    Some(dir.join("plumage").join(name))
+}
+
+/// Reads the default params: `params` in the config directory, or
+/// `./params` if that doesn't exist, or `()` if neither exist. Returns the
+/// params and the path they were read from, if any.
+pub fn default_params() -> (String, Option<PathBuf>) {
+    let paths = config_path("params").into_iter().chain(["params".into()]);
+    for path in paths {
+        match fs::read_to_string(&path) {
+            Ok(text) => return (text, Some(path)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                let path = path.display();
+                error_exit!(Params, "error reading params from {path}: {e}");
+            }
+        }
+    }
+    ("()".into(), None)
+}
@@ -0,0 +1,431 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! `plumage daemon`, which renders jobs submitted over a Unix socket.
+//!
+//! Each request and response is a single line of RON. Requests are:
+//!
+//! * `Submit(params: (...), output: "<path>")`: Queues an image to be
+//!   written to `<path>`, with its params next to it. Responds with
+//!   `Submitted(id: <n>)`.
+//! * `Status(id: <n>)`: Responds with `Status(<status>)`, where
+//!   `<status>` is like `(id: 1, output: "...", state: Queued)`.
+//! * `Cancel(id: <n>)`: Stops the job, without writing its image.
+//!   Responds with `Cancelled(id: <n>)`.
+//! * `List`: Responds with `Jobs([<status>, ...])`.
+//!
+//! Invalid requests get `Error("<message>")`.
+
+use super::atomic::AtomicFile;
+use super::{cpus, parse_value, resolve_max_pixels};
+use plumage::{Generator, Params};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Options for `plumage daemon`.
+struct Options {
+    socket: PathBuf,
+    /// The number of jobs to render at once.
+    jobs: NonZeroUsize,
+    max_pixels: usize,
+}
+
+/// The default socket path: `$XDG_RUNTIME_DIR/plumage.sock`, or
+/// `plumage.sock` in the temporary directory.
+fn default_socket() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map_or_else(env::temp_dir, PathBuf::from)
+        .join("plumage.sock")
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut socket = None;
+    let mut jobs = NonZeroUsize::MIN;
+    let mut max_pixels = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--socket" => socket = Some(parse_value(&arg, args.next())),
+            "-j" | "--jobs" => jobs = parse_value(&arg, args.next()),
+            "--max-pixels" => {
+                max_pixels = Some(parse_value(&arg, args.next()));
+            }
+            _ if arg.starts_with('-') => {
+                args_error!("unrecognized option: {arg}");
+            }
+            _ => {
+                args_error!("unexpected argument: {arg}");
+            }
+        }
+    }
+    Options {
+        socket: socket.unwrap_or_else(default_socket),
+        jobs,
+        max_pixels: resolve_max_pixels(max_pixels),
+    }
+}
+
+#[derive(Deserialize)]
+enum Request {
+    Submit {
+        params: Box<Params>,
+        output: PathBuf,
+    },
+    Status {
+        id: u64,
+    },
+    Cancel {
+        id: u64,
+    },
+    List,
+}
+
+#[derive(Serialize)]
+enum Response {
+    Submitted {
+        id: u64,
+    },
+    Status(Status),
+    Cancelled {
+        id: u64,
+    },
+    Jobs(Vec<Status>),
+    Error(String),
+}
+
+/// The state of a job.
+#[derive(Clone, Serialize)]
+enum State {
+    Queued,
+    Running {
+        rows: usize,
+        total_rows: usize,
+    },
+    /// The image was written. The fingerprint is as printed by
+    /// `plumage --fingerprint`.
+    Done {
+        fingerprint: String,
+    },
+    Cancelled,
+    Failed(String),
+}
+
+/// The status of a job, as sent to clients.
+#[derive(Serialize)]
+struct Status {
+    id: u64,
+    output: PathBuf,
+    state: State,
+}
+
+struct Job {
+    output: PathBuf,
+    /// The state, except that the progress of running jobs is in `rows`.
+    state: State,
+    /// The number of rows filled so far.
+    rows: Arc<AtomicUsize>,
+    /// Set to cancel the job.
+    cancel: Arc<AtomicBool>,
+}
+
+impl Job {
+    fn status(&self, id: u64) -> Status {
+        let state = match self.state {
+            State::Running {
+                total_rows,
+                ..
+            } => State::Running {
+                rows: self.rows.load(Ordering::Relaxed),
+                total_rows,
+            },
+            ref state => state.clone(),
+        };
+        Status {
+            id,
+            output: self.output.clone(),
+            state,
+        }
+    }
+}
+
+/// A job waiting to be rendered.
+struct Queued {
+    id: u64,
+    params: Params,
+}
+
+struct Daemon {
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    queue: Sender<Queued>,
+    max_pixels: usize,
+}
+
+impl Daemon {
+    /// Sets the state of job `id`.
+    fn set_state(&self, id: u64, state: State) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.state = state;
+        }
+    }
+
+    fn submit(&self, params: Params, output: PathBuf) -> Response {
+        if let Err(e) = params.validate() {
+            return Response::Error(format!("invalid params: {e}"));
+        }
+        let dim = params.output_dimensions();
+        let max = self.max_pixels;
+        if dim.width.checked_mul(dim.height).is_none_or(|n| n > max) {
+            return Response::Error(format!(
+                "image is {}x{}, which is more than {max} pixels",
+                dim.width, dim.height,
+            ));
+        }
+        let mut jobs = self.jobs.lock().unwrap();
+        let id = jobs.last_key_value().map_or(1, |(id, _)| id + 1);
+        jobs.insert(
+            id,
+            Job {
+                output,
+                state: State::Queued,
+                rows: Arc::default(),
+                cancel: Arc::default(),
+            },
+        );
+        let queued = Queued {
+            id,
+            params,
+        };
+        // The workers run for as long as the daemon does.
+        let _ = self.queue.send(queued);
+        Response::Submitted {
+            id,
+        }
+    }
+
+    fn cancel(&self, id: u64) -> Response {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(job) = jobs.get_mut(&id) else {
+            return Response::Error(format!("no job with id {id}"));
+        };
+        match job.state {
+            State::Queued => job.state = State::Cancelled,
+            State::Running {
+                ..
+            } => job.cancel.store(true, Ordering::Relaxed),
+            _ => {
+                return Response::Error(format!("job {id} already finished"));
+            }
+        }
+        Response::Cancelled {
+            id,
+        }
+    }
+
+    fn handle(&self, request: Request) -> Response {
+        match request {
+            Request::Submit {
+                params,
+                output,
+            } => self.submit(*params, output),
+            Request::Status {
+                id,
+            } => match self.jobs.lock().unwrap().get(&id) {
+                Some(job) => Response::Status(job.status(id)),
+                None => Response::Error(format!("no job with id {id}")),
+            },
+            Request::Cancel {
+                id,
+            } => self.cancel(id),
+            Request::List => Response::Jobs(
+                self.jobs
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|(&id, job)| job.status(id))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Responds to each request sent over `stream` until it's closed.
+    fn serve(&self, stream: UnixStream) -> io::Result<()> {
+        let mut writer = BufWriter::new(stream.try_clone()?);
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match ron::from_str(&line) {
+                Ok(request) => self.handle(request),
+                Err(e) => Response::Error(format!("invalid request: {e}")),
+            };
+            let text = ron::to_string(&response).map_err(io::Error::other)?;
+            writeln!(writer, "{text}")?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Renders job `id` with `params`, returning its fingerprint, or
+    /// [`None`] if it was cancelled. Unless the params say otherwise, the
+    /// image is generated with `threads` threads.
+    fn render(
+        &self,
+        id: u64,
+        params: Params,
+        threads: NonZeroUsize,
+    ) -> io::Result<Option<String>> {
+        let (output, rows, cancel) = {
+            let jobs = self.jobs.lock().unwrap();
+            let Some(job) = jobs.get(&id) else {
+                return Ok(None);
+            };
+            (job.output.clone(), job.rows.clone(), job.cancel.clone())
+        };
+        let threads = params.threads.unwrap_or(threads);
+        let generator = Generator::new(params.clone())
+            .threads(threads)
+            .interrupt_flag(cancel.clone());
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            let Some(job) = jobs.get_mut(&id) else {
+                return Ok(None);
+            };
+            if !matches!(job.state, State::Queued) {
+                return Ok(None);
+            }
+            // Progress counts rows as they're filled, which are columns of
+            // the image when filling vertically.
+            job.state = State::Running {
+                rows: 0,
+                total_rows: generator.total_rows(),
+            };
+        }
+        let mut writer = BufWriter::new(AtomicFile::create(&output)?);
+        let (_, fingerprint) = generator
+            .generate_fingerprinted_with_progress(&mut writer, |p| {
+                rows.store(p.rows, Ordering::Relaxed);
+            })?;
+        if cancel.load(Ordering::Relaxed) {
+            // Dropping the file removes it.
+            return Ok(None);
+        }
+        writer.into_inner().map_err(|e| e.into_error())?.commit()?;
+        write_params(&output.with_extension("params"), &params)?;
+        Ok(Some(fingerprint.to_string()))
+    }
+
+    /// Renders queued jobs until the daemon exits.
+    fn work(&self, queue: &Mutex<Receiver<Queued>>, threads: NonZeroUsize) {
+        loop {
+            let Ok(Queued {
+                id,
+                params,
+            }) = queue.lock().unwrap().recv()
+            else {
+                return;
+            };
+            let state = match self.render(id, params, threads) {
+                Ok(Some(fingerprint)) => State::Done {
+                    fingerprint,
+                },
+                Ok(None) => State::Cancelled,
+                Err(e) => State::Failed(e.to_string()),
+            };
+            self.set_state(id, state);
+        }
+    }
+}
+
+/// Writes `params` to `path`, like the params written with images.
+fn write_params(path: &Path, params: &Params) -> io::Result<()> {
+    let mut writer = BufWriter::new(AtomicFile::create(path)?);
+    let pretty = PrettyConfig::new().depth_limit(1);
+    ron::ser::to_writer_pretty(&mut writer, params, pretty)
+        .map_err(io::Error::other)?;
+    writeln!(writer)?;
+    writer.into_inner().map_err(|e| e.into_error())?.commit()
+}
+
+/// Binds to the socket at `path`, replacing it if it's left over from a
+/// daemon that's no longer running.
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    if UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "a daemon is already running",
+        ));
+    }
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    UnixListener::bind(path)
+}
+
+/// Runs a daemon that renders jobs submitted over a Unix socket.
+pub fn daemon(args: impl Iterator<Item = String>) {
+    let options = parse_args(args);
+    let listener = bind(&options.socket).unwrap_or_else(|e| {
+        let path = options.socket.display();
+        error_exit!(Io, "could not listen on {path}: {e}");
+    });
+    eprintln!("listening on {}", options.socket.display());
+
+    let (sender, receiver) = mpsc::channel();
+    let daemon = Arc::new(Daemon {
+        jobs: Mutex::default(),
+        queue: sender,
+        max_pixels: options.max_pixels,
+    });
+    let receiver = Arc::new(Mutex::new(receiver));
+    let threads = cpus();
+    for _ in 0..options.jobs.get() {
+        let daemon = daemon.clone();
+        let receiver = receiver.clone();
+        thread::spawn(move || daemon.work(&receiver, threads));
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("error: could not accept connection: {e}");
+                continue;
+            }
+        };
+        let daemon = daemon.clone();
+        thread::spawn(move || {
+            if let Err(e) = daemon.serve(stream) {
+                eprintln!("error: connection failed: {e}");
+            }
+        });
+    }
+}
@@ -19,11 +19,16 @@
 
 use std::fmt::Display;
 use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-/// Displays an error message and exits.
+/// Displays an error message and exits with the status for the given
+/// [`Kind`] of error, like `error_exit!(Io, "could not write {path}")`.
 macro_rules! error_exit {
-    ($($args:tt)*) => {
-        crate::error::__exit(format_args!($($args)*))
+    ($kind:ident, $($args:tt)*) => {
+        crate::error::__exit(
+            crate::error::Kind::$kind,
+            format_args!($($args)*),
+        )
     };
 }
 
@@ -31,6 +36,7 @@ macro_rules! error_exit {
 macro_rules! args_error {
     ($($args:tt)*) => {
         error_exit!(
+            Usage,
             "{}\n{}",
             format_args!($($args)*),
             "See `plumage --help` for usage information.",
@@ -38,12 +44,50 @@ macro_rules! args_error {
     };
 }
 
+/// Displays a warning, or in strict mode, an error about invalid params.
+macro_rules! warning {
+    ($($args:tt)*) => {
+        crate::error::__warn(format_args!($($args)*))
+    };
+}
+
+/// The kinds of errors, which exit with different statuses so scripts can
+/// tell them apart.
+#[derive(Clone, Copy)]
+pub enum Kind {
+    /// Invalid command-line arguments or config.
+    Usage = 2,
+    /// Params that can't be read or are invalid.
+    Params = 3,
+    /// An error reading or writing a file other than params.
+    Io = 4,
+    /// An error while generating an image.
+    Generate = 5,
+}
+
+/// Whether warnings are errors, as with `--strict`.
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Makes [`warning`] exit with an error.
+pub fn set_strict() {
+    STRICT.store(true, Ordering::Relaxed);
+}
+
 #[doc(hidden)]
-pub fn __exit(args: impl Display) -> ! {
+pub fn __exit(kind: Kind, args: impl Display) -> ! {
     eprintln!("error: {args}");
     if cfg!(feature = "cli-panic") {
         panic!("error: {args}");
     } else {
-        exit(1);
+        crate::atomic::remove_pending();
+        exit(kind as i32);
+    }
+}
+
+#[doc(hidden)]
+pub fn __warn(args: impl Display) {
+    if STRICT.load(Ordering::Relaxed) {
+        __exit(Kind::Params, format_args!("{args} (in strict mode)"));
     }
+    eprintln!("warning: {args}");
 }
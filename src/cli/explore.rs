@@ -0,0 +1,45 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use plumage::ParamRanges;
+use std::fs;
+use std::path::Path;
+
+/// Reads the ranges for `plumage explore` from the RON file at `path`,
+/// like `(spread: (start: 2, end: 6), gamma: (start: 0.5, end: 0.8))`.
+/// Missing fields have their default ranges.
+pub fn load_ranges(path: &Path) -> ParamRanges {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        error_exit!(Io, "error reading {}: {e}", path.display());
+    });
+    let ranges: ParamRanges = ron::from_str(&text).unwrap_or_else(|e| {
+        error_exit!(Usage, "error reading {}: {e}", path.display());
+    });
+    let empty = [
+        ("spread", ranges.spread.is_empty()),
+        ("distance_power", ranges.distance_power.is_empty()),
+        ("random_power", ranges.random_power.is_empty()),
+        ("random_max", ranges.random_max.is_empty()),
+        ("gamma", ranges.gamma.is_empty()),
+    ];
+    if let Some((field, _)) = empty.iter().find(|(_, empty)| *empty) {
+        error_exit!(Usage, "{}: range for {field} is empty", path.display());
+    }
+    ranges
+}
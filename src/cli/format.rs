@@ -0,0 +1,122 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use plumage::Params;
+use ron::Value;
+#[cfg(feature = "yaml")]
+use serde_yaml::Mapping;
+use std::path::Path;
+
+/// The names of the formats params can be read from.
+pub const NAMES: &[&str] = &[
+    "ron",
+    #[cfg(feature = "yaml")]
+    "yaml",
+];
+
+/// The format of a params file.
+#[derive(Clone, Copy)]
+pub enum Format {
+    Ron,
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl Format {
+    /// The format of the params file at `path`: YAML if its extension is
+    /// `.yaml` or `.yml` (and the `yaml` feature is enabled), or RON
+    /// otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        #[cfg(feature = "yaml")]
+        if let Some(ext) = path.extension() {
+            if ext == "yaml" || ext == "yml" {
+                return Self::Yaml;
+            }
+        }
+        let _ = path;
+        Self::Ron
+    }
+
+    /// Deserializes params in this format.
+    pub fn parse(self, text: &str) -> Result<Params, String> {
+        match self {
+            Self::Ron => ron::de::from_str(text).map_err(|e| e.to_string()),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => {
+                // An empty YAML document is null, not an empty map.
+                let text = if text.trim().is_empty() { "{}" } else { text };
+                serde_yaml::from_str(text).map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// The `base` field in `text`, params in this format, if present.
+    pub fn base(self, text: &str) -> Option<String> {
+        match self {
+            Self::Ron => {
+                let Ok(Value::Map(fields)) = ron::de::from_str(text) else {
+                    return None;
+                };
+                fields.into_iter().find_map(|field| match field {
+                    (Value::String(key), Value::String(base))
+                        if key == "base" =>
+                    {
+                        Some(base)
+                    }
+                    _ => None,
+                })
+            }
+            #[cfg(feature = "yaml")]
+            Self::Yaml => {
+                let fields = serde_yaml::from_str::<Mapping>(text).ok()?;
+                fields.get("base")?.as_str().map(String::from)
+            }
+        }
+    }
+
+    /// The names of the fields given in `text`, params in this format, which
+    /// must already have been successfully deserialized.
+    pub fn given_fields(self, text: &str) -> Vec<String> {
+        match self {
+            Self::Ron => {
+                // `text` is either a map of fields or `()`.
+                let Ok(Value::Map(fields)) = ron::de::from_str(text) else {
+                    return Vec::new();
+                };
+                fields
+                    .keys()
+                    .filter_map(|key| match key {
+                        Value::String(name) => Some(name.clone()),
+                        _ => None,
+                    })
+                    .collect()
+            }
+            #[cfg(feature = "yaml")]
+            Self::Yaml => {
+                let Ok(fields) = serde_yaml::from_str::<Mapping>(text) else {
+                    return Vec::new();
+                };
+                fields
+                    .keys()
+                    .filter_map(|key| key.as_str().map(String::from))
+                    .collect()
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::atomic::AtomicFile;
+use super::thumbnail;
+use std::fmt::Write as _;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Escapes text for use in HTML.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&#39;",
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encodes a file name for use in a relative URL.
+fn encode_url(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for b in name.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' => {
+                encoded.push(b as char);
+            }
+            _ => {
+                let _ = write!(encoded, "%{b:02X}");
+            }
+        }
+    }
+    encoded
+}
+
+/// The file name of `path`, for linking to it from a file in the same
+/// directory.
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Writes `index.html` to the directory containing the images, which must
+/// all be in the same directory, showing a thumbnail of each image that
+/// links to it, and a link to its params file, if any. Thumbnails written
+/// with `--thumbnail` are shown instead of the full images when they exist.
+/// Returns the path of the written file.
+pub fn write(images: &[(PathBuf, Option<PathBuf>)]) -> io::Result<PathBuf> {
+    let dir = images
+        .first()
+        .and_then(|(image, _)| image.parent())
+        .unwrap_or(Path::new(""));
+    let path = dir.join("index.html");
+    let mut writer = BufWriter::new(AtomicFile::create(&path)?);
+    write!(
+        writer,
+        "\
+<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Plumage</title>
+<style>
+body {{ font-family: sans-serif; background: #222; color: #ddd; }}
+a {{ color: #9cf; }}
+.images {{ display: flex; flex-wrap: wrap; gap: 1em; }}
+figure {{ margin: 0; }}
+img {{ display: block; width: 320px; }}
+</style>
+</head>
+<body>
+<div class=\"images\">
+",
+    )?;
+    for (image, params) in images {
+        let name = file_name(image);
+        let thumbnail = thumbnail::path(image);
+        let src = if thumbnail.exists() {
+            file_name(&thumbnail)
+        } else {
+            name.clone()
+        };
+        write!(
+            writer,
+            "<figure>\n<a href=\"{url}\"><img src=\"{src}\" alt=\"{name}\" \
+             loading=\"lazy\"></a>\n<figcaption>{name}",
+            url = escape(&encode_url(&name)),
+            src = escape(&encode_url(&src)),
+            name = escape(&name),
+        )?;
+        if let Some(params) = params {
+            let name = file_name(params);
+            write!(
+                writer,
+                " (<a href=\"{}\">params</a>)",
+                escape(&encode_url(&name)),
+            )?;
+        }
+        writeln!(writer, "</figcaption>\n</figure>")?;
+    }
+    writeln!(writer, "</div>\n</body>\n</html>")?;
+    writer.into_inner().map_err(|e| e.into_error())?.commit()?;
+    Ok(path)
+}
@@ -0,0 +1,90 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use signal_hook::consts::SIGINT;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+/// Set when Ctrl-C is pressed.
+static FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// When generation must stop, set with `--max-duration`.
+static DEADLINE: OnceLock<Instant> = OnceLock::new();
+
+/// Makes Ctrl-C set the interrupt flag instead of exiting, so partial
+/// images can be written. Pressing Ctrl-C again exits immediately.
+pub fn install() {
+    let flag = flag();
+    // This must be registered first, so it sees the flag before the second
+    // handler sets it.
+    let result = signal_hook::flag::register_conditional_shutdown(
+        SIGINT,
+        130,
+        flag.clone(),
+    )
+    .and_then(|_| signal_hook::flag::register(SIGINT, flag));
+    if let Err(e) = result {
+        error_exit!(Io, "could not install Ctrl-C handler: {e}");
+    }
+}
+
+/// The interrupt flag, for passing to [`plumage::Generator`].
+pub fn flag() -> Arc<AtomicBool> {
+    FLAG.get_or_init(Default::default).clone()
+}
+
+/// Whether Ctrl-C has been pressed.
+pub fn interrupted() -> bool {
+    FLAG.get().is_some_and(|flag| flag.load(Ordering::Relaxed))
+}
+
+/// Stops generation once `deadline` has passed, as if Ctrl-C had been
+/// pressed.
+pub fn set_deadline(deadline: Instant) {
+    let _ = DEADLINE.set(deadline);
+}
+
+/// The deadline set with [`set_deadline`], for passing to
+/// [`plumage::Generator`].
+pub fn deadline() -> Option<Instant> {
+    DEADLINE.get().copied()
+}
+
+/// Whether the deadline set with [`set_deadline`] has passed.
+pub fn timed_out() -> bool {
+    deadline().is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// Why generation stopped early, if it did: because Ctrl-C was pressed, or
+/// because the deadline passed.
+pub fn reason() -> Option<&'static str> {
+    if interrupted() {
+        Some("interrupted")
+    } else if timed_out() {
+        Some("time limit exceeded")
+    } else {
+        None
+    }
+}
+
+/// Whether generation should stop early (see [`reason`]).
+pub fn stopped() -> bool {
+    reason().is_some()
+}
@@ -19,28 +19,307 @@
 
 #![deny(unsafe_op_in_unsafe_fn)]
 
-use plumage::{Generator, Params};
+use atomic::AtomicFile;
+use base::Layer;
+use config::Config;
+use format::Format;
+use mosaic::Mosaic;
+use preview::Preview;
+use progress::ProgressBar;
+use stats::Generated;
+use thumbnail::{Tee, Thumbnail};
+#[cfg(feature = "tui")]
+use tune::tune;
+use wallpaper::Wallpaper;
+use plumage::{
+    Dimensions, Fingerprint, Float, Generator, ParamRanges, Params,
+    ParamsError, Seed, Timings,
+};
 use ron::ser::PrettyConfig;
 use std::env;
-use std::fmt::Display;
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Write};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 
 const USAGE: &str = "\
-Usage: plumage <name>
+Usage: plumage [options] <name>
+       plumage [options] -o <path>
+       plumage init [<path>]
 
 Creates `<name>.bmp` and `<name>.params`.
-Optionally reads params from `./params`.
+Optionally reads params from `$XDG_CONFIG_HOME/plumage/params` (or
+`~/.config/plumage/params`), or if that doesn't exist, `./params`.
+`<name>` is relative to `output_dir`, if set in `plumage/config` in the
+same directory, like `(output_dir: \"/home/user/images\")`.
+
+Commands:
+  init [<path>]    Write a params file with every field filled in to
+                   <path> [default: `./params`]
+  info <path>...   Print a summary of each params file, with defaults
+                   filled in
+  bench [--runs <n>] [--size <s>]... [--spread <n>]... [-j <n>] <path>
+                   Generate the params in <path> <n> times [default: 3]
+                   with each combination of the given sizes and spread
+                   sizes, and print how long each stage took
+  animate [--frames <n>] [--max-pixels <n>] [-j <n>] [-q] <from> <to>
+          <name>
+                   Generate <n> frames [default: 60] named `<name>-0001.bmp`,
+                   etc., interpolating the params from <from> to <to>, and
+                   list each frame's params in `<name>.frames`. All frames
+                   use the seed from <from>. If <name> is `-`, the frames
+                   are written to standard output one after another.
+  render [--scale <n>] [-o <path>] [--fingerprint] [-v] [-q] <path>
+                   Render the params file at <path>, like a `.params` file
+                   written with an image, to the same path with the
+                   extension changed to `.bmp`, or to the path given with
+                   -o. The size and spread are multiplied by <n>, if
+                   given. Fails if the seed or start color are missing.
+  preview [--scale <n>] [--open] [--print] [options] <name>
+                   Quickly generate a smaller image, dividing the size and
+                   spread by <n> [default: 4], but write the full-size
+                   params. --open opens the image, and --print prints it
+                   to the terminal.
+  explore [--ranges <path>] [--scale <n>] [options] <name>
+                   Like `preview`, but sample the spread, distance_power,
+                   random_power, random_max, and gamma from ranges, so
+                   new looks can be found with --count. The ranges are
+                   read from <path> in RON, like `(gamma: (start: 0.5,
+                   end: 0.8))`, with defaults for missing fields. Fields
+                   in the params override the sampled ones.
+  mutate [--rate <r>] [options] <base> <name>
+                   Generate images whose params are small random changes
+                   to the params in <base>: each numeric field is scaled
+                   by up to <r> [default: 0.1] either way, and the spread
+                   size changes by one with probability <r>. The seed and
+                   start color are kept, so they must be in <base>. Use
+                   --count to generate several.
+  wallpaper [--dir <dir>] [--keep <k>] [--interval <i>] [options]
+                   Generate a wallpaper named `plumage-<time>.bmp` in
+                   <dir> [default: `wallpaper_dir` in the config], and
+                   delete all but the newest <k> [default: 10, or
+                   `wallpaper_keep` in the config]. With --interval,
+                   generate a new wallpaper every <i>, like `30m` or `1h`,
+                   until stopped with Ctrl-C.
+  check [--strict] <path>...
+                   Check that each params file is valid, and exit with an
+                   error if any aren't
+  selftest         Check that this build generates the same images as
+                   every other build, by generating a few small images
+                   and comparing their fingerprints to known answers
+  tune [options] <name>
+                   Adjust the params with a preview in the terminal, if
+                   Plumage was built with the `tui` feature, then generate
+                   the image at full size
+  daemon [--socket <path>] [-j <n>] [--max-pixels <n>]
+                   Render images submitted over a Unix socket at <path>
+                   [default: `$XDG_RUNTIME_DIR/plumage.sock`], up to <n>
+                   [default: 1] at once. Each request is a line of RON,
+                   like `Submit(params: (...), output: \"/tmp/a.bmp\")`,
+                   `Status(id: 1)`, `Cancel(id: 1)`, or `List`, and gets
+                   a line of RON in response.
+
+To create an image named after a command, use `-o`, like
+`plumage -o init.bmp`.
+
+Params files ending in `.yaml` or `.yml` are read as YAML, if Plumage was
+built with the `yaml` feature.
+
+Options:
+  --params <path>  Read params from <path> instead of the default, or from
+                   standard input if <path> is `-`
+  --base <path>    Use the params in <path> as a base, which the params
+                   override. Params can also name their base with a
+                   `base` field, relative to their own directory.
+  -o, --output <path>
+                   Write the image to <path> instead, and the params next
+                   to it, with the extension changed to `.params`
+  --no-params      Don't write the params file
+  --draft <n>      First write a draft scaled down by a factor of <n>, with
+                   the extension `.draft.bmp`, so a bad image can be
+                   stopped early, then the full image with the same seed
+  -n, --count <n>  Generate <n> images named `<name>1`, `<name>2`, etc.,
+                   each with a different random seed (unless the seed is
+                   specified in the params)
+  --master-seed <s>
+                   With --count, derive the seed of image <i> from the 64
+                   hex digits <s> and <i>, instead of choosing it at
+                   random, so the whole batch can be reproduced from <s>.
+                   The master seed and <i> are noted in each params file
+  --gallery        With --count, also write `index.html` next to the
+                   images, showing all of them with links to their params
+  --thumbnail <n>  Also write a copy of each image scaled down to fit in
+                   <n> by <n> pixels, with the extension `.thumb.bmp`
+  --mosaic <c>x<r> Also write each image averaged into <c> columns and <r>
+                   rows of cells, as an SVG with the extension `.svg`
+  --mosaic-blur    Blur the cells of --mosaic into smooth gradients
+  --window         Show the image in a window as it's generated, if
+                   Plumage was built with the `window` feature. Closing
+                   the window stops early, like Ctrl-C.
+  --pass <path>    Apply the pass in the dynamic library at <path> after
+                   gamma correction, if Plumage was built with the
+                   `plugins` feature. Can be given more than once. Passes
+                   aren't recorded in the params file.
+  -j, --jobs <n>   Generate up to <n> images at once [default: number of
+                   CPUs]
+  -s, --size <s>   Set the image size, like `3840x2160` or `4k`,
+                   overriding the params
+  -p, --preset <p> Start from a built-in preset: `classic`, `fine-silk`,
+                   `storm`, or `pastel-drift`. Fields in the params
+                   override the preset.
+  -v, --verbose    Print the resolved params, output paths, and how long
+                   each stage took
+  -q, --quiet      Don't show progress or the names of generated images
+  --fingerprint    Print a hash of each image's pixels, which is the same
+                   whenever the params are
+  --stats <path>   Write a JSON report to <path> describing each image: its
+                   params, how long each stage took, the sizes of the
+                   output files, and its fingerprint
+  --count-rng      Count the random numbers drawn while filling each image
+                   and include the count in --stats and -v, to check that
+                   a change doesn't shift the random stream. Fills on one
+                   thread
+  --log <path>     Append a line to <path> for each image, like
+                   `plumage.log.jsonl`, with the time, seed, a hash of the
+                   params, and the output paths [default: `log` in the
+                   config]
+  --max-pixels <n> Refuse to generate images with more than <n> pixels
+                   [default: 268435456, or `max_pixels` in the config]
+  --max-duration <d>
+                   Stop once <d> has passed, like `90s`, `10m`, or `1h`,
+                   writing any partial images and previews as if Ctrl-C
+                   were pressed, and exit with status 124. No more images
+                   are started.
+  --dry-run        Print the params after applying bases, presets, and
+                   options like --size, with the image size and memory
+                   needed, then exit without generating anything.
+                   <name> may be omitted. Random fields show one choice
+  --strict         Fail instead of choosing a random start color or seed,
+                   so the params fully determine the image, and fail on
+                   warnings, like unknown fields in the params
+  --schema         Print a JSON Schema describing the params and exit
+  --list-formats   Print the output and params formats, spread shapes,
+                   presets, and features this build supports, and exit
+
+Exit status:
+  0                Success
+  2                Invalid arguments or config
+  3                Invalid params, or params that can't be read
+  4                Error reading or writing another file
+  5                Error generating an image
+  124              Stopped by --max-duration
+  130              Interrupted with Ctrl-C
 ";
 
+/// The default for `--max-pixels`, which is 16384 by 16384. Larger images
+/// are usually typos, and can use enough memory to bring down the system.
+const DEFAULT_MAX_PIXELS: usize = 1 << 28;
+
 #[macro_use]
 mod error;
 
-fn deserialize_params<R: Read>(stream: R) -> Params {
-    ron::de::from_reader(stream).unwrap_or_else(|e| {
-        error_exit!("error reading params: {e}");
-    })
+mod animate;
+// Shared with the GUI.
+#[path = "../shared/atomic.rs"]
+mod atomic;
+mod base;
+mod bench;
+mod commands;
+mod config;
+#[cfg(unix)]
+mod daemon;
+mod explore;
+mod format;
+mod gallery;
+mod interrupt;
+mod mosaic;
+#[cfg(feature = "plugins")]
+mod plugin;
+mod preview;
+mod progress;
+mod stats;
+mod thumbnail;
+#[cfg(feature = "tui")]
+mod tune;
+mod wallpaper;
+#[cfg(feature = "window")]
+mod window;
+
+/// Where to write the output files.
+enum Output {
+    /// Write `<name>.bmp` and `<name>.params`.
+    Name(String),
+    /// Write the image to the given path.
+    Path(PathBuf),
+}
+
+/// The command that images are being generated for.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Mode {
+    Generate,
+    Preview,
+    Explore,
+    Mutate,
+    Wallpaper,
+    Tune,
+}
+
+/// How much to print.
+#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+struct Args {
+    params: Option<PathBuf>,
+    base: Option<PathBuf>,
+    output: Output,
+    output_dir: Option<PathBuf>,
+    max_pixels: usize,
+    /// How long generation may take before it's stopped.
+    max_duration: Option<Duration>,
+    no_params: bool,
+    count: Option<usize>,
+    /// The seed from which the seed of each image in a batch is derived.
+    master_seed: Option<Seed>,
+    jobs: Option<NonZeroUsize>,
+    size: Option<Dimensions>,
+    preset: Option<String>,
+    strict: bool,
+    fingerprint: bool,
+    stats: Option<PathBuf>,
+    /// Whether to count the random numbers drawn while filling.
+    count_rng: bool,
+    /// The JSON Lines file to which each image is logged.
+    log: Option<PathBuf>,
+    gallery: bool,
+    /// Scales down the draft written before each image.
+    draft: Option<NonZeroUsize>,
+    /// The maximum width and height of thumbnails.
+    thumbnail: Option<NonZeroUsize>,
+    mosaic: Option<Mosaic>,
+    /// Whether to show the image in a window as it's generated.
+    window: bool,
+    verbosity: Verbosity,
+    preview: Option<Preview>,
+    /// The ranges from which params are sampled for `plumage explore`.
+    ranges: Option<ParamRanges>,
+    /// How much params are changed by for `plumage mutate`.
+    mutate: Option<Float>,
+    /// Whether to adjust the params in a terminal UI first.
+    tune: bool,
+    /// Whether to print the resolved params instead of generating.
+    dry_run: bool,
+    wallpaper: Option<Wallpaper>,
+    /// Multiplies the image size and spread.
+    scale: Option<NonZeroUsize>,
 }
 
 fn usage() {
@@ -48,55 +327,986 @@ fn usage() {
     exit(0);
 }
 
-fn params_write_failed<T>(e: impl Display) -> T {
-    error_exit!("could not write to output params file: {e}");
+fn schema() {
+    print!("{}", Params::json_schema());
+    exit(0);
 }
 
-fn main() {
-    let mut args = env::args().skip(1);
-    let Some(mut name) = args.next() else {
-        args_error!("missing <name>");
+fn list_formats() {
+    let caps = plumage::capabilities();
+    let presets: Vec<_> = Params::preset_names().collect();
+    println!("version: {}", caps.version);
+    println!("params version: {}", caps.params_version);
+    println!("output formats: {}", caps.formats.join(", "));
+    println!("params formats: {}", format::NAMES.join(", "));
+    println!("spreads: {}", caps.spreads.join(", "));
+    println!("presets: {}", presets.join(", "));
+    println!("passes: {}", caps.passes.join(", "));
+    println!("features: {}", caps.features.join(", "));
+    exit(0);
+}
+
+fn parse_value<T: std::str::FromStr>(opt: &str, value: Option<String>) -> T {
+    let Some(value) = value else {
+        args_error!("missing value for {opt}");
+    };
+    value.parse().unwrap_or_else(|_| {
+        args_error!("invalid value for {opt}: {value}");
+    })
+}
+
+/// Parses the options in `args`, including those specific to `mode`.
+fn parse_args(mut args: impl Iterator<Item = String>, mode: Mode) -> Args {
+    let preview = matches!(mode, Mode::Preview | Mode::Explore);
+    let explore = mode == Mode::Explore;
+    let mutate = mode == Mode::Mutate;
+    let wallpaper = mode == Mode::Wallpaper;
+    let tune = mode == Mode::Tune;
+    let mut name = None;
+    let mut output = None;
+    let mut params = None;
+    let mut base = None;
+    let mut max_pixels = None;
+    let mut max_duration = None;
+    let mut no_params = false;
+    let mut count = None;
+    let mut master_seed = None;
+    let mut jobs = None;
+    let mut size = None;
+    let mut preset = None;
+    let mut strict = false;
+    let mut dry_run = false;
+    let mut fingerprint = false;
+    let mut stats = None;
+    let mut count_rng = false;
+    let mut log = None;
+    let mut gallery = false;
+    let mut thumbnail = None;
+    let mut mosaic = None;
+    let mut mosaic_blur = false;
+    let mut draft = None;
+    let mut window = false;
+    let mut passes: Vec<PathBuf> = Vec::new();
+    let mut verbosity = Verbosity::Normal;
+    let mut scale = NonZeroUsize::new(4).unwrap();
+    let mut open = false;
+    let mut print = false;
+    let mut ranges = None;
+    let mut rate = 0.1;
+    let mut dir = None;
+    let mut keep = None;
+    let mut interval = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => usage(),
+            "--schema" => schema(),
+            "--list-formats" => list_formats(),
+            "-o" | "--output" => output = Some(parse_value(&arg, args.next())),
+            "--no-params" => no_params = true,
+            "--params" => params = Some(parse_value(&arg, args.next())),
+            "--base" => base = Some(parse_value(&arg, args.next())),
+            "--max-pixels" => {
+                max_pixels = Some(parse_value(&arg, args.next()));
+            }
+            "--max-duration" => {
+                max_duration = parse_duration(&arg, args.next());
+            }
+            "--strict" => strict = true,
+            "--dry-run" => dry_run = true,
+            "--fingerprint" => fingerprint = true,
+            "--stats" => stats = Some(parse_value(&arg, args.next())),
+            "--count-rng" => count_rng = true,
+            "--log" => log = Some(parse_value(&arg, args.next())),
+            "--gallery" => gallery = true,
+            "--thumbnail" => {
+                thumbnail = Some(parse_value(&arg, args.next()));
+            }
+            "--mosaic" => mosaic = Some(parse_value(&arg, args.next())),
+            "--mosaic-blur" => mosaic_blur = true,
+            "--window" if cfg!(feature = "window") => window = true,
+            "--pass" if cfg!(feature = "plugins") => {
+                passes.push(parse_value(&arg, args.next()));
+            }
+            "-v" | "--verbose" => verbosity = Verbosity::Verbose,
+            "-q" | "--quiet" => verbosity = Verbosity::Quiet,
+            "-n" | "--count" => count = Some(parse_value(&arg, args.next())),
+            "--master-seed" => {
+                let value: String = parse_value(&arg, args.next());
+                master_seed = plumage::seed_from_hex(&value);
+                if master_seed.is_none() {
+                    args_error!("--master-seed must be 64 hex digits");
+                }
+            }
+            "-j" | "--jobs" => jobs = Some(parse_value(&arg, args.next())),
+            "-s" | "--size" => size = Some(parse_value(&arg, args.next())),
+            "-p" | "--preset" => {
+                let name: String = parse_value(&arg, args.next());
+                if Params::preset(&name).is_none() {
+                    args_error!("unknown preset: {name}");
+                }
+                preset = Some(name);
+            }
+            "--draft" if !preview => {
+                draft = Some(parse_value(&arg, args.next()));
+            }
+            "--scale" if preview => scale = parse_value(&arg, args.next()),
+            "--open" if preview => open = true,
+            "--print" if preview => print = true,
+            "--ranges" if explore => {
+                let path: PathBuf = parse_value(&arg, args.next());
+                ranges = Some(explore::load_ranges(&path));
+            }
+            "--dir" if wallpaper => dir = Some(parse_value(&arg, args.next())),
+            "--keep" if wallpaper => {
+                keep = Some(parse_value(&arg, args.next()));
+            }
+            "--interval" if wallpaper => {
+                interval = parse_duration(&arg, args.next());
+            }
+            "--rate" if mutate => {
+                rate = parse_value(&arg, args.next());
+                if !(0.0..=1.0).contains(&rate) {
+                    args_error!("--rate must be between 0 and 1");
+                }
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                args_error!("unrecognized option: {arg}");
+            }
+            _ if mutate && params.is_none() => params = Some(arg.into()),
+            _ if name.is_none() => name = Some(arg),
+            _ => {
+                args_error!("unexpected argument: {arg}");
+            }
+        }
+    }
+    if mutate && params.is_none() {
+        args_error!("missing <base>");
+    }
+    if mosaic_blur && mosaic.is_none() {
+        args_error!("--mosaic-blur requires --mosaic");
+    }
+    if strict {
+        error::set_strict();
+    }
+    #[cfg(feature = "plugins")]
+    plugin::load(&passes);
+    let config = Config::load();
+    let mut output_dir = config.output_dir;
+    let output = match (name, output) {
+        _ if wallpaper && count.is_some() => {
+            args_error!("--count can't be used with `plumage wallpaper`");
+        }
+        (None, None) if wallpaper => {
+            let Some(dir) = dir.or(config.wallpaper_dir) else {
+                args_error!(
+                    "missing --dir, and `wallpaper_dir` isn't set in the \
+                     config"
+                );
+            };
+            fs::create_dir_all(&dir).unwrap_or_else(|e| {
+                error_exit!(Io, "could not create {}: {e}", dir.display());
+            });
+            output_dir = Some(dir);
+            Output::Name(wallpaper::PREFIX.into())
+        }
+        (Some(_), _) | (_, Some(_)) if wallpaper => {
+            args_error!(
+                "<name> and --output can't be used with `plumage wallpaper`"
+            );
+        }
+        // Nothing is written.
+        (None, None) if dry_run => Output::Name(String::new()),
+        (None, None) => {
+            args_error!("missing <name>");
+        }
+        (Some(_), Some(_)) => {
+            args_error!("<name> can't be used with --output");
+        }
+        (Some(name), None) => Output::Name(name),
+        (None, Some(path)) => Output::Path(path),
     };
-    if let Some(arg) = args.next() {
-        args_error!("unexpected argument: {arg}");
+    if gallery && count.is_none() {
+        args_error!("--gallery can only be used with --count");
     }
-    let name_len = name.len();
-    if name == "-h" || name == "--help" {
-        usage();
+    if master_seed.is_some() && count.is_none() {
+        args_error!("--master-seed can only be used with --count");
     }
+    if count.is_some() && (open || print) {
+        args_error!("--open and --print can't be used with --count");
+    }
+    if count.is_some() && window {
+        args_error!("--window can't be used with --count");
+    }
+    if count.is_some() && tune {
+        args_error!("--count can't be used with `plumage tune`");
+    }
+    let preview = preview.then_some(Preview {
+        scale,
+        open,
+        print,
+    });
+    Args {
+        params,
+        base,
+        output,
+        output_dir,
+        max_pixels: resolve_max_pixels(max_pixels),
+        max_duration,
+        no_params,
+        count,
+        master_seed,
+        jobs,
+        size,
+        preset,
+        // Mutations keep the seed and start color of the base.
+        strict: strict || mutate,
+        fingerprint,
+        stats,
+        count_rng,
+        log: log.or(config.log),
+        gallery,
+        draft,
+        thumbnail,
+        mosaic: mosaic.map(|cells| Mosaic {
+            cells,
+            blur: mosaic_blur,
+        }),
+        window,
+        verbosity,
+        preview,
+        ranges: explore.then(|| ranges.unwrap_or_default()),
+        mutate: mutate.then_some(rate),
+        tune,
+        dry_run,
+        wallpaper: wallpaper.then(|| Wallpaper {
+            keep: keep
+                .or(config.wallpaper_keep)
+                .unwrap_or(NonZeroUsize::new(10).unwrap()),
+            interval,
+        }),
+        scale: None,
+    }
+}
 
-    // Read input params.
-    let params = if let Ok(f) = File::open("params") {
-        deserialize_params(BufReader::new(f))
-    } else {
-        deserialize_params("()".as_bytes())
+/// Parses the options for `plumage render`, which renders an existing
+/// params file as it was originally rendered.
+fn parse_render_args(mut args: impl Iterator<Item = String>) -> Args {
+    let mut params = None;
+    let mut output = None;
+    let mut scale = None;
+    let mut fingerprint = false;
+    let mut stats = None;
+    let mut count_rng = false;
+    let mut log = None;
+    let mut max_pixels = None;
+    let mut max_duration = None;
+    let mut verbosity = Verbosity::Normal;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(parse_value(&arg, args.next())),
+            "--scale" => scale = Some(parse_value(&arg, args.next())),
+            "--fingerprint" => fingerprint = true,
+            "--stats" => stats = Some(parse_value(&arg, args.next())),
+            "--count-rng" => count_rng = true,
+            "--log" => log = Some(parse_value(&arg, args.next())),
+            "--max-pixels" => {
+                max_pixels = Some(parse_value(&arg, args.next()));
+            }
+            "--max-duration" => {
+                max_duration = parse_duration(&arg, args.next());
+            }
+            "-v" | "--verbose" => verbosity = Verbosity::Verbose,
+            "-q" | "--quiet" => verbosity = Verbosity::Quiet,
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                args_error!("unrecognized option: {arg}");
+            }
+            _ if params.is_none() => params = Some(PathBuf::from(arg)),
+            _ => {
+                args_error!("unexpected argument: {arg}");
+            }
+        }
+    }
+    let Some(params) = params else {
+        args_error!("missing params file");
     };
+    let output = output.unwrap_or_else(|| params.with_extension("bmp"));
+    Args {
+        params: Some(params),
+        base: None,
+        output: Output::Path(output),
+        output_dir: None,
+        max_pixels: resolve_max_pixels(max_pixels),
+        max_duration,
+        no_params: true,
+        count: None,
+        master_seed: None,
+        jobs: None,
+        size: None,
+        preset: None,
+        // The image must be the same as when the params were written.
+        strict: true,
+        fingerprint,
+        stats,
+        count_rng,
+        log: log.or(Config::load().log),
+        gallery: false,
+        draft: None,
+        thumbnail: None,
+        mosaic: None,
+        window: false,
+        verbosity,
+        preview: None,
+        ranges: None,
+        mutate: None,
+        tune: false,
+        dry_run: false,
+        wallpaper: None,
+        scale,
+    }
+}
 
-    // Create output params file.
-    name.replace_range(name_len.., ".params");
-    let file = File::create(&name).unwrap_or_else(|e| {
-        error_exit!("could not create output params file: {e}");
+/// Parses the value of an option that takes a duration, like `90s`, `10m`,
+/// or `1h`.
+fn parse_duration(arg: &str, value: Option<String>) -> Option<Duration> {
+    let value: String = parse_value(arg, value);
+    let duration = wallpaper::parse_interval(&value);
+    if duration.is_none() {
+        args_error!("invalid value for {arg}: {value}");
+    }
+    duration
+}
+
+/// Derives the seed of image `index` of a batch from `master`, by hashing
+/// `master` followed by `index` as a little-endian 64-bit integer.
+fn derive_seed(master: &Seed, index: usize) -> Seed {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(master);
+    hasher.update(&(index as u64).to_le_bytes());
+    hasher.finalize().into()
+}
+
+/// Deserializes the params and their bases, applying any overrides from
+/// `args`. `index` is the number of the image in a batch, from which its
+/// seed is derived if `args` has a master seed and the params don't give
+/// a seed.
+fn deserialize_params(
+    layers: &[Layer],
+    args: &Args,
+    index: Option<usize>,
+) -> Params {
+    let (mut params, mut fields) = base::resolve(layers).unwrap_or_else(|e| {
+        error_exit!(Params, "error reading params: {e}");
     });
-    let mut writer = BufWriter::new(file);
-    let pretty = PrettyConfig::new().depth_limit(1);
-    ron::ser::to_writer_pretty(&mut writer, &params, pretty)
-        .unwrap_or_else(params_write_failed);
-    writeln!(writer)
-        .and_then(|_| writer.flush())
-        .unwrap_or_else(params_write_failed);
-    drop(writer);
+    if let (Some(master), Some(index)) = (&args.master_seed, index) {
+        if !fields.iter().any(|f| f == "seed") {
+            params.seed = derive_seed(master, index);
+            // The seed is determined, even with `--strict`.
+            fields.push("seed".into());
+        }
+    }
+    if args.strict {
+        for field in ["start_color", "seed"] {
+            if !fields.iter().any(|f| f == field) {
+                let e = ParamsError::Unspecified(field);
+                error_exit!(Params, "invalid params: {e}");
+            }
+        }
+    }
+    if let Some(name) = &args.preset {
+        // The preset exists, as it was checked in `parse_args`.
+        let preset = Params::preset(name).unwrap().build();
+        apply_defaults(&mut params, preset, &fields);
+    }
+    if let Some(ranges) = &args.ranges {
+        let sampled = Params::randomize(ranges, rand::thread_rng());
+        apply_defaults(&mut params, sampled, &fields);
+    }
+    if let Some(rate) = args.mutate {
+        params = params.mutate(rate, rand::thread_rng());
+    }
+    if let Some(size) = args.size {
+        params.dimensions = size;
+    }
+    if let Some(scale) = args.scale {
+        params = params.rescale(|n| n.saturating_mul(scale.get()));
+    }
+    if let Err(e) = params.validate() {
+        error_exit!(Params, "invalid params: {e}");
+    }
+    // Repeats, borders, and expanding rotations make the image larger than
+    // its dimensions.
+    let dimensions = match &args.preview {
+        Some(preview) => preview.apply(params.clone()).output_dimensions(),
+        None => params.output_dimensions(),
+    };
+    check_pixels(dimensions, args.max_pixels);
+    params
+}
+
+/// The maximum number of pixels given with `--max-pixels`, or `max_pixels`
+/// in the config, or the default.
+fn resolve_max_pixels(option: Option<usize>) -> usize {
+    option.or_else(|| Config::load().max_pixels).unwrap_or(DEFAULT_MAX_PIXELS)
+}
+
+/// Exits with an error if an image with dimensions `dim` would have more
+/// than `max` pixels.
+fn check_pixels(dim: Dimensions, max: usize) {
+    if dim.width.checked_mul(dim.height).is_some_and(|n| n <= max) {
+        return;
+    }
+    error_exit!(
+        Params,
+        "image is {}x{}, which is more than {max} pixels\n\
+        Use --max-pixels to allow larger images.",
+        dim.width,
+        dim.height,
+    );
+}
+
+/// Sets the fields of `params` that presets and `plumage explore` choose
+/// to those in `preset`, unless they're in `fields`, the fields given in
+/// the params file.
+fn apply_defaults(params: &mut Params, preset: Params, fields: &[String]) {
+    let has = |field: &str| fields.iter().any(|f| f == field);
+    if !has("spread") {
+        params.spread = preset.spread;
+    }
+    if !has("distance_power") {
+        params.distance_power = preset.distance_power;
+    }
+    if !has("random_power") {
+        params.random_power = preset.random_power;
+    }
+    if !has("random_max") {
+        params.random_max = preset.random_max;
+    }
+    if !has("gamma") {
+        params.gamma = preset.gamma;
+    }
+}
+
+/// Without the `tui` feature, `plumage tune` isn't accepted.
+#[cfg(not(feature = "tui"))]
+fn tune(_: Params) -> Params {
+    unreachable!("built without the `tui` feature");
+}
+
+/// The number of CPUs, or 1 if it can't be determined.
+fn cpus() -> NonZeroUsize {
+    thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// The paths of the output image and params files. `suffix` is appended to
+/// the name of each file, before the extension.
+fn output_paths(args: &Args, suffix: &str) -> (PathBuf, Option<PathBuf>) {
+    let image = match &args.output {
+        Output::Name(name) => {
+            let file = format!("{name}{suffix}.bmp");
+            match &args.output_dir {
+                Some(dir) => dir.join(file),
+                None => PathBuf::from(file),
+            }
+        }
+        Output::Path(path) => {
+            let mut file = path.file_stem().unwrap_or_default().to_owned();
+            file.push(suffix);
+            if let Some(ext) = path.extension() {
+                file.push(".");
+                file.push(ext);
+            }
+            path.with_file_name(file)
+        }
+    };
+    let params = (!args.no_params).then(|| image.with_extension("params"));
+    (image, params)
+}
+
+/// If `window` is true, fills the image being generated by `generator`
+/// while showing it in a window, and returns how long filling took. The
+/// image is shown with `gamma` applied.
+fn watch(
+    window: bool,
+    generator: &mut Generator,
+    gamma: Float,
+    progress: bool,
+) -> Duration {
+    #[cfg(feature = "window")]
+    if window {
+        return window::watch(generator, gamma, progress);
+    }
+    let _ = (window, generator, gamma, progress);
+    Duration::ZERO
+}
+
+/// Options for generating a single image with [`generate`].
+struct ImageOptions {
+    /// The number of threads to use, unless the params say otherwise.
+    threads: NonZeroUsize,
+    /// Whether to show a progress bar.
+    progress: bool,
+    /// The maximum width and height of the thumbnail, if one is written.
+    thumbnail: Option<NonZeroUsize>,
+    mosaic: Option<Mosaic>,
+    /// Whether to show the image in a window as it's filled.
+    window: bool,
+    /// Whether to count the random numbers drawn in the timings.
+    count_rng: bool,
+}
+
+/// Writes the image to `image`, along with any thumbnail or mosaic in
+/// `options`, returning how long each stage took and the fingerprint of
+/// the image.
+fn generate(
+    image: &Path,
+    params: Params,
+    options: ImageOptions,
+) -> (Timings, Fingerprint) {
+    let ImageOptions {
+        threads,
+        progress,
+        thumbnail,
+        mosaic,
+        window,
+        count_rng,
+    } = options;
 
     // Create image.
-    name.replace_range(name_len.., ".bmp");
-    let generator = Generator::new(params);
-    let file = File::create(name).unwrap_or_else(|e| {
-        error_exit!("could not create output file: {e}");
+    let threads = params.threads.unwrap_or(threads);
+    let dimensions = params.output_dimensions();
+    let mut thumbnail =
+        thumbnail.map(|size| Thumbnail::new(dimensions, size.get()));
+    let mut mosaic_grid = mosaic.map(|mosaic| mosaic.grid(dimensions));
+    let gamma = params.gamma;
+    let generator = Generator::new(params)
+        .threads(threads)
+        .interrupt_flag(interrupt::flag());
+    let generator = match interrupt::deadline() {
+        Some(deadline) => generator.deadline(deadline),
+        None => generator,
+    };
+    let generator = if count_rng {
+        generator.count_rng_draws()
+    } else {
+        generator
+    };
+    #[cfg(feature = "plugins")]
+    let generator = plugin::attach(generator);
+    let mut generator = generator;
+    let file = AtomicFile::create(image).unwrap_or_else(|e| {
+        error_exit!(Io, "could not create output file: {e}");
     });
-    let mut writer = BufWriter::new(file);
-    generator
-        .generate(&mut writer)
-        .and_then(|_| writer.flush())
+    let fill = watch(window, &mut generator, gamma, progress);
+    let thumbnails = thumbnail.iter_mut().chain(&mut mosaic_grid).collect();
+    let mut result = write_image(file, generator, progress, thumbnails)
         .unwrap_or_else(|e| {
-            error_exit!("error generating image: {e}");
+            error_exit!(Generate, "error generating image: {e}");
+        });
+    result.0.fill += fill;
+
+    // Create thumbnail.
+    if let Some(thumbnail) = thumbnail {
+        let path = thumbnail::path(image);
+        let write = || {
+            let mut writer = BufWriter::new(AtomicFile::create(&path)?);
+            thumbnail.write(&mut writer)?;
+            writer.into_inner().map_err(|e| e.into_error())?.commit()
+        };
+        write().unwrap_or_else(|e: io::Error| {
+            error_exit!(Io, "could not write {}: {e}", path.display());
         });
+    }
+
+    // Create mosaic.
+    if let (Some(mosaic), Some(grid)) = (mosaic, mosaic_grid) {
+        let path = mosaic::path(image);
+        let write = || {
+            let mut writer = BufWriter::new(AtomicFile::create(&path)?);
+            mosaic.write(&grid, dimensions, &mut writer)?;
+            writer.into_inner().map_err(|e| e.into_error())?.commit()
+        };
+        write().unwrap_or_else(|e: io::Error| {
+            error_exit!(Io, "could not write {}: {e}", path.display());
+        });
+    }
+    result
+}
+
+fn write_image(
+    file: AtomicFile,
+    generator: Generator,
+    progress: bool,
+    thumbnails: Vec<&mut Thumbnail>,
+) -> io::Result<(Timings, Fingerprint)> {
+    let mut writer = BufWriter::new(file);
+    let mut bar = ProgressBar::new(progress);
+    let result = generator.generate_fingerprinted_with_progress(
+        Tee {
+            inner: &mut writer,
+            thumbnails,
+        },
+        |p| bar.update(p),
+    );
+    bar.finish();
+    let result = result?;
+    writer.into_inner().map_err(|e| e.into_error())?.commit()?;
+    Ok(result)
+}
+
+/// Creates the output params file, with `note` as a comment at the top, if
+/// given.
+fn write_params(path: &Path, params: &Params, note: Option<&str>) {
+    let file = AtomicFile::create(path).unwrap_or_else(|e| {
+        error_exit!(Io, "could not create output params file: {e}");
+    });
+    let write = || -> ron::Result<()> {
+        let mut writer = BufWriter::new(file);
+        if let Some(note) = note {
+            writeln!(writer, "// {note}")?;
+        }
+        let pretty = PrettyConfig::new().depth_limit(1);
+        ron::ser::to_writer_pretty(&mut writer, params, pretty)?;
+        writeln!(writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.commit()?;
+        Ok(())
+    };
+    write().unwrap_or_else(|e| {
+        error_exit!(Io, "could not write to output params file: {e}");
+    });
+}
+
+/// Generates an image as described by `args`, with `suffix` appended to the
+/// names of the output files, and prints its fingerprint if requested.
+/// `threads` is passed to [`generate`]. If `progress` is true, a progress
+/// bar is shown unless `args` says to be quiet. `index` is the number of
+/// the image in a batch.
+fn generate_one(
+    args: &Args,
+    suffix: &str,
+    index: Option<usize>,
+    params: Params,
+    threads: NonZeroUsize,
+    progress: bool,
+) -> Generated {
+    let verbose = args.verbosity == Verbosity::Verbose;
+    let (image, params_path) = output_paths(args, suffix);
+    if verbose {
+        // `eprintln!` locks stderr, so messages from different threads won't
+        // be interleaved.
+        let ron = ron::to_string(&params).unwrap_or_else(|e| e.to_string());
+        eprintln!("{}: params: {ron}", image.display());
+    }
+    let path = params_path.as_deref();
+    let progress = progress && args.verbosity > Verbosity::Quiet;
+    if let Some(scale) = args.draft {
+        let draft = image.with_extension("draft.bmp");
+        let preview = Preview {
+            scale,
+            open: false,
+            print: false,
+        };
+        let params = preview.apply(params.clone());
+        let generated_params = params.clone();
+        let options = ImageOptions {
+            threads,
+            progress,
+            thumbnail: None,
+            mosaic: None,
+            window: false,
+            count_rng: args.count_rng,
+        };
+        let (timings, fingerprint) = generate(&draft, params, options);
+        if let Some(reason) = interrupt::reason() {
+            // Don't start the full image, which would overwrite any
+            // existing image with a blank one.
+            eprintln!("{}: {reason}; wrote partial draft", draft.display());
+            return Generated {
+                image: draft,
+                params_path: None,
+                params: generated_params,
+                timings,
+                fingerprint,
+            };
+        }
+        if args.verbosity > Verbosity::Quiet {
+            eprintln!("{}: wrote draft", draft.display());
+        }
+    }
+    if let Some(path) = path {
+        // Note where the seed came from if it was derived.
+        let note = (args.master_seed.as_ref())
+            .zip(index)
+            .filter(|&(master, i)| params.seed == derive_seed(master, i))
+            .map(|(master, i)| {
+                let master = plumage::seed_to_hex(master);
+                format!("Seed derived from master seed {master}, index {i}.")
+            });
+        // With a preview, this writes the full-size params, so the preview
+        // can be rendered at full size with `--params`.
+        write_params(path, &params, note.as_deref());
+    }
+    let params = match &args.preview {
+        Some(preview) => preview.apply(params),
+        None => params,
+    };
+    let dimensions = params.output_dimensions();
+    let generated_params = params.clone();
+    let options = ImageOptions {
+        threads,
+        progress,
+        thumbnail: args.thumbnail,
+        mosaic: args.mosaic,
+        window: args.window,
+        count_rng: args.count_rng,
+    };
+    let (timings, fingerprint) = generate(&image, params, options);
+    if let Some(reason) = interrupt::reason() {
+        eprintln!("{}: {reason}; wrote partial image", image.display());
+    }
+    if verbose {
+        eprintln!(
+            "{}: wrote image{}\n{}: {}",
+            image.display(),
+            match &params_path {
+                Some(path) => format!(" and {}", path.display()),
+                None => String::new(),
+            },
+            image.display(),
+            format_timings(&timings),
+        );
+    }
+    if args.fingerprint {
+        // `println!` locks stdout, so lines from different threads won't be
+        // interleaved.
+        println!("{fingerprint}  {}", image.display());
+    }
+    if let Some(preview) = &args.preview {
+        if preview.print {
+            preview::print(&image, dimensions).unwrap_or_else(|e| {
+                error_exit!(Io, "could not print image: {e}");
+            });
+        }
+        if preview.open {
+            preview::open(&image).unwrap_or_else(|e| {
+                error_exit!(Io, "could not open image: {e}");
+            });
+        }
+    }
+    let generated = Generated {
+        image,
+        params_path: path.map(Path::to_owned),
+        params: generated_params,
+        timings,
+        fingerprint,
+    };
+    if let Some(log) = &args.log {
+        let interrupted = interrupt::stopped();
+        stats::log(log, &generated, interrupted).unwrap_or_else(|e| {
+            error_exit!(Io, "could not write to {}: {e}", log.display());
+        });
+    }
+    generated
+}
+
+/// Describes how long each stage of generating an image took.
+fn format_timings(timings: &Timings) -> String {
+    let mut stages = vec![("fill", timings.fill)];
+    stages.extend(timings.passes.iter().copied());
+    stages.push(("quantize", timings.quantize));
+    stages.push(("write", timings.write));
+    stages.push(("total", timings.total()));
+    let mut stages: Vec<_> = stages
+        .into_iter()
+        .map(|(name, time)| format!("{name} {:.3}s", time.as_secs_f64()))
+        .collect();
+    if let Some(words) = timings.rng_words {
+        stages.push(format!("{words} random words"));
+    }
+    stages.join(", ")
+}
+
+/// Generates `count` images across `jobs` threads, printing the name of
+/// each image when it's done (or its fingerprint, if requested), and then
+/// writing the gallery, if requested. Returns the generated images in order.
+fn generate_batch(
+    args: &Args,
+    count: usize,
+    layers: &[Layer],
+) -> Vec<Generated> {
+    let jobs = args.jobs.unwrap_or_else(cpus).get();
+    let width = count.to_string().len();
+    let next = AtomicUsize::new(1);
+    let generated = Mutex::new(Vec::with_capacity(count));
+    thread::scope(|s| {
+        for _ in 0..jobs.min(count) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i > count || interrupt::stopped() {
+                    break;
+                }
+                let suffix = format!("{i:0width$}");
+                // Each image gets its own random defaults.
+                // Parallelism comes from generating multiple images at
+                // once, so each image uses one thread by default.
+                let params = deserialize_params(layers, args, Some(i));
+                let threads = NonZeroUsize::MIN;
+                let image = generate_one(
+                    args,
+                    &suffix,
+                    Some(i),
+                    params,
+                    threads,
+                    false,
+                );
+                let quiet = args.verbosity == Verbosity::Quiet;
+                if !args.fingerprint && !quiet {
+                    // `println!` locks stdout, so lines from different
+                    // threads won't be interleaved.
+                    match &args.output {
+                        Output::Name(name) => println!("{name}{suffix}"),
+                        Output::Path(_) => {
+                            println!("{}", image.image.display());
+                        }
+                    }
+                }
+                generated.lock().unwrap().push((i, image));
+            });
+        }
+    });
+    let mut generated = generated.into_inner().unwrap();
+    generated.sort_by_key(|(i, _)| *i);
+    let generated = generated.into_iter().map(|(_, image)| image).collect();
+
+    // Some images may not exist if stopped early.
+    if !args.gallery || interrupt::stopped() {
+        return generated;
+    }
+    let images: Vec<_> = (1..=count)
+        .map(|i| output_paths(args, &format!("{i:0width$}")))
+        .collect();
+    let path = gallery::write(&images).unwrap_or_else(|e| {
+        error_exit!(Io, "could not write gallery: {e}");
+    });
+    if args.verbosity == Verbosity::Verbose {
+        eprintln!("wrote gallery to {}", path.display());
+    }
+    generated
+}
+
+/// Prints the params that would be generated, as a params file, followed
+/// by comments estimating the size of the image. With `--count`, these are
+/// the params of the first image.
+fn dry_run(args: &Args, layers: &[Layer]) {
+    let params = deserialize_params(layers, args, args.count.map(|_| 1));
+    let pretty = PrettyConfig::new().depth_limit(1);
+    let text = ron::ser::to_string_pretty(&params, pretty)
+        .expect("could not serialize params");
+    println!("{text}");
+    let params = match &args.preview {
+        Some(preview) => preview.apply(params),
+        None => params,
+    };
+    let dim = params.output_dimensions();
+    let (file, memory) = commands::estimate_sizes(&params);
+    println!("// image: {}x{}", dim.width, dim.height);
+    println!("// bitmap size: {}", commands::format_bytes(file));
+    println!("// memory needed: {}", commands::format_bytes(memory));
+}
+
+fn main() {
+    // Commands must be the first argument.
+    match env::args().nth(1).as_deref() {
+        Some("init") => return commands::init(env::args().skip(2)),
+        Some("info") => return commands::info(env::args().skip(2)),
+        Some("check") => return commands::check(env::args().skip(2)),
+        Some("selftest") => {
+            return commands::selftest(env::args().skip(2));
+        }
+        Some("bench") => return bench::bench(env::args().skip(2)),
+        Some("animate") => return animate::animate(env::args().skip(2)),
+        #[cfg(unix)]
+        Some("daemon") => return daemon::daemon(env::args().skip(2)),
+        #[cfg(not(unix))]
+        Some("daemon") => {
+            error_exit!(Usage, "`plumage daemon` is only supported on Unix");
+        }
+        _ => {}
+    }
+    let args = match env::args().nth(1).as_deref() {
+        Some("preview") => parse_args(env::args().skip(2), Mode::Preview),
+        Some("explore") => parse_args(env::args().skip(2), Mode::Explore),
+        Some("mutate") => parse_args(env::args().skip(2), Mode::Mutate),
+        Some("wallpaper") => {
+            parse_args(env::args().skip(2), Mode::Wallpaper)
+        }
+        Some("tune") if cfg!(feature = "tui") => {
+            parse_args(env::args().skip(2), Mode::Tune)
+        }
+        Some("render") => parse_render_args(env::args().skip(2)),
+        _ => parse_args(env::args().skip(1), Mode::Generate),
+    };
+
+    // Read input params. The default params files are optional, but a path
+    // given with `--params` must exist.
+    let (text, path) = match &args.params {
+        Some(path) if path.as_os_str() == "-" => {
+            let text = io::read_to_string(io::stdin()).unwrap_or_else(|e| {
+                error_exit!(Params, "error reading params from stdin: {e}");
+            });
+            (text, None)
+        }
+        Some(path) => {
+            let text = fs::read_to_string(path).unwrap_or_else(|e| {
+                let path = path.display();
+                error_exit!(Params, "error reading params from {path}: {e}");
+            });
+            (text, Some(path.clone()))
+        }
+        None => config::default_params(),
+    };
+    let format = path.as_deref().map_or(Format::Ron, Format::from_path);
+    let layers = base::load(text, format, path.as_deref(), args.base.clone())
+        .unwrap_or_else(|e| error_exit!(Params, "{e}"));
+    for problem in commands::unknown_fields(&layers) {
+        warning!("{problem}");
+    }
+
+    if args.dry_run {
+        return dry_run(&args, &layers);
+    }
+
+    interrupt::install();
+    // A deadline too far in the future to represent is no deadline.
+    if let Some(deadline) =
+        args.max_duration.and_then(|d| Instant::now().checked_add(d))
+    {
+        interrupt::set_deadline(deadline);
+    }
+    let generated = match (&args.wallpaper, args.count) {
+        (Some(wallpaper), _) => wallpaper::run(&args, wallpaper, &layers),
+        (None, Some(count)) => generate_batch(&args, count, &layers),
+        (None, None) => {
+            let params = deserialize_params(&layers, &args, None);
+            let params = if args.tune {
+                tune(params)
+            } else {
+                params
+            };
+            vec![generate_one(&args, "", None, params, cpus(), true)]
+        }
+    };
+    if let Some(path) = &args.stats {
+        let interrupted = interrupt::stopped();
+        stats::write(path, &generated, interrupted).unwrap_or_else(|e| {
+            let path = path.display();
+            error_exit!(Io, "could not write stats to {path}: {e}");
+        });
+    }
+    atomic::remove_pending();
+    if interrupt::interrupted() {
+        exit(130);
+    }
+    if interrupt::timed_out() {
+        exit(124);
+    }
 }
@@ -0,0 +1,102 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::thumbnail::Thumbnail;
+use plumage::Dimensions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// How far the colors of neighboring cells are blended with
+/// `--mosaic-blur`, in cells.
+const BLUR: f64 = 0.6;
+
+/// Options for `--mosaic`.
+#[derive(Clone, Copy)]
+pub struct Mosaic {
+    /// The number of columns and rows of cells.
+    pub cells: Dimensions,
+    /// Blur the cells into smooth gradients.
+    pub blur: bool,
+}
+
+/// The path of the mosaic for `image`.
+pub fn path(image: &Path) -> PathBuf {
+    image.with_extension("svg")
+}
+
+impl Mosaic {
+    /// Creates a [`Thumbnail`] that averages an image of size `image` into
+    /// the cells of the mosaic.
+    pub fn grid(&self, image: Dimensions) -> Thumbnail {
+        Thumbnail::with_dimensions(image, self.cells)
+    }
+
+    /// Writes the cells in `grid`, created with [`Self::grid`], as an SVG
+    /// image the same size as the image they were averaged from. Adjacent
+    /// cells of the same color in a row are merged into one rectangle.
+    pub fn write<W: Write>(
+        &self,
+        grid: &Thumbnail,
+        image: Dimensions,
+        mut stream: W,
+    ) -> io::Result<()> {
+        let cells = grid.dimensions();
+        // The view box has one unit per cell, which the SVG stretches to
+        // the image size.
+        writeln!(
+            stream,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" \
+             height=\"{}\" viewBox=\"0 0 {} {}\" \
+             preserveAspectRatio=\"none\" shape-rendering=\"crispEdges\">",
+            image.width, image.height, cells.width, cells.height,
+        )?;
+        if self.blur {
+            // Repeat edge cells instead of fading to transparent.
+            writeln!(
+                stream,
+                "<filter id=\"blur\" x=\"0\" y=\"0\" width=\"1\" \
+                 height=\"1\" color-interpolation-filters=\"sRGB\">\
+                 <feGaussianBlur stdDeviation=\"{BLUR}\" \
+                 edgeMode=\"duplicate\"/></filter>\n\
+                 <g filter=\"url(#blur)\">",
+            )?;
+        }
+        for y in 0..cells.height {
+            let mut x = 0;
+            while x < cells.width {
+                let rgb = grid.rgb(x, y);
+                let run = (x + 1..cells.width)
+                    .take_while(|&x| grid.rgb(x, y) == rgb)
+                    .count()
+                    + 1;
+                let [red, green, blue] = rgb;
+                writeln!(
+                    stream,
+                    "<rect x=\"{x}\" y=\"{y}\" width=\"{run}\" height=\"1\" \
+                     fill=\"#{red:02x}{green:02x}{blue:02x}\"/>",
+                )?;
+                x += run;
+            }
+        }
+        if self.blur {
+            writeln!(stream, "</g>")?;
+        }
+        writeln!(stream, "</svg>")
+    }
+}
@@ -0,0 +1,144 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Passes loaded from dynamic libraries with `--pass`.
+//!
+//! A library must export these C functions:
+//!
+//! ```c
+//! // Returns 1, the version of this interface.
+//! uint32_t plumage_pass_abi_version(void);
+//! // Applies the pass to `rows` rows of the image, starting with row `y`.
+//! // `pixels` holds `width * rows` pixels, each a red, green, and blue
+//! // component between 0 and 1.
+//! void plumage_pass_apply(float *pixels, size_t width, size_t rows,
+//!                         size_t y);
+//! ```
+//!
+//! The pass is named after the library's file.
+
+use libloading::{Library, Symbol};
+use plumage::{Color, Float, Generator, Pass};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+/// The version of the interface in the module documentation.
+const ABI_VERSION: u32 = 1;
+
+type ApplyFn = unsafe extern "C" fn(*mut f32, usize, usize, usize);
+
+/// A pass loaded from a dynamic library.
+struct Plugin {
+    name: &'static str,
+    apply: ApplyFn,
+    /// Keeps `apply` loaded.
+    _library: Library,
+}
+
+/// The passes given with `--pass`.
+static PLUGINS: OnceLock<Vec<Arc<Plugin>>> = OnceLock::new();
+
+impl Plugin {
+    fn load(path: &Path) -> Result<Self, String> {
+        // SAFETY: Loading a library runs its initialization code, which we
+        // have to trust, as with the pass itself.
+        let library =
+            unsafe { Library::new(path) }.map_err(|e| e.to_string())?;
+        // SAFETY: The types match the interface in the module
+        // documentation.
+        let version = unsafe {
+            let version: Symbol<'_, unsafe extern "C" fn() -> u32> = library
+                .get(b"plumage_pass_abi_version\0")
+                .map_err(|e| e.to_string())?;
+            version()
+        };
+        if version != ABI_VERSION {
+            return Err(format!(
+                "unsupported interface version {version} (expected \
+                 {ABI_VERSION})"
+            ));
+        }
+        // SAFETY: As above.
+        let apply = unsafe {
+            *library
+                .get::<ApplyFn>(b"plumage_pass_apply\0")
+                .map_err(|e| e.to_string())?
+        };
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        Ok(Self {
+            // Passes live for the whole program.
+            name: String::leak(name.into_owned()),
+            apply,
+            _library: library,
+        })
+    }
+}
+
+/// Shares a loaded [`Plugin`] among generators.
+struct PluginPass(Arc<Plugin>);
+
+impl Pass for PluginPass {
+    fn name(&self) -> &'static str {
+        self.0.name
+    }
+
+    // `Float` may be `f64`.
+    #[allow(clippy::unnecessary_cast)]
+    fn apply(&self, rows: &mut [Color], width: usize, y: usize) {
+        let mut pixels: Vec<f32> = rows
+            .iter()
+            .flat_map(|c| [c.red, c.green, c.blue])
+            .map(|n| n as f32)
+            .collect();
+        let height = rows.len() / width.max(1);
+        // SAFETY: `pixels` holds `width * height` pixels, as required.
+        unsafe { (self.0.apply)(pixels.as_mut_ptr(), width, height, y) };
+        for (color, rgb) in rows.iter_mut().zip(pixels.chunks_exact(3)) {
+            let [red, green, blue] = [rgb[0], rgb[1], rgb[2]].map(Float::from);
+            *color = Color {
+                red,
+                green,
+                blue,
+            };
+        }
+    }
+}
+
+/// Loads the passes in `paths`, which are added to each generator by
+/// [`attach`].
+pub fn load(paths: &[impl AsRef<Path>]) {
+    let plugins = paths
+        .iter()
+        .map(|path| {
+            let path = path.as_ref();
+            Plugin::load(path).map(Arc::new).unwrap_or_else(|e| {
+                error_exit!(Io, "could not load {}: {e}", path.display());
+            })
+        })
+        .collect();
+    let _ = PLUGINS.set(plugins);
+}
+
+/// Adds the passes loaded by [`load`] to `generator`.
+pub fn attach(generator: Generator) -> Generator {
+    let plugins = PLUGINS.get().map_or(&[][..], Vec::as_slice);
+    plugins.iter().fold(generator, |generator, plugin| {
+        generator.pass(PluginPass(plugin.clone()))
+    })
+}
@@ -0,0 +1,104 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use plumage::{Dimensions, Params};
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::process::Command;
+
+/// The combined size of the bitmap file header and BITMAPINFOHEADER, as
+/// written by [`plumage::Generator`].
+const BMP_HEADER_SIZE: usize = 14 + 40;
+
+/// Options for `plumage preview`.
+pub struct Preview {
+    /// The image and spread are divided by this.
+    pub scale: NonZeroUsize,
+    /// Open the image in the default viewer.
+    pub open: bool,
+    /// Print the image to the terminal.
+    pub print: bool,
+}
+
+impl Preview {
+    /// Scales down the image and spread in `params` (see
+    /// [`Params::rescale`]).
+    pub fn apply(&self, params: Params) -> Params {
+        params.rescale(|n| (n / self.scale).max(1))
+    }
+}
+
+/// Opens `path` with the system's default program.
+pub fn open(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    let status = command.arg(path).status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("viewer failed: {status}")));
+    }
+    Ok(())
+}
+
+/// Prints the bitmap at `path`, which must have been written by
+/// [`plumage::Generator`] with the given dimensions, to the terminal using
+/// 24-bit color. Each character shows two pixels, one above the other.
+pub fn print(path: &Path, dim: Dimensions) -> io::Result<()> {
+    let data = fs::read(path)?;
+    let row_size = (dim.width * 3).div_ceil(4) * 4;
+    if data.len() < BMP_HEADER_SIZE + row_size * dim.height {
+        return Err(io::Error::other("bitmap is truncated"));
+    }
+    let pixel = |x: usize, y: usize| {
+        let i = BMP_HEADER_SIZE + y * row_size + x * 3;
+        [data[i + 2], data[i + 1], data[i]]
+    };
+
+    let columns = env::var("COLUMNS").ok().and_then(|c| c.parse().ok());
+    let columns = columns.unwrap_or(80).clamp(1, dim.width);
+    // Terminal characters are about twice as tall as they are wide, so
+    // each half of a character is about square.
+    let rows = (columns * dim.height / dim.width).max(1).div_ceil(2) * 2;
+    let mut out = String::new();
+    for row in (0..rows).step_by(2) {
+        for column in 0..columns {
+            let x = column * dim.width / columns;
+            let top = pixel(x, row * dim.height / rows);
+            let bottom = pixel(x, (row + 1) * dim.height / rows);
+            let _ = write!(
+                out,
+                "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}",
+                top[0], top[1], top[2], bottom[0], bottom[1], bottom[2],
+            );
+        }
+        out += "\x1b[0m\n";
+    }
+    print!("{out}");
+    Ok(())
+}
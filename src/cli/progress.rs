@@ -0,0 +1,96 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use plumage::Progress;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// The width of the bar itself, in characters.
+const BAR_WIDTH: usize = 40;
+
+/// The minimum time between redraws.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A progress bar with a percentage and estimated time remaining, drawn on
+/// standard error.
+pub struct ProgressBar {
+    enabled: bool,
+    last_draw: Option<Instant>,
+}
+
+impl ProgressBar {
+    /// Creates a new progress bar. Nothing is drawn unless `enabled` is true
+    /// and standard error is a terminal.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: enabled && io::stderr().is_terminal(),
+            last_draw: None,
+        }
+    }
+
+    /// Redraws the bar, unless it was drawn very recently.
+    pub fn update(&mut self, progress: Progress) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        let done = progress.rows == progress.total_rows;
+        if !done
+            && self.last_draw.is_some_and(|t| now - t < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        self.last_draw = Some(now);
+
+        let fraction = progress.rows as f64 / progress.total_rows as f64;
+        let filled = (fraction * BAR_WIDTH as f64) as usize;
+        let eta = match progress.eta {
+            Some(eta) => format_duration(eta),
+            None => "?".into(),
+        };
+        let mut stderr = io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r[{:=<filled$}{:<rest$}] {:3.0}%  ETA {eta} \x1b[K",
+            "",
+            "",
+            fraction * 100.0,
+            rest = BAR_WIDTH - filled,
+        );
+        let _ = stderr.flush();
+    }
+
+    /// Erases the bar.
+    pub fn finish(&mut self) {
+        if self.enabled && self.last_draw.is_some() {
+            let _ = write!(io::stderr(), "\r\x1b[K");
+        }
+    }
+}
+
+/// Formats a duration like `1:05:09` or `5:09`.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, mins, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}:{mins:02}:{secs:02}")
+    } else {
+        format!("{mins}:{secs:02}")
+    }
+}
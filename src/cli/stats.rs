@@ -0,0 +1,201 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::atomic::AtomicFile;
+use plumage::{Fingerprint, Params, Timings};
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A generated image.
+pub struct Generated {
+    pub image: PathBuf,
+    pub params_path: Option<PathBuf>,
+    /// The params the image was generated with.
+    pub params: Params,
+    pub timings: Timings,
+    pub fingerprint: Fingerprint,
+}
+
+/// Formats `text` as a JSON string.
+fn string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json += "\\\"",
+            '\\' => json += "\\\\",
+            '\n' => json += "\\n",
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Formats a path as a JSON string.
+fn path(path: &Path) -> String {
+    string(&path.to_string_lossy())
+}
+
+/// The size of the file at `path` as JSON, or `null` if it can't be read.
+fn file_size(path: &Path) -> String {
+    fs::metadata(path).map_or("null".into(), |m| m.len().to_string())
+}
+
+/// Formats `params` as JSON, in the same format as params files, so the
+/// params can be read back to generate the same image. Non-finite numbers,
+/// which JSON can't represent, are written as `null`.
+fn params(params: &Params) -> String {
+    serde_json::to_string(params).expect("params should serialize")
+}
+
+/// Formats how long each stage took as a JSON object, in seconds.
+fn timings(timings: &Timings) -> String {
+    let mut stages = vec![("fill", timings.fill)];
+    stages.extend(timings.passes.iter().copied());
+    stages.push(("quantize", timings.quantize));
+    stages.push(("write", timings.write));
+    stages.push(("total", timings.total()));
+    let stages: Vec<_> = stages
+        .into_iter()
+        .map(|(name, time)| {
+            format!("{}: {}", string(name), time.as_secs_f64())
+        })
+        .collect();
+    format!("{{ {} }}", stages.join(", "))
+}
+
+/// Formats the number of random words drawn at each stage as a JSON object,
+/// or `null` if they weren't counted.
+fn rng_words(timings: &Timings) -> String {
+    timings
+        .rng_words
+        .map_or("null".into(), |words| format!("{{ \"fill\": {words} }}"))
+}
+
+/// Formats a generated image as a JSON object.
+fn entry(generated: &Generated) -> String {
+    let params_path = generated.params_path.as_deref();
+    format!(
+        r#"    {{
+      "image": {image},
+      "image_size": {image_size},
+      "params_file": {params_file},
+      "params_size": {params_size},
+      "params": {params},
+      "timings": {timings},
+      "rng_words": {rng_words},
+      "fingerprint": "{fingerprint}"
+    }}"#,
+        image = path(&generated.image),
+        image_size = file_size(&generated.image),
+        params_file = params_path.map_or("null".into(), path),
+        params_size = params_path.map_or("null".into(), file_size),
+        params = params(&generated.params),
+        timings = timings(&generated.timings),
+        rng_words = rng_words(&generated.timings),
+        fingerprint = generated.fingerprint,
+    )
+}
+
+/// Writes a JSON report describing each generated image to `path`.
+/// `interrupted` is whether the run was interrupted with Ctrl-C or by
+/// `--max-duration`.
+pub fn write(
+    path: &Path,
+    images: &[Generated],
+    interrupted: bool,
+) -> io::Result<()> {
+    let images: Vec<_> = images.iter().map(entry).collect();
+    let mut writer = BufWriter::new(AtomicFile::create(path)?);
+    writeln!(
+        writer,
+        "{{\n  \"interrupted\": {interrupted},\n  \"images\": [\n{}\n  ]\n}}",
+        images.join(",\n"),
+    )?;
+    writer.into_inner().map_err(|e| e.into_error())?.commit()
+}
+
+/// Formats `time` as an RFC 3339 timestamp in UTC, like
+/// `2024-01-02T03:04:05Z`.
+fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // Convert days since 1970-01-01 to a date in the proleptic Gregorian
+    // calendar, using eras of 400 years starting on March 1.
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+        - day_of_era / 146096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 {
+        month + 3
+    } else {
+        month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as u64;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+    )
+}
+
+/// Appends a line describing a generated image to the JSON Lines file at
+/// `path`: when it was generated, its seed, the canonical hash of its
+/// params, and where it was written. `interrupted` is whether the image is
+/// incomplete because of Ctrl-C or `--max-duration`.
+pub fn log(
+    path: &Path,
+    generated: &Generated,
+    interrupted: bool,
+) -> io::Result<()> {
+    let seed = plumage::seed_to_hex(&generated.params.seed);
+    let params_path = generated.params_path.as_deref();
+    let line = format!(
+        "{{\"time\": \"{time}\", \"image\": {image}, \
+         \"params_file\": {params_file}, \"seed\": \"{seed}\", \
+         \"params_hash\": \"{params_hash}\", \
+         \"fingerprint\": \"{fingerprint}\", \
+         \"interrupted\": {interrupted}}}\n",
+        time = timestamp(SystemTime::now()),
+        image = self::path(&generated.image),
+        params_file = params_path.map_or("null".into(), self::path),
+        params_hash = generated.params.canonicalize().hash(),
+        fingerprint = generated.fingerprint,
+    );
+    // Write the line all at once, so lines from different threads or
+    // processes aren't interleaved.
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    file.write_all(line.as_bytes())
+}
@@ -0,0 +1,183 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use plumage::Dimensions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The combined size of the bitmap file header and BITMAPINFOHEADER, as
+/// written by [`plumage::Generator`].
+const HEADER_SIZE: usize = 14 + 40;
+
+/// The number of bytes in a bitmap row of the given width, with padding.
+fn row_size(width: usize) -> usize {
+    (width * 3).div_ceil(4) * 4
+}
+
+/// The path of the thumbnail for `image`.
+pub fn path(image: &Path) -> PathBuf {
+    image.with_extension("thumb.bmp")
+}
+
+/// Builds a downscaled copy of a bitmap as it's written, by averaging the
+/// pixels that map to each pixel of the thumbnail.
+pub struct Thumbnail {
+    src: Dimensions,
+    dest: Dimensions,
+    /// The sum of the BGR components of the pixels in each thumbnail pixel.
+    sums: Vec<[u64; 3]>,
+    counts: Vec<u64>,
+    /// The bitmap bytes not yet processed.
+    pending: Vec<u8>,
+    /// The number of bytes of the header left to skip.
+    header_left: usize,
+    /// The next source row.
+    y: usize,
+}
+
+impl Thumbnail {
+    /// Creates a thumbnail of a bitmap with dimensions `src` that is at
+    /// most `max_size` pixels wide and tall.
+    pub fn new(src: Dimensions, max_size: usize) -> Self {
+        let longest = src.width.max(src.height).max(1);
+        let scale = |n: usize| (n * max_size / longest).clamp(1, n.max(1));
+        let dest = if longest <= max_size {
+            src
+        } else {
+            Dimensions::new(scale(src.width), scale(src.height))
+        };
+        Self::with_dimensions(src, dest)
+    }
+
+    /// Creates a thumbnail of a bitmap with dimensions `src` that is `dest`
+    /// pixels in size, or as close as possible without being larger than
+    /// the bitmap or empty.
+    pub fn with_dimensions(src: Dimensions, dest: Dimensions) -> Self {
+        let fit = |dest: usize, src: usize| dest.clamp(1, src.max(1));
+        let dest = Dimensions::new(
+            fit(dest.width, src.width),
+            fit(dest.height, src.height),
+        );
+        Self {
+            src,
+            dest,
+            sums: vec![[0; 3]; dest.count()],
+            counts: vec![0; dest.count()],
+            pending: Vec::new(),
+            header_left: HEADER_SIZE,
+            y: 0,
+        }
+    }
+
+    /// The size of the thumbnail.
+    pub fn dimensions(&self) -> Dimensions {
+        self.dest
+    }
+
+    /// The average color of the pixels that map to thumbnail pixel `(x,
+    /// y)`, as red, green, and blue components.
+    pub fn rgb(&self, x: usize, y: usize) -> [u8; 3] {
+        let i = y * self.dest.width + x;
+        let count = self.counts[i].max(1);
+        let [blue, green, red] = self.sums[i].map(|sum| (sum / count) as u8);
+        [red, green, blue]
+    }
+
+    /// Processes bytes of the bitmap.
+    fn push(&mut self, mut bytes: &[u8]) {
+        let skip = self.header_left.min(bytes.len());
+        self.header_left -= skip;
+        bytes = &bytes[skip..];
+        self.pending.extend_from_slice(bytes);
+
+        let (src, dest) = (self.src, self.dest);
+        let row_size = row_size(src.width);
+        let mut start = 0;
+        while self.pending.len() - start >= row_size && self.y < src.height {
+            let row = &self.pending[start..][..row_size];
+            let dest_y = self.y * dest.height / src.height;
+            for (x, bgr) in row[..src.width * 3].chunks_exact(3).enumerate() {
+                let dest_x = x * dest.width / src.width;
+                let i = dest_y * dest.width + dest_x;
+                for (sum, &n) in self.sums[i].iter_mut().zip(bgr) {
+                    *sum += u64::from(n);
+                }
+                self.counts[i] += 1;
+            }
+            start += row_size;
+            self.y += 1;
+        }
+        self.pending.drain(..start);
+    }
+
+    /// Writes the thumbnail as a bitmap.
+    pub fn write<W: Write>(&self, mut stream: W) -> io::Result<()> {
+        let dim = self.dest;
+        let row_size = row_size(dim.width);
+        let size = (HEADER_SIZE + row_size * dim.height) as u32;
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(b"BM");
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(b"PLMG");
+        header.extend_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        header.extend_from_slice(&40_u32.to_le_bytes());
+        header.extend_from_slice(&(dim.width as u32).to_le_bytes());
+        // Negative heights mean rows are stored top to bottom.
+        let height = (dim.height as u32).wrapping_neg();
+        header.extend_from_slice(&height.to_le_bytes());
+        header.extend_from_slice(&1_u16.to_le_bytes());
+        header.extend_from_slice(&24_u16.to_le_bytes());
+        for n in [0, 0, 96, 96, 0, 0_u32] {
+            header.extend_from_slice(&n.to_le_bytes());
+        }
+        stream.write_all(&header)?;
+
+        let mut row = Vec::with_capacity(row_size);
+        for y in 0..dim.height {
+            row.clear();
+            for x in 0..dim.width {
+                let [red, green, blue] = self.rgb(x, y);
+                row.extend([blue, green, red]);
+            }
+            row.resize(row_size, 0);
+            stream.write_all(&row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes to a stream while passing the written bytes to thumbnails.
+pub struct Tee<'a, W> {
+    pub inner: W,
+    pub thumbnails: Vec<&'a mut Thumbnail>,
+}
+
+impl<W: Write> Write for Tee<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        for thumbnail in &mut self.thumbnails {
+            thumbnail.push(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,368 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! `plumage tune`, which adjusts params in a terminal UI.
+
+use super::cpus;
+use super::preview::Preview;
+use plumage::{Color, Float, Generator, Params, Position, Spread};
+use rand::{thread_rng, Rng};
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{self, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+use std::num::NonZeroUsize;
+use std::process::exit;
+
+const HELP: &str = "\
+↑/↓: select  ←/→: adjust
+r: new seed  c: new color
+enter: render  q: quit";
+
+/// A param that can be adjusted.
+#[derive(Clone, Copy)]
+enum Field {
+    Spread,
+    Shape,
+    DistancePower,
+    CountPower,
+    RandomPower,
+    RandomMax,
+    Gamma,
+    StartColor,
+    Seed,
+    FastMath,
+    PreciseAveraging,
+    UniformAveraging,
+    AntialiasSpread,
+    ExtendedRange,
+}
+
+const FIELDS: [Field; 14] = [
+    Field::Spread,
+    Field::Shape,
+    Field::DistancePower,
+    Field::CountPower,
+    Field::RandomPower,
+    Field::RandomMax,
+    Field::Gamma,
+    Field::StartColor,
+    Field::Seed,
+    Field::FastMath,
+    Field::PreciseAveraging,
+    Field::UniformAveraging,
+    Field::AntialiasSpread,
+    Field::ExtendedRange,
+];
+
+/// The size of the spread in `spread`.
+fn spread_size(spread: Spread) -> usize {
+    match spread {
+        Spread::Square {
+            width,
+        } => width,
+        Spread::QuarterCircle {
+            radius,
+        } => radius,
+    }
+}
+
+/// Adds `step` to `value`, rounding away floating-point error.
+fn nudge(value: &mut Float, step: Float) {
+    *value = ((*value + step) * 1000.0).round() / 1000.0;
+}
+
+impl Field {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Spread => "spread",
+            Self::Shape => "shape",
+            Self::DistancePower => "distance_power",
+            Self::CountPower => "count_power",
+            Self::RandomPower => "random_power",
+            Self::RandomMax => "random_max",
+            Self::Gamma => "gamma",
+            Self::StartColor => "start_color",
+            Self::Seed => "seed",
+            Self::FastMath => "fast_math",
+            Self::PreciseAveraging => "precise_averaging",
+            Self::UniformAveraging => "uniform_averaging",
+            Self::AntialiasSpread => "antialias_spread",
+            Self::ExtendedRange => "extended_range",
+        }
+    }
+
+    fn value(self, params: &Params) -> String {
+        match self {
+            Self::Spread => spread_size(params.spread).to_string(),
+            Self::Shape => match params.spread {
+                Spread::Square {
+                    ..
+                } => "square".into(),
+                Spread::QuarterCircle {
+                    ..
+                } => "quarter circle".into(),
+            },
+            Self::DistancePower => params.distance_power.to_string(),
+            Self::CountPower => params.count_power.to_string(),
+            Self::RandomPower => params.random_power.to_string(),
+            Self::RandomMax => params.random_max.to_string(),
+            Self::Gamma => params.gamma.to_string(),
+            Self::StartColor => {
+                format!("#{:06x}", params.start_color.to_u32())
+            }
+            Self::Seed => {
+                plumage::seed_to_hex(&params.seed)[..8].into()
+            }
+            Self::FastMath => params.fast_math.to_string(),
+            Self::PreciseAveraging => params.precise_averaging.to_string(),
+            Self::UniformAveraging => params.uniform_averaging.to_string(),
+            Self::AntialiasSpread => params.antialias_spread.to_string(),
+            Self::ExtendedRange => params.extended_range.to_string(),
+        }
+    }
+
+    /// Increases the value if `up` is true, or decreases it. Random fields
+    /// get a new random value either way, and booleans are toggled.
+    fn adjust(self, params: &mut Params, up: bool) {
+        let sign = if up {
+            1.0
+        } else {
+            -1.0
+        };
+        match self {
+            Self::Spread => {
+                let size = spread_size(params.spread);
+                let size = if up {
+                    size + 1
+                } else {
+                    size.saturating_sub(1).max(1)
+                };
+                params.spread = match params.spread {
+                    Spread::Square {
+                        ..
+                    } => Spread::Square {
+                        width: size,
+                    },
+                    Spread::QuarterCircle {
+                        ..
+                    } => Spread::QuarterCircle {
+                        radius: size,
+                    },
+                };
+            }
+            Self::Shape => {
+                let size = spread_size(params.spread);
+                params.spread = match params.spread {
+                    Spread::Square {
+                        ..
+                    } => Spread::QuarterCircle {
+                        radius: size,
+                    },
+                    Spread::QuarterCircle {
+                        ..
+                    } => Spread::Square {
+                        width: size,
+                    },
+                };
+            }
+            Self::DistancePower => {
+                nudge(&mut params.distance_power, sign / 10.0);
+            }
+            Self::CountPower => nudge(&mut params.count_power, sign / 100.0),
+            // Adjusting a field replaces its expression, if any, with the
+            // expression's value at the first pixel.
+            Self::RandomPower => {
+                params.expressions.random_power = None;
+                nudge(&mut params.random_power, sign / 10.0);
+            }
+            Self::RandomMax => {
+                params.expressions.random_max = None;
+                nudge(&mut params.random_max, sign / 200.0);
+            }
+            Self::Gamma => {
+                params.expressions.gamma = None;
+                nudge(&mut params.gamma, sign / 20.0);
+            }
+            Self::StartColor => {
+                params.start_color = Color::random(thread_rng());
+            }
+            Self::Seed => thread_rng().fill(&mut params.seed),
+            Self::FastMath => params.fast_math ^= true,
+            Self::PreciseAveraging => params.precise_averaging ^= true,
+            Self::UniformAveraging => params.uniform_averaging ^= true,
+            Self::AntialiasSpread => params.antialias_spread ^= true,
+            Self::ExtendedRange => params.extended_range ^= true,
+        }
+    }
+}
+
+struct Tuner {
+    params: Params,
+    /// The index of the selected field in [`FIELDS`].
+    selected: usize,
+    /// A message about the last adjustment, if it couldn't be made.
+    status: String,
+}
+
+impl Tuner {
+    /// Adjusts the selected field, unless that would make the params
+    /// invalid.
+    fn adjust(&mut self, up: bool) {
+        self.set(FIELDS[self.selected], up);
+    }
+
+    fn set(&mut self, field: Field, up: bool) {
+        let mut params = self.params.clone();
+        field.adjust(&mut params, up);
+        match params.validate() {
+            Ok(()) => {
+                self.params = params;
+                self.status.clear();
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame<'_>) {
+        let [side, image] =
+            Layout::horizontal([Constraint::Length(36), Constraint::Min(0)])
+                .areas(frame.area());
+        let [fields, help] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(5)])
+                .areas(side);
+
+        let dim = self.params.dimensions;
+        let mut lines =
+            vec![Line::raw(format!("{}x{}", dim.width, dim.height))];
+        lines.extend(FIELDS.iter().enumerate().map(|(i, field)| {
+            let text = format!(
+                "{:<18}{}",
+                field.name(),
+                field.value(&self.params),
+            );
+            let style = if i == self.selected {
+                Style::new().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::new()
+            };
+            Line::styled(text, style)
+        }));
+        if !self.status.is_empty() {
+            lines.push(Line::raw(""));
+            lines.push(Line::raw(self.status.as_str()));
+        }
+        let block = Block::bordered().title("plumage tune");
+        frame.render_widget(Paragraph::new(lines).block(block), fields);
+        frame.render_widget(Paragraph::new(HELP), help);
+        draw_preview(&self.params, image, frame.buffer_mut());
+    }
+}
+
+/// Draws a preview of the image for `params` scaled down to fit in `area`.
+/// Each character shows two pixels, one above the other.
+fn draw_preview(params: &Params, area: Rect, buf: &mut Buffer) {
+    let (columns, rows) = (usize::from(area.width), usize::from(area.height));
+    if columns == 0 || rows == 0 {
+        return;
+    }
+    let dim = params.dimensions;
+    let scale = dim.width.div_ceil(columns).max(dim.height.div_ceil(rows * 2));
+    let preview = Preview {
+        scale: NonZeroUsize::new(scale).unwrap_or(NonZeroUsize::MIN),
+        open: false,
+        print: false,
+    };
+    let params = preview.apply(params.clone());
+    let dim = params.dimensions;
+    let pixmap = Generator::new(params).threads(cpus()).into_pixmap();
+    let pixel = |x, y| {
+        let [r, g, b] = pixmap
+            .get(Position::new(x, y))
+            .map_or([0; 3], |color| color.to_rgb8());
+        style::Color::Rgb(r, g, b)
+    };
+
+    // Center the image.
+    let left = usize::from(area.x) + (columns - dim.width.min(columns)) / 2;
+    let height = dim.height.div_ceil(2).min(rows);
+    let top = usize::from(area.y) + (rows - height) / 2;
+    for row in 0..height {
+        for x in 0..dim.width.min(columns) {
+            let pos = ((left + x) as u16, (top + row) as u16);
+            if let Some(cell) = buf.cell_mut(pos) {
+                cell.set_char('▀')
+                    .set_fg(pixel(x, row * 2))
+                    .set_bg(pixel(x, row * 2 + 1));
+            }
+        }
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, tuner: &mut Tuner) -> io::Result<bool> {
+    loop {
+        terminal.draw(|frame| tuner.draw(frame))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                tuner.selected = tuner.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                tuner.selected = (tuner.selected + 1).min(FIELDS.len() - 1);
+            }
+            KeyCode::Right | KeyCode::Char('l') => tuner.adjust(true),
+            KeyCode::Left | KeyCode::Char('h') => tuner.adjust(false),
+            KeyCode::Char('r') => tuner.set(Field::Seed, true),
+            KeyCode::Char('c') => tuner.set(Field::StartColor, true),
+            KeyCode::Enter => return Ok(true),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            _ => {}
+        }
+    }
+}
+
+/// Shows a preview of the image for `params` in the terminal and lets the
+/// user adjust them, returning the adjusted params to render at full size.
+/// Exits if the user quits instead.
+pub fn tune(params: Params) -> Params {
+    let mut tuner = Tuner {
+        params,
+        selected: 0,
+        status: String::new(),
+    };
+    let mut terminal = ratatui::try_init().unwrap_or_else(|e| {
+        error_exit!(Io, "could not start terminal UI: {e}");
+    });
+    let result = run(&mut terminal, &mut tuner);
+    ratatui::restore();
+    match result {
+        Ok(true) => tuner.params,
+        Ok(false) => exit(0),
+        Err(e) => error_exit!(Io, "terminal UI failed: {e}"),
+    }
+}
@@ -0,0 +1,142 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::base::Layer;
+use super::stats::Generated;
+use super::{cpus, deserialize_params, generate_one, interrupt, Args};
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The start of the names of wallpapers, which are followed by the time
+/// they were generated.
+pub const PREFIX: &str = "plumage-";
+
+/// Options for `plumage wallpaper`.
+pub struct Wallpaper {
+    /// How many wallpapers to keep.
+    pub keep: NonZeroUsize,
+    /// How often to generate a new wallpaper, if repeatedly.
+    pub interval: Option<Duration>,
+}
+
+/// Parses an interval like `90`, `90s`, `30m`, or `1h`.
+pub fn parse_interval(text: &str) -> Option<Duration> {
+    let (n, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => text.split_at(i),
+        None => (text, "s"),
+    };
+    let n: u64 = n.parse().ok()?;
+    let secs = match unit {
+        "s" => n,
+        "m" => n.checked_mul(60)?,
+        "h" => n.checked_mul(60 * 60)?,
+        _ => return None,
+    };
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Whether `name` is the name of a wallpaper image, like
+/// `plumage-1700000000.bmp`, returning the part without the extension.
+fn wallpaper_stem(name: &str) -> Option<&str> {
+    let stem = name.strip_suffix(".bmp")?;
+    let time = stem.strip_prefix(PREFIX)?;
+    let valid = !time.is_empty() && time.bytes().all(|b| b.is_ascii_digit());
+    valid.then_some(stem)
+}
+
+/// Deletes all but the newest `keep` wallpapers in `dir`, along with their
+/// params, drafts, and thumbnails.
+fn rotate(dir: &Path, keep: usize) -> io::Result<()> {
+    let mut stems = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        if let Some(stem) = name.to_str().and_then(wallpaper_stem) {
+            stems.push(stem.to_owned());
+        }
+    }
+    // Sort from newest to oldest. The times all have the same number of
+    // digits until the year 2286.
+    stems.sort_by(|a, b| (b.len(), b).cmp(&(a.len(), a)));
+    for stem in stems.iter().skip(keep) {
+        for ext in ["bmp", "params", "draft.bmp", "thumb.bmp"] {
+            match fs::remove_file(dir.join(format!("{stem}.{ext}"))) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(e);
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sleeps until `deadline`, returning early if Ctrl-C is pressed or the
+/// time limit is exceeded.
+fn sleep_until(deadline: Instant) {
+    const POLL: Duration = Duration::from_millis(100);
+    while !interrupt::stopped() {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return;
+        }
+        thread::sleep(left.min(POLL));
+    }
+}
+
+/// Generates a wallpaper in the output directory, and then deletes old
+/// ones, repeating every interval if one was given. Returns the generated
+/// images.
+pub fn run(
+    args: &Args,
+    wallpaper: &Wallpaper,
+    layers: &[Layer],
+) -> Vec<Generated> {
+    // `parse_args` always sets the output directory for wallpapers.
+    let dir = args.output_dir.as_deref().expect("no wallpaper directory");
+    let mut generated = Vec::new();
+    loop {
+        let start = Instant::now();
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        // Each wallpaper gets its own random defaults.
+        let params = deserialize_params(layers, args, None);
+        let suffix = time.to_string();
+        let image = generate_one(args, &suffix, None, params, cpus(), true);
+        generated.push(image);
+        if interrupt::stopped() {
+            break;
+        }
+        rotate(dir, wallpaper.keep.get()).unwrap_or_else(|e| {
+            error_exit!(Io, "could not delete old wallpapers: {e}");
+        });
+        let Some(interval) = wallpaper.interval else {
+            break;
+        };
+        sleep_until(start + interval);
+        if interrupt::stopped() {
+            break;
+        }
+    }
+    generated
+}
@@ -0,0 +1,138 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::interrupt;
+use super::progress::ProgressBar;
+use minifb::{Key, Window, WindowOptions};
+use plumage::{Dimensions, Float, Generator, Position, Progress};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+/// The longest side of the window, in pixels. Larger images are scaled
+/// down to fit.
+const MAX_SIZE: usize = 1024;
+
+/// The minimum time between redraws.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A window showing an image as it's filled.
+struct Preview {
+    window: Window,
+    /// The size of the image shown, after scaling down.
+    dimensions: Dimensions,
+    /// How much the image is scaled down by.
+    factor: usize,
+    /// Pixels in 0RGB format.
+    buffer: Vec<u32>,
+    gamma: Float,
+}
+
+impl Preview {
+    fn new(image: Dimensions, gamma: Float) -> minifb::Result<Self> {
+        let factor = image.width.max(image.height).div_ceil(MAX_SIZE).max(1);
+        let dimensions = Dimensions::new(
+            (image.width / factor).max(1),
+            (image.height / factor).max(1),
+        );
+        let window = Window::new(
+            "Plumage",
+            dimensions.width,
+            dimensions.height,
+            WindowOptions::default(),
+        )?;
+        Ok(Self {
+            window,
+            dimensions,
+            factor,
+            buffer: vec![0; dimensions.count()],
+            gamma,
+        })
+    }
+
+    /// Redraws the window with the image being generated by `generator`.
+    fn draw(&mut self, generator: &Generator) -> minifb::Result<()> {
+        let pixmap = generator.pixmap();
+        let width = self.dimensions.width;
+        for (i, pixel) in self.buffer.iter_mut().enumerate() {
+            let pos = Position::new(
+                i % width * self.factor,
+                i / width * self.factor,
+            );
+            if let Some(color) = pixmap.get(pos) {
+                *pixel = color.powf(self.gamma).to_u32();
+            }
+        }
+        self.window.update_with_buffer(
+            &self.buffer,
+            self.dimensions.width,
+            self.dimensions.height,
+        )
+    }
+
+    /// Whether the window is still open. Pressing Escape closes it.
+    fn is_open(&self) -> bool {
+        self.window.is_open() && !self.window.is_key_down(Key::Escape)
+    }
+}
+
+/// Fills the image being generated by `generator` while showing it in a
+/// window, with a progress bar if `progress` is true. Closing the window
+/// interrupts generation, as if Ctrl-C were pressed. Returns how long was
+/// spent filling the image.
+///
+/// If the window can't be opened, this prints a warning and fills nothing,
+/// so the image is generated as usual.
+pub fn watch(
+    generator: &mut Generator,
+    gamma: Float,
+    progress: bool,
+) -> Duration {
+    let dim = generator.pixmap().dimensions();
+    let mut preview = match Preview::new(dim, gamma) {
+        Ok(preview) => preview,
+        Err(e) => {
+            warning!("could not open window: {e}");
+            return Duration::ZERO;
+        }
+    };
+    let mut bar = ProgressBar::new(progress);
+    let start = Instant::now();
+    let mut fill = Duration::ZERO;
+    let step = dim.height / 100 + 1;
+    let mut more = true;
+    while more {
+        let frame = Instant::now();
+        while more && frame.elapsed() < REDRAW_INTERVAL {
+            more = generator.step_rows(step);
+        }
+        fill += frame.elapsed();
+        let rows = generator.rows_filled();
+        bar.update(Progress::new(dim, rows, start.elapsed()));
+        if let Err(e) = preview.draw(generator) {
+            warning!("could not update window: {e}");
+            break;
+        }
+        if !preview.is_open() {
+            interrupt::flag().store(true, Ordering::Relaxed);
+            break;
+        }
+    }
+    bar.finish();
+    fill
+}
@@ -17,14 +17,30 @@
  * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use super::math;
 use super::Float;
+use core::fmt;
 use core::ops::{Add, Div, Mul, Sub};
 use core::ops::{AddAssign, DivAssign, MulAssign, SubAssign};
 use rand::Rng;
-use serde::{Deserialize, Serialize};
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+#[cfg(feature = "palette")]
+mod palette;
 
 /// The color of a pixel in an image. Each component is between 0 and 1.
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+///
+/// Colors are serialized as hex strings like `"#008080"` when that can be
+/// done without losing precision, and as their components otherwise. When
+/// deserializing, a color can be given as a hex string, as its components,
+/// or as one of the standard CSS color names, like `"teal"`.
+///
+/// With the `bytemuck` feature, colors implement [`Pod`](bytemuck::Pod), so
+/// a slice of them can be reinterpreted as a slice of [`Float`] components.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
 pub struct Color {
     pub red: Float,
     pub green: Float,
@@ -39,6 +55,35 @@ impl Color {
         blue: 0.0,
     };
 
+    /// Looks up a standard CSS color name, like `"rebeccapurple"`. Names are
+    /// case-insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let key = |entry: &(&str, u32)| {
+            entry.0.bytes().cmp(name.bytes().map(|b| b.to_ascii_lowercase()))
+        };
+        let i = NAMED_COLORS.binary_search_by(key).ok()?;
+        Some(Self::from_u32(NAMED_COLORS[i].1))
+    }
+
+    /// Parses a hex color like `"#008080"` or `"#088"`. The leading `#` is
+    /// optional.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let rgb = u32::from_str_radix(digits, 16).ok()?;
+        match digits.len() {
+            6 => Some(Self::from_u32(rgb)),
+            3 => Some(Self::from_u32(
+                (rgb & 0xf00) * 0x1100
+                    + (rgb & 0xf0) * 0x110
+                    + (rgb & 0xf) * 0x11,
+            )),
+            _ => None,
+        }
+    }
+
     /// Generates a random color.
     pub fn random<R: Rng>(mut rng: R) -> Self {
         Self {
@@ -48,12 +93,56 @@ impl Color {
         }
     }
 
-    /// Calls [`powf`](Float::powf) on each component.
+    /// Converts 8-bit red, green, and blue components to a color.
+    pub fn from_rgb8([red, green, blue]: [u8; 3]) -> Self {
+        let conv = |n: u8| Float::from(n) / 255.0;
+        Self {
+            red: conv(red),
+            green: conv(green),
+            blue: conv(blue),
+        }
+    }
+
+    /// Converts the color to 8-bit red, green, and blue components. Each
+    /// component is clamped to between 0 and 1 and rounded to the nearest
+    /// multiple of 1/255. NaN components become 0.
+    pub fn to_rgb8(self) -> [u8; 3] {
+        // `as` saturates out-of-range values and converts NaN to 0.
+        let conv = |n: Float| math::round(n.clamp(0.0, 1.0) * 255.0) as u8;
+        [conv(self.red), conv(self.green), conv(self.blue)]
+    }
+
+    /// Converts a packed `0xRRGGBB` value to a color. The upper 8 bits are
+    /// ignored.
+    pub fn from_u32(rgb: u32) -> Self {
+        let [_, red, green, blue] = rgb.to_be_bytes();
+        Self::from_rgb8([red, green, blue])
+    }
+
+    /// Converts the color to a packed `0xRRGGBB` value, as with
+    /// [`Self::to_rgb8`].
+    pub fn to_u32(self) -> u32 {
+        let [red, green, blue] = self.to_rgb8();
+        u32::from_be_bytes([0, red, green, blue])
+    }
+
+    /// Converts the color to a `0xRRGGBB` value, if that can be done without
+    /// losing precision.
+    fn to_exact_u32(self) -> Option<u32> {
+        let rgb = self.to_u32();
+        let exact = Self::from_u32(rgb);
+        (exact.red == self.red
+            && exact.green == self.green
+            && exact.blue == self.blue)
+            .then_some(rgb)
+    }
+
+    /// Raises each component to the power of `n`.
     pub fn powf(self, n: Float) -> Self {
         Self {
-            red: self.red.powf(n),
-            green: self.green.powf(n),
-            blue: self.blue.powf(n),
+            red: math::powf(self.red, n),
+            green: math::powf(self.green, n),
+            blue: math::powf(self.blue, n),
         }
     }
 
@@ -65,8 +154,47 @@ impl Color {
             blue: self.blue.clamp(min, max),
         }
     }
+
+    /// Calls [`min`](Float::min) on each component.
+    pub fn min(self, other: Self) -> Self {
+        Self {
+            red: self.red.min(other.red),
+            green: self.green.min(other.green),
+            blue: self.blue.min(other.blue),
+        }
+    }
+
+    /// Calls [`max`](Float::max) on each component.
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            red: self.red.max(other.red),
+            green: self.green.max(other.green),
+            blue: self.blue.max(other.blue),
+        }
+    }
+
+    /// Linearly interpolates between `self` and `other`. A `t` of 0 gives
+    /// `self`, and a `t` of 1 gives `other`.
+    pub fn lerp(self, other: Self, t: Float) -> Self {
+        self + (other - self) * t
+    }
+
+    /// The luminance of the color, using the Rec. 709 coefficients. No gamma
+    /// correction is applied.
+    pub fn luminance(self) -> Float {
+        0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
+    }
 }
 
+// SAFETY: `Color` is `repr(C)` and consists of three `Float`s, which are
+// `Pod`, with no padding.
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Zeroable for Color {}
+
+// SAFETY: See above.
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Pod for Color {}
+
 impl Add for Color {
     type Output = Self;
 
@@ -103,6 +231,30 @@ impl Mul<Float> for Color {
     }
 }
 
+impl Mul for Color {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            red: self.red * rhs.red,
+            green: self.green * rhs.green,
+            blue: self.blue * rhs.blue,
+        }
+    }
+}
+
+impl Div for Color {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        Self {
+            red: self.red / rhs.red,
+            green: self.green / rhs.green,
+            blue: self.blue / rhs.blue,
+        }
+    }
+}
+
 impl Div<Float> for Color {
     type Output = Self;
 
@@ -138,3 +290,264 @@ impl DivAssign<Float> for Color {
         *self = *self / rhs
     }
 }
+
+impl MulAssign for Color {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs
+    }
+}
+
+impl DivAssign for Color {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs
+    }
+}
+
+/// The components of a [`Color`], used to derive its component-wise
+/// serialization.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Color")]
+struct Components {
+    red: Float,
+    green: Float,
+    blue: Float,
+}
+
+impl From<Components> for Color {
+    fn from(c: Components) -> Self {
+        Self {
+            red: c.red,
+            green: c.green,
+            blue: c.blue,
+        }
+    }
+}
+
+impl From<Color> for Components {
+    fn from(c: Color) -> Self {
+        Self {
+            red: c.red,
+            green: c.green,
+            blue: c.blue,
+        }
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let Some(rgb) = self.to_exact_u32() else {
+            return Components::from(*self).serialize(serializer);
+        };
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+        let mut hex = *b"#000000";
+        for (i, b) in hex[1..].iter_mut().rev().enumerate() {
+            *b = DIGITS[(rgb >> (i * 4)) as usize & 0xf];
+        }
+        // `hex` contains only ASCII characters.
+        serializer.serialize_str(core::str::from_utf8(&hex).unwrap())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
+
+struct ColorVisitor;
+
+impl<'de> Visitor<'de> for ColorVisitor {
+    type Value = Color;
+
+    fn expecting(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "color components, a hex color, or a CSS color name")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let color = if s.starts_with('#') {
+            Color::from_hex(s)
+        } else {
+            Color::from_name(s)
+        };
+        color.ok_or_else(|| E::invalid_value(de::Unexpected::Str(s), &self))
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        Components::deserialize(MapAccessDeserializer::new(map))
+            .map(Into::into)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        Components::deserialize(SeqAccessDeserializer::new(seq))
+            .map(Into::into)
+    }
+}
+
+/// The standard CSS color names, sorted by name, with their `0xRRGGBB`
+/// values.
+#[rustfmt::skip]
+static NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
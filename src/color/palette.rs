@@ -0,0 +1,67 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Conversions between [`Color`] and the [`palette`] crate's types.
+
+use super::Color;
+use crate::Float;
+use palette::{FromColor, LinSrgb, Oklab, Srgb};
+
+/// Plumage colors are nonlinear sRGB, so this conversion is lossless.
+impl From<Srgb<Float>> for Color {
+    fn from(rgb: Srgb<Float>) -> Self {
+        Self {
+            red: rgb.red,
+            green: rgb.green,
+            blue: rgb.blue,
+        }
+    }
+}
+
+impl From<Color> for Srgb<Float> {
+    fn from(color: Color) -> Self {
+        Self::new(color.red, color.green, color.blue)
+    }
+}
+
+impl From<LinSrgb<Float>> for Color {
+    fn from(rgb: LinSrgb<Float>) -> Self {
+        Srgb::from_linear(rgb).into()
+    }
+}
+
+impl From<Color> for LinSrgb<Float> {
+    fn from(color: Color) -> Self {
+        Srgb::from(color).into_linear()
+    }
+}
+
+/// Colors outside the sRGB gamut have components outside 0 to 1; use
+/// [`Color::clamp`] if needed.
+impl From<Oklab<Float>> for Color {
+    fn from(lab: Oklab<Float>) -> Self {
+        Srgb::from_color(lab).into()
+    }
+}
+
+impl From<Color> for Oklab<Float> {
+    fn from(color: Color) -> Self {
+        Self::from_color(Srgb::from(color))
+    }
+}
@@ -17,11 +17,20 @@
  * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use core::fmt::{self, Display};
 use core::ops::{Add, AddAssign, Sub, SubAssign};
+use core::str::FromStr;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::{Deserialize, Serialize};
 
 /// The dimensions of an image.
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+///
+/// Dimensions can be parsed from strings like `"3840x2160"`, or from one of
+/// the presets `720p`, `1080p`, `1440p`, `4k` (`2160p`), and `8k`
+/// (`4320p`). When deserializing, such strings are accepted in addition to
+/// the usual width and height.
+#[derive(Clone, Copy, Debug, Serialize)]
 pub struct Dimensions {
     pub width: usize,
     pub height: usize,
@@ -73,6 +82,101 @@ impl Dimensions {
     }
 }
 
+/// Common image sizes, accepted by [`Dimensions::from_str`].
+const PRESETS: [(&str, Dimensions); 7] = [
+    ("720p", Dimensions::new(1280, 720)),
+    ("1080p", Dimensions::new(1920, 1080)),
+    ("1440p", Dimensions::new(2560, 1440)),
+    ("2160p", Dimensions::new(3840, 2160)),
+    ("4k", Dimensions::new(3840, 2160)),
+    ("4320p", Dimensions::new(7680, 4320)),
+    ("8k", Dimensions::new(7680, 4320)),
+];
+
+/// An error returned when parsing [`Dimensions`] from a string.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParseDimensionsError;
+
+impl Display for ParseDimensionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "dimensions must be `<width>x<height>` or a preset like `4k`"
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseDimensionsError {}
+
+impl FromStr for Dimensions {
+    type Err = ParseDimensionsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, dim)) =
+            PRESETS.iter().find(|(name, _)| name.eq_ignore_ascii_case(s))
+        {
+            return Ok(*dim);
+        }
+        let (width, height) =
+            s.split_once(['x', 'X']).ok_or(ParseDimensionsError)?;
+        let parse =
+            |n: &str| n.trim().parse().map_err(|_| ParseDimensionsError);
+        Ok(Self::new(parse(width)?, parse(height)?))
+    }
+}
+
+/// The fields of [`Dimensions`], used to derive its usual deserialization.
+#[derive(Deserialize)]
+#[serde(rename = "Dimensions")]
+struct Fields {
+    width: usize,
+    height: usize,
+}
+
+impl<'de> Deserialize<'de> for Dimensions {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(DimensionsVisitor)
+    }
+}
+
+struct DimensionsVisitor;
+
+impl<'de> Visitor<'de> for DimensionsVisitor {
+    type Value = Dimensions;
+
+    fn expecting(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "a width and height, or a string like \"3840x2160\"")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        s.parse().map_err(|_| E::invalid_value(de::Unexpected::Str(s), &self))
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let f = Fields::deserialize(MapAccessDeserializer::new(map))?;
+        Ok(Dimensions::new(f.width, f.height))
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let f = Fields::deserialize(SeqAccessDeserializer::new(seq))?;
+        Ok(Dimensions::new(f.width, f.height))
+    }
+}
+
 impl From<Position> for Dimensions {
     fn from(pos: Position) -> Self {
         Self {
@@ -0,0 +1,84 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{Pixel, Pixmap};
+use core::fmt::{self, Display};
+
+/// A stable hash of an image's final 8-bit pixels, for checking that params
+/// reproduce an image exactly, even on another machine.
+///
+/// The hash is the [BLAKE3](https://github.com/BLAKE3-team/BLAKE3) hash of
+/// each pixel's red, green, and blue bytes, in row-major order. It doesn't
+/// depend on the output format, and it's displayed as 64 hex digits.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Fingerprint(pub [u8; 32]);
+
+impl Fingerprint {
+    /// Computes the fingerprint of a finished image, like one returned by
+    /// [`Generator::into_pixmap`](crate::Generator::into_pixmap).
+    pub fn of_pixmap<P: Pixel>(pixmap: &Pixmap<P>) -> Self {
+        let mut hasher = Hasher::new();
+        for (_, pixel) in pixmap.enumerate_pixels() {
+            hasher.push(pixel.to_color().to_rgb8());
+        }
+        hasher.finish()
+    }
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// How many bytes [`Hasher`] buffers before hashing them.
+const BUFFER_SIZE: usize = 3 * 256;
+
+/// Computes a [`Fingerprint`] incrementally. Pixels are buffered, as hashing
+/// them one at a time is slow.
+pub(crate) struct Hasher {
+    hasher: blake3::Hasher,
+    buffer: [u8; BUFFER_SIZE],
+    len: usize,
+}
+
+impl Hasher {
+    pub fn new() -> Self {
+        Self {
+            hasher: blake3::Hasher::new(),
+            buffer: [0; BUFFER_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Hashes the red, green, and blue bytes of a pixel.
+    pub fn push(&mut self, rgb: [u8; 3]) {
+        if self.len == BUFFER_SIZE {
+            self.hasher.update(&self.buffer);
+            self.len = 0;
+        }
+        self.buffer[self.len..][..3].copy_from_slice(&rgb);
+        self.len += 3;
+    }
+
+    pub fn finish(mut self) -> Fingerprint {
+        self.hasher.update(&self.buffer[..self.len]);
+        Fingerprint(*self.hasher.finalize().as_bytes())
+    }
+}
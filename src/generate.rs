@@ -17,182 +17,860 @@
  * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use super::{Color, Float, Params, Pixmap, Position, Spread};
-use rand::{Rng, SeedableRng};
+#[cfg(feature = "std")]
+use super::fingerprint::Fingerprint;
+use super::fingerprint::Hasher;
+use super::math;
+use super::pixel::{self, Pixel};
+use super::{Color, Dimensions, Expr, Float, Params, Pixmap, Position};
+use super::{Border, Duotone, Repeat, Rotation, Storage};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::num::NonZeroUsize;
+use core::sync::atomic::AtomicBool;
+use fill::{Filler, Stop};
+use orientation::Orientation;
+use rand::{Rng, RngCore, SeedableRng};
 use rand_chacha::ChaChaRng;
 #[cfg(feature = "std")]
 use std::io::{self, Write};
+use timings::Instant;
+
+mod approx;
+#[cfg(feature = "tokio")]
+mod async_write;
+mod bmp;
+mod fill;
+mod kernel;
+mod orientation;
+mod pass;
+mod progress;
+mod sum;
+mod timings;
+
+pub use pass::Pass;
+pub use progress::Progress;
+pub use timings::Timings;
+
+/// Applies gamma correction to a single pixel.
+fn gamma_correct<P: Pixel>(pixel: P, gamma: Float, fast_math: bool) -> P {
+    pixel::map(pixel, |n| {
+        if fast_math {
+            approx::powf(n, gamma)
+        } else {
+            math::powf(n, gamma)
+        }
+    })
+}
+
+/// The gamma at `pos`, which is `gamma` unless overridden by `expr`. Values
+/// from `expr` are clamped to be positive, as [`Params::validate`]
+/// requires.
+fn gamma_at(
+    gamma: Float,
+    expr: Option<&Expr>,
+    pos: Position,
+    dim: Dimensions,
+) -> Float {
+    expr.map_or(gamma, |expr| expr.eval(pos, dim).max(Float::MIN_POSITIVE))
+}
+
+/// The random number generator used to fill the image.
+// Only one of these exists per generator, so the size difference doesn't
+// matter, and boxing the default generator would add an indirection to
+// every random number.
+#[allow(clippy::large_enum_variant)]
+enum GenRng {
+    /// Seeded from [`Params::seed`]. This generator can be seeked, which
+    /// allows the image to be filled in parallel.
+    Seeded(ChaChaRng),
+    /// Supplied with [`Generator::rng`].
+    Custom(Box<dyn RngCore + Send + Sync>),
+}
+
+/// Counts the 32-bit words drawn from an RNG, as with
+/// [`Generator::count_rng_draws`].
+struct CountingRng<'a> {
+    rng: &'a mut dyn RngCore,
+    words: u64,
+}
+
+impl RngCore for CountingRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        self.words += 1;
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.words += 2;
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.words += dest.len().div_ceil(4) as u64;
+        self.rng.fill_bytes(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.words += dest.len().div_ceil(4) as u64;
+        self.rng.try_fill_bytes(dest)
+    }
+}
 
-/// Generates and writes the image.
-pub struct Generator {
-    spread: Spread,
-    distance_power: Float,
-    random_power: Float,
-    random_max: Float,
+/// Generates and writes the image. By default, each pixel is a [`Color`],
+/// but any [`Pixel`] type can be generated.
+pub struct Generator<P: Pixel = Color> {
+    filler: Filler,
     gamma: Float,
-    data: Pixmap,
-    rng: ChaChaRng,
+    /// An expression that overrides `gamma` at each pixel.
+    gamma_expr: Option<Expr>,
+    fast_math: bool,
+    /// The dimensions of the full image as it's filled, which are swapped
+    /// from the output dimensions when filling vertically.
+    dimensions: Dimensions,
+    /// Maps the image as it's filled to the output. Once filling is done,
+    /// the image is reoriented and this becomes the identity.
+    orientation: Orientation,
+    /// Maps the image onto inks after gamma correction. Once applied, this
+    /// becomes [`None`]. In streaming mode, it's applied to each row as it's
+    /// read.
+    duotone: Option<Duotone>,
+    /// Rotates the image once it's filled. Once rotated, this becomes the
+    /// identity.
+    rotation: Rotation,
+    /// Repeats the image once it's rotated. Once repeated, this becomes the
+    /// identity.
+    repeat: Repeat,
+    /// Drawn around the image once it's repeated. Once drawn, this becomes
+    /// the identity.
+    border: Border,
+    /// The image, or in streaming mode, the most recently filled rows.
+    data: Pixmap<P>,
+    streaming: bool,
+    threads: NonZeroUsize,
+    rows_filled: usize,
+    rng: GenRng,
+    /// The number of words drawn from `rng`, if they're being counted.
+    rng_words: Option<u64>,
+    interrupt: Option<Arc<AtomicBool>>,
+    #[cfg(feature = "std")]
+    deadline: Option<std::time::Instant>,
+    /// Passes applied after gamma correction.
+    passes: Vec<Box<dyn Pass>>,
 }
 
 impl Generator {
     /// Creates a new [`Generator`].
     pub fn new(params: Params) -> Self {
-        let rng = ChaChaRng::from_seed(params.seed);
-        let mut data = Pixmap::new(params.dimensions);
-        data[Position::new(0, 0)] = params.start_color;
+        Self::with_storage(params, Storage::Float)
+    }
+
+    /// Creates a new [`Generator`] whose image is stored as described by
+    /// `storage`.
+    pub fn with_storage(params: Params, storage: Storage) -> Self {
+        Self::new_generic(params, storage)
+    }
+
+    /// Creates a new [`Generator`] in streaming mode. Instead of the entire
+    /// image, only the rows needed to fill the next row are kept in memory,
+    /// and each row is written as soon as it's complete. This allows
+    /// generating images much larger than available memory.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if [`Params::start_corner`] is a bottom corner,
+    /// [`Params::direction`] is vertical, or [`Params::rotation`],
+    /// [`Params::repeat`], or [`Params::border`] isn't the identity, as rows
+    /// then can't be written until the entire image is filled.
+    pub fn streaming(params: Params, storage: Storage) -> Self {
+        Self::streaming_generic(params, storage)
+    }
+
+    /// Creates a new [`Generator`] that uses `rng` instead of generating
+    /// random numbers from [`Params::seed`]. See [`Self::rng`].
+    pub fn with_rng<R>(params: Params, rng: R) -> Self
+    where
+        R: Rng + Send + Sync + 'static,
+    {
+        Self::new(params).rng(rng)
+    }
+}
+
+impl<P: Pixel> Generator<P> {
+    /// Creates a new [`Generator`] with any pixel type, storing the image
+    /// as described by `storage`. The start color is converted with
+    /// [`Pixel::from_color`].
+    pub fn new_generic(params: Params, storage: Storage) -> Self {
+        let height = Orientation::new(&params).fill_dimensions().height;
+        Self::with_window(params, storage, height, false)
+    }
+
+    /// Like [`Self::new_generic`], but creates the generator in streaming
+    /// mode, as with [`Generator::streaming`].
+    pub fn streaming_generic(params: Params, storage: Storage) -> Self {
+        assert!(
+            Orientation::new(&params).preserves_rows(),
+            "streaming mode must start at a top corner and fill horizontally",
+        );
+        assert!(
+            params.rotation.is_identity(),
+            "streaming mode can't rotate the image",
+        );
+        assert!(
+            params.repeat.is_identity(),
+            "streaming mode can't repeat the image",
+        );
+        assert!(
+            params.border.is_identity(),
+            "streaming mode can't draw a border",
+        );
+        let height = params.spread.bounds().height;
+        let height = height.min(params.dimensions.height);
+        Self::with_window(params, storage, height, true)
+    }
+
+    /// Creates a new [`Generator`] that keeps `height` rows in memory.
+    fn with_window(
+        params: Params,
+        storage: Storage,
+        height: usize,
+        streaming: bool,
+    ) -> Self {
+        let rng = GenRng::Seeded(ChaChaRng::from_seed(params.seed));
+        let orientation = Orientation::new(&params);
+        let dimensions = orientation.fill_dimensions();
+        let window = Dimensions::new(dimensions.width, height);
+        let mut data = Pixmap::new_generic(window, storage);
+        data.set(Position::new(0, 0), P::from_color(params.start_color));
         Self {
-            spread: params.spread,
-            distance_power: params.distance_power,
-            random_power: params.random_power,
-            random_max: params.random_max,
+            filler: Filler::new(&params, height),
             gamma: params.gamma,
+            gamma_expr: params.expressions.gamma.clone(),
+            fast_math: params.fast_math,
+            dimensions,
+            orientation,
+            duotone: params.duotone,
+            rotation: params.rotation,
+            repeat: params.repeat,
+            border: params.border,
             data,
+            streaming,
+            threads: params.threads.unwrap_or(NonZeroUsize::MIN),
+            rows_filled: 0,
             rng,
+            rng_words: None,
+            interrupt: None,
+            #[cfg(feature = "std")]
+            deadline: None,
+            passes: Vec::new(),
         }
     }
 
-    /// Calculates the average color near a pixel.
+    /// Sets the maximum number of threads used to generate the image,
+    /// overriding [`Params::threads`]. The output is the same regardless of
+    /// the number of threads.
     ///
-    /// # Safety
+    /// Only one thread is used in streaming mode or without the `std`
+    /// feature. Using more threads than there are CPUs will slow down
+    /// generation.
+    pub fn threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Uses `rng` instead of generating random numbers from
+    /// [`Params::seed`]. This allows any source of randomness to be used,
+    /// like a mock generator in tests, but only one thread is used to fill
+    /// the image, as filling in parallel requires seeking within the
+    /// stream of random numbers.
+    ///
+    /// # Panics
     ///
-    /// `pos.x` and `pos.y` must be less than the image width and height,
-    /// respectively.
-    unsafe fn avg_neighbor_unchecked(&self, pos: Position) -> Color {
-        let mut count = 0.0;
-        let mut avg = Color::BLACK;
+    /// This method panics if any rows have already been filled.
+    pub fn rng<R>(mut self, rng: R) -> Self
+    where
+        R: Rng + Send + Sync + 'static,
+    {
+        assert!(self.rows_filled == 0, "rows have already been filled");
+        self.rng = GenRng::Custom(Box::new(rng));
+        self
+    }
 
-        let bounds = self.spread.bounds();
-        let bounds = bounds.min((pos + Position::new(1, 1)).into());
-        bounds.for_each(|delta| {
-            // Skip the pixel we haven't filled yet.
-            if delta == Position::ZERO {
-                return;
-            }
+    /// Counts the 32-bit words drawn from the random number generator,
+    /// recording the count in [`Timings::rng_words`]. The count depends
+    /// only on the params, so comparing it across changes shows whether
+    /// they've shifted the stream of random numbers, which would change
+    /// every image. While counting, the image is filled a row at a time on
+    /// one thread, which is slower.
+    pub fn count_rng_draws(mut self) -> Self {
+        self.rng_words.get_or_insert(0);
+        self
+    }
 
-            let dx = delta.x as Float;
-            let dy = delta.y as Float;
-            let dist = (dx.powf(2.0) + dy.powf(2.0)).powf(0.5);
+    /// Stops filling the image once `flag` is set, as when the user asks
+    /// to cancel. The image is still written at its full size, with the
+    /// rows that weren't filled left black, so an interrupted render
+    /// produces a partial image rather than nothing.
+    ///
+    /// Filling stops at the end of a row, or in parallel, once every row
+    /// already started is done. Very wide images are filled in bands of
+    /// rows, which stop at the end of a band.
+    pub fn interrupt_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(flag);
+        self
+    }
 
-            if let Spread::QuarterCircle {
-                radius,
-            } = self.spread
-            {
-                if dist > radius as Float {
-                    return;
-                }
-            }
+    /// Stops filling the image once `deadline` has passed, as when
+    /// generation has a fixed time budget. This behaves like
+    /// [`Self::interrupt_flag`]: the image is still written, with the rows
+    /// that weren't filled left black.
+    #[cfg(feature = "std")]
+    pub fn deadline(mut self, deadline: std::time::Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
 
-            let neighbor = pos - delta;
-            // SAFETY: `delta` cannot be greater than `pos`, so `neighbor` is
-            // valid.
-            let color = unsafe { self.data.get_unchecked(neighbor) };
-            let weight = dist.powf(self.distance_power);
-            avg += color * weight;
-            count += weight;
-        });
-        avg / count
+    /// Adds a pass, which is applied after gamma correction and any passes
+    /// added before it. Passes aren't recorded in the params, so the image
+    /// can no longer be reproduced from them alone.
+    ///
+    /// In streaming mode, passes are applied to one row at a time.
+    pub fn pass<T: Pass + 'static>(mut self, pass: T) -> Self {
+        self.passes.push(Box::new(pass));
+        self
     }
 
-    /// Generates a random color similar to `color`.
-    fn random_near(&mut self, color: Color) -> Color {
-        let mut component = || {
-            let n: Float = self.rng.gen();
-            let n = n.powf(self.random_power) * self.random_max;
-            let positive: bool = self.rng.gen();
-            n * Float::from(positive as i8 * 2 - 1)
-        };
-        let delta = Color {
-            red: component(),
-            green: component(),
-            blue: component(),
-        };
-        (color + delta).clamp(0.0, 1.0)
+    /// When filling should stop early: once the interrupt flag set with
+    /// [`Self::interrupt_flag`] is set, or the deadline set with
+    /// [`Self::deadline`] has passed.
+    fn stop(&self) -> Stop<'_> {
+        Stop {
+            flag: self.interrupt.as_deref(),
+            #[cfg(feature = "std")]
+            deadline: self.deadline,
+        }
     }
 
-    /// Fills a single pixel.
+    /// Whether generation has been interrupted or its deadline has passed.
+    fn interrupted(&self) -> bool {
+        self.stop().is_set()
+    }
+
+    /// Fills the next unfilled row of the image.
     ///
-    /// # Safety
+    /// # Panics
     ///
-    /// `pos.x` and `pos.y` must be less than the image width and height,
-    /// respectively.
-    unsafe fn fill_pos_unchecked(&mut self, pos: Position) {
-        // SAFETY: Checked by caller.
-        let neighbor = unsafe { self.avg_neighbor_unchecked(pos) };
-        let color = self.random_near(neighbor);
-        // SAFETY: Checked by caller.
-        *unsafe { self.data.get_unchecked_mut(pos) } = color;
+    /// This method panics if every row has already been filled.
+    fn fill_next_row(&mut self) {
+        let dim = self.dimensions;
+        let y = self.rows_filled;
+        assert!(y < dim.height, "no rows left to fill");
+        let data = self.data.raw();
+        // SAFETY: `y` is less than the height, and `data` is used only by
+        // this thread.
+        unsafe {
+            match (&mut self.rng, &mut self.rng_words) {
+                (rng, Some(words)) => {
+                    let rng: &mut dyn RngCore = match rng {
+                        GenRng::Seeded(rng) => rng,
+                        GenRng::Custom(rng) => &mut **rng,
+                    };
+                    let mut rng = CountingRng {
+                        rng,
+                        words: 0,
+                    };
+                    let rng = &mut rng;
+                    self.filler.fill_row_unchecked(&data, rng, y, dim.width);
+                    *words += rng.words;
+                }
+                (GenRng::Seeded(rng), None) => {
+                    self.filler.fill_row_unchecked(&data, rng, y, dim.width);
+                }
+                (GenRng::Custom(rng), None) => {
+                    let rng = &mut **rng;
+                    self.filler.fill_row_unchecked(&data, rng, y, dim.width);
+                }
+            }
+        }
+        self.rows_filled += 1;
+        #[cfg(debug_assertions)]
+        if let GenRng::Seeded(rng) = &self.rng {
+            let pixels = ((y + 1) * dim.width - 1) as u128;
+            let words = pixels * fill::words_per_pixel::<P>();
+            debug_assert_eq!(rng.get_word_pos(), words);
+        }
     }
 
-    /// Fills every pixel in the image.
-    fn fill(&mut self) {
-        self.data.dimensions().for_each(|pos| {
-            // Don't fill the starting pixel.
-            if pos == Position::ZERO {
+    /// Fills rows until `end` rows have been filled, calling `on_row` with
+    /// the number of rows filled so far after each row, or in very wide
+    /// images, after each band of rows (see [`Filler::fill_strips`]).
+    fn fill_until<R: FnMut(usize)>(&mut self, end: usize, mut on_row: R) {
+        let start = self.rows_filled;
+        if start >= end {
+            return;
+        }
+        // Counting draws requires filling in order on this thread.
+        let counting = self.rng_words.is_some();
+        // Not `self.stop()`, which would borrow all of `self`.
+        let stop = Stop {
+            flag: self.interrupt.as_deref(),
+            #[cfg(feature = "std")]
+            deadline: self.deadline,
+        };
+        #[cfg(feature = "std")]
+        if let GenRng::Seeded(rng) = &mut self.rng {
+            if self.threads.get() > 1 && !self.streaming && !counting {
+                let width = self.dimensions.width;
+                let data = self.data.raw();
+                // SAFETY: We're not in streaming mode, so `data` holds the
+                // entire image, and the image is not empty, as we were able
+                // to set the starting pixel. `end` is at most the image
+                // height.
+                let filled = unsafe {
+                    self.filler.fill_parallel(
+                        &data,
+                        rng,
+                        start..end,
+                        self.threads.get(),
+                        stop,
+                        on_row,
+                    )
+                };
+                self.rows_filled = filled;
+                // The starting pixel doesn't use any random numbers.
+                let index = (filled * width).saturating_sub(1) as u128;
+                rng.set_word_pos(index * fill::words_per_pixel::<P>());
                 return;
             }
-            // SAFETY: We call this method only with valid positions.
-            unsafe {
-                self.fill_pos_unchecked(pos);
+        }
+        // Fill very wide images in strips, so that the rows being averaged
+        // stay in cache.
+        if let GenRng::Seeded(rng) = &mut self.rng {
+            let wide = self.dimensions.width > fill::STRIP_WIDTH;
+            if wide && !self.streaming && !counting {
+                let data = self.data.raw();
+                // SAFETY: We're not in streaming mode, so `data` holds the
+                // entire image, which only this thread accesses. `end` is
+                // at most the image height.
+                self.rows_filled = unsafe {
+                    self.filler.fill_strips(
+                        &data,
+                        rng,
+                        start..end,
+                        stop,
+                        on_row,
+                    )
+                };
+                return;
             }
-        })
+        }
+        while self.rows_filled < end && !self.interrupted() {
+            self.fill_next_row();
+            on_row(self.rows_filled);
+        }
+    }
+
+    /// Fills every remaining pixel in the image, calling `on_row` with the
+    /// number of rows filled so far after each row.
+    fn fill<R: FnMut(usize)>(&mut self, on_row: R) {
+        self.fill_until(self.dimensions.height, on_row);
+    }
+
+    /// Fills up to `n` more rows of the image, returning whether there are
+    /// rows left to fill. This allows generation to be done incrementally;
+    /// [`Self::pixmap`] can be used to view the partially filled image.
+    /// Generating the image afterward fills only the remaining rows.
+    ///
+    /// Once interrupted (see [`Self::interrupt_flag`] and [`Self::deadline`]),
+    /// this returns false.
+    ///
+    /// # Panics
+    ///
+    /// This method panics in streaming mode.
+    pub fn step_rows(&mut self, n: usize) -> bool {
+        assert!(!self.streaming, "cannot step rows in streaming mode");
+        let height = self.dimensions.height;
+        self.fill_until(
+            self.rows_filled.saturating_add(n).min(height),
+            |_| {},
+        );
+        self.rows_filled < height && !self.interrupted()
+    }
+
+    /// The number of rows of the image that have been filled.
+    pub fn rows_filled(&self) -> usize {
+        self.rows_filled
+    }
+
+    /// The number of rows that are filled in all, which is what
+    /// [`Progress::total_rows`] counts. When filling vertically, this is the
+    /// width of the output image.
+    pub fn total_rows(&self) -> usize {
+        self.dimensions.height
+    }
+
+    /// The image being generated. Unfilled pixels are black, and passes
+    /// like gamma correction have not yet been applied. The image is in the
+    /// orientation it's filled in, starting at the top left and proceeding
+    /// a row at a time, regardless of [`Params::start_corner`] and
+    /// [`Params::direction`].
+    ///
+    /// In streaming mode, this holds only the most recently filled rows;
+    /// row `y` of the image is stored in row `y % height`.
+    pub fn pixmap(&self) -> &Pixmap<P> {
+        &self.data
     }
 
     /// Applies gamma correction.
     fn apply_gamma(&mut self) {
-        for color in self.data.data_mut() {
-            *color = color.powf(self.gamma);
+        let (gamma, expr) = (self.gamma, self.gamma_expr.as_ref());
+        let dim = self.dimensions;
+        let fast_math = self.fast_math;
+        self.data.for_each_mut(|pos, pixel| {
+            let gamma = gamma_at(gamma, expr, pos, dim);
+            *pixel = gamma_correct(*pixel, gamma, fast_math);
+        });
+    }
+
+    /// Moves the filled image to its output orientation.
+    fn reorient(&mut self) {
+        let orientation = self.orientation;
+        if orientation.is_identity() {
+            return;
         }
+        let output = orientation.output_dimensions();
+        let mut data = Pixmap::new_generic(output, self.data.storage());
+        self.dimensions.for_each(|pos| {
+            if let Some(pixel) = self.data.get(pos) {
+                data.set(orientation.output_pos(pos), pixel);
+            }
+        });
+        self.data = data;
+        self.dimensions = output;
+        self.orientation = Orientation::identity(output);
+    }
+
+    /// The dimensions of the output, after reorienting, rotating,
+    /// repeating, and adding the border.
+    fn output_dimensions(&self) -> Dimensions {
+        let dim = self.orientation.output_dimensions();
+        let dim = self.rotation.output_dimensions(dim);
+        let dim = self.repeat.output_dimensions(dim);
+        self.border.output_dimensions(dim)
     }
 
-    /// Applies all passes.
-    fn apply_all(&mut self) {
-        self.fill();
+    /// Applies all passes, recording how long each one takes. The image is
+    /// first moved to its output orientation, and rotated and repeated
+    /// after gamma correction, so expressions see a single unrotated
+    /// copy of the image.
+    fn apply_passes(&mut self, timings: &mut Timings) {
+        if !self.orientation.is_identity() {
+            let start = Instant::now();
+            self.reorient();
+            timings.passes.push(("orient", start.elapsed()));
+        }
+        let start = Instant::now();
         self.apply_gamma();
+        timings.passes.push(("gamma", start.elapsed()));
+        if let Some(duotone) = self.duotone.take() {
+            let start = Instant::now();
+            self.data.for_each_mut(|_, pixel| {
+                *pixel = P::from_color(duotone.map(pixel.to_color()));
+            });
+            timings.passes.push(("duotone", start.elapsed()));
+        }
+        if !self.rotation.is_identity() {
+            let start = Instant::now();
+            self.data = self.data.rotate(self.rotation);
+            self.dimensions = self.data.dimensions();
+            self.rotation = Rotation::default();
+            timings.passes.push(("rotate", start.elapsed()));
+        }
+        if !self.repeat.is_identity() {
+            let start = Instant::now();
+            self.data = self.data.repeat(self.repeat);
+            self.dimensions = self.data.dimensions();
+            self.repeat = Repeat::default();
+            timings.passes.push(("repeat", start.elapsed()));
+        }
+        if !self.border.is_identity() {
+            let start = Instant::now();
+            self.data.add_border(self.border);
+            self.dimensions = self.data.dimensions();
+            self.border = Border::default();
+            timings.passes.push(("border", start.elapsed()));
+        }
+        for pass in &self.passes {
+            let start = Instant::now();
+            pass::apply(&mut self.data, &**pass);
+            timings.passes.push((pass.name(), start.elapsed()));
+        }
+    }
+
+    /// Fills the remaining rows of the image and applies all passes,
+    /// returning the finished image instead of writing it.
+    ///
+    /// # Panics
+    ///
+    /// This method panics in streaming mode.
+    pub fn into_pixmap(mut self) -> Pixmap<P> {
+        assert!(!self.streaming, "cannot get pixmap in streaming mode");
+        self.fill(|_| {});
+        self.apply_passes(&mut Timings::default());
+        self.data
     }
 
     #[cfg(feature = "std")]
-    /// Generates an image and writes it to `stream`.
-    pub fn generate<W: Write>(self, mut stream: W) -> io::Result<()> {
+    /// Generates an image and writes it to `stream`, returning how long
+    /// each stage took.
+    pub fn generate<W: Write>(self, mut stream: W) -> io::Result<Timings> {
         self.generate_with(|bytes| stream.write_all(bytes))
     }
 
+    #[cfg(feature = "std")]
+    /// Generates an image and writes it to `stream`, calling `progress`
+    /// after each row is filled. Returns how long each stage took.
+    pub fn generate_with_progress<W, F>(
+        self,
+        mut stream: W,
+        mut progress: F,
+    ) -> io::Result<Timings>
+    where
+        W: Write,
+        F: FnMut(Progress),
+    {
+        let dim = self.dimensions;
+        let start = Instant::now();
+        self.generate_impl(
+            |bytes| stream.write_all(bytes),
+            |rows| progress(Progress::new(dim, rows, start.elapsed())),
+            None,
+        )
+    }
+
+    #[cfg(feature = "std")]
+    /// Like [`Self::generate`], but also returns the [`Fingerprint`] of the
+    /// image.
+    pub fn generate_fingerprinted<W: Write>(
+        self,
+        stream: W,
+    ) -> io::Result<(Timings, Fingerprint)> {
+        self.generate_fingerprinted_with_progress(stream, |_| {})
+    }
+
+    #[cfg(feature = "std")]
+    /// Like [`Self::generate_with_progress`], but also returns the
+    /// [`Fingerprint`] of the image.
+    pub fn generate_fingerprinted_with_progress<W, F>(
+        self,
+        mut stream: W,
+        mut progress: F,
+    ) -> io::Result<(Timings, Fingerprint)>
+    where
+        W: Write,
+        F: FnMut(Progress),
+    {
+        let dim = self.dimensions;
+        let start = Instant::now();
+        let mut hasher = Hasher::new();
+        let timings = self.generate_impl(
+            |bytes| stream.write_all(bytes),
+            |rows| progress(Progress::new(dim, rows, start.elapsed())),
+            Some(&mut hasher),
+        )?;
+        Ok((timings, hasher.finish()))
+    }
+
     /// Generates an image and writes it by calling a custom function.
+    /// Returns how long each stage took.
+    ///
+    /// `push` should append the given bytes when called. Unlike
+    /// [`Self::generate`], this doesn't need the `std` feature.
+    pub fn generate_with<F, E>(self, push: F) -> Result<Timings, E>
+    where
+        F: FnMut(&[u8]) -> Result<(), E>,
+    {
+        self.generate_impl(push, |_| {}, None)
+    }
+
+    /// The final colors of row `y` of the image. In streaming mode, passes
+    /// are applied to the row as it's read.
+    fn row_colors(&self, y: usize) -> impl Iterator<Item = Color> + '_ {
+        // Outside of streaming mode, the image may have been reoriented, so
+        // its height may differ from the filler's.
+        let window_y = if self.streaming {
+            self.filler.window_row(y)
+        } else {
+            y
+        };
+        (0..self.dimensions.width).map(move |x| {
+            let pos = Position::new(x, y);
+            // In streaming mode, rows keep their order, but they may be
+            // mirrored.
+            let fill_x = self.orientation.fill_pos(pos).x;
+            // SAFETY: `fill_x` is less than the width, and `window_row`
+            // always returns a valid row.
+            let pixel = unsafe {
+                self.data.get_unchecked(Position::new(fill_x, window_y))
+            };
+            if !self.streaming {
+                return pixel.to_color();
+            }
+            let expr = self.gamma_expr.as_ref();
+            let gamma = gamma_at(self.gamma, expr, pos, self.dimensions);
+            let pixel = gamma_correct(pixel, gamma, self.fast_math);
+            match self.duotone {
+                Some(duotone) => {
+                    P::from_color(duotone.map(pixel.to_color())).to_color()
+                }
+                None => pixel.to_color(),
+            }
+        })
+    }
+
+    /// Calls `f` with each final color of row `y`, like
+    /// [`Self::row_colors`], but also applies the passes added with
+    /// [`Self::pass`] in streaming mode.
+    fn for_each_color<F: FnMut(Color)>(&self, y: usize, f: F) {
+        if !self.streaming || self.passes.is_empty() {
+            return self.row_colors(y).for_each(f);
+        }
+        let mut colors: Vec<_> = self.row_colors(y).collect();
+        for pass in &self.passes {
+            pass.apply(&mut colors, self.dimensions.width, y);
+        }
+        colors.into_iter().for_each(f);
+    }
+
+    /// Converts row `y` of the image to BMP pixel data, replacing the
+    /// contents of `row`.
+    fn encode_row(&self, y: usize, row: &mut Vec<u8>) {
+        row.clear();
+        self.for_each_color(y, |color| bmp::push_pixel(row, color));
+        bmp::pad_row(row);
+    }
+
+    /// Generates an image and writes it to `buf` as 8-bit RGBA pixels, with
+    /// each row starting `stride` bytes after the previous one. Alpha is
+    /// always 255, and bytes between rows are left untouched. Returns how
+    /// long each stage took.
+    ///
+    /// This is useful for rendering into an existing buffer, like a window
+    /// surface, without an intermediate copy.
+    ///
+    /// # Panics
     ///
-    /// `push` should append the given bytes when called.
-    pub fn generate_with<F, E>(mut self, mut push: F) -> Result<(), E>
+    /// This method panics if `stride` is less than four times the image
+    /// width, or if `buf` is too small to hold the image.
+    pub fn generate_into_rgba(
+        mut self,
+        buf: &mut [u8],
+        stride: usize,
+    ) -> Timings {
+        let dim = self.output_dimensions();
+        let row_len = dim.width * 4;
+        assert!(stride >= row_len, "stride is too small");
+        if dim.height > 0 {
+            let len = stride * (dim.height - 1) + row_len;
+            assert!(buf.len() >= len, "buffer is too small");
+        }
+
+        let mut timings = Timings::default();
+        if !self.streaming {
+            let start = Instant::now();
+            self.fill(|_| {});
+            timings.fill = start.elapsed();
+            self.apply_passes(&mut timings);
+        }
+
+        for y in 0..dim.height {
+            if self.streaming && !self.interrupted() {
+                let start = Instant::now();
+                self.fill_next_row();
+                timings.fill += start.elapsed();
+            }
+
+            let start = Instant::now();
+            let row = &mut buf[y * stride..][..row_len];
+            if self.streaming && y >= self.rows_filled {
+                // As in `generate_impl`, unfilled rows must be written as
+                // black explicitly.
+                for out in row.chunks_exact_mut(4) {
+                    out.copy_from_slice(&[0, 0, 0, 255]);
+                }
+                timings.quantize += start.elapsed();
+                continue;
+            }
+            let mut pixels = row.chunks_exact_mut(4);
+            self.for_each_color(y, |color| {
+                let [red, green, blue] = color.to_rgb8();
+                if let Some(out) = pixels.next() {
+                    out.copy_from_slice(&[red, green, blue, 255]);
+                }
+            });
+            timings.quantize += start.elapsed();
+        }
+        timings.rng_words = self.rng_words;
+        timings
+    }
+
+    /// Generates an image, writing it with `push` and calling `on_row` with
+    /// the number of rows filled so far after each row.
+    /// If `hasher` is given, the final pixels are hashed with it.
+    fn generate_impl<F, E, R>(
+        mut self,
+        mut push: F,
+        mut on_row: R,
+        mut hasher: Option<&mut Hasher>,
+    ) -> Result<Timings, E>
     where
         F: FnMut(&[u8]) -> Result<(), E>,
+        R: FnMut(usize),
     {
-        self.apply_all();
-        let dim = self.data.dimensions();
-
-        // SAFETY: The algorithm we applied ensures no color components can
-        // fall outside [0, 1].
-        let bgr = unsafe { self.data.to_bgr_unchecked() };
-        drop(self.data);
-        let size: u32 = 14 + 40 + bgr.len() as u32;
-
-        // Write bitmap file header.
-        push(b"BM")?;
-        push(&size.to_le_bytes())?;
-        push(b"PLMG")?;
-        push(&(14_u32 + 40).to_le_bytes())?;
-
-        // Write BITMAPINFOHEADER.
-        push(&40_u32.to_le_bytes())?;
-        push(&(dim.width as u32).to_le_bytes())?;
-        push(&(dim.height as u32).wrapping_neg().to_le_bytes())?;
-        push(&1_u16.to_le_bytes())?;
-        push(&24_u16.to_le_bytes())?;
-        push(&0_u32.to_le_bytes())?;
-        push(&0_u32.to_le_bytes())?;
-        push(&96_u32.to_le_bytes())?;
-        push(&96_u32.to_le_bytes())?;
-        push(&0_u32.to_le_bytes())?;
-        push(&0_u32.to_le_bytes())?;
+        let mut timings = Timings::default();
+        let dim = self.output_dimensions();
+        let start = Instant::now();
+        bmp::write_header(&mut push, dim)?;
+        timings.write += start.elapsed();
+
+        if !self.streaming {
+            let start = Instant::now();
+            self.fill(&mut on_row);
+            timings.fill = start.elapsed();
+            self.apply_passes(&mut timings);
+        }
 
         // Write pixel array.
-        push(&bgr)?;
-        Ok(())
+        let mut row = Vec::with_capacity(bmp::row_size(dim.width));
+        for y in 0..dim.height {
+            if self.streaming && !self.interrupted() {
+                let start = Instant::now();
+                self.fill_next_row();
+                timings.fill += start.elapsed();
+                on_row(y + 1);
+            }
+
+            let start = Instant::now();
+            if self.streaming && y >= self.rows_filled {
+                // The row wasn't filled because generation was interrupted,
+                // and the window no longer holds black pixels, so write
+                // black explicitly.
+                row.clear();
+                row.resize(bmp::row_size(dim.width), 0);
+            } else {
+                self.encode_row(y, &mut row);
+            }
+            if let Some(hasher) = &mut hasher {
+                bmp::hash_row(hasher, &row, dim.width);
+            }
+            timings.quantize += start.elapsed();
+
+            let start = Instant::now();
+            push(&row)?;
+            timings.write += start.elapsed();
+        }
+        timings.rng_words = self.rng_words;
+        Ok(timings)
     }
 }
@@ -0,0 +1,70 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Fast approximations of floating-point functions, used when
+//! [`Params::fast_math`](super::super::Params::fast_math) is enabled.
+//!
+//! These have a relative error of roughly 10<sup>-4</sup>, which is
+//! invisible in the output but means images are not bit-identical to those
+//! generated without fast math.
+
+use super::super::Float;
+
+/// Approximates the base-2 logarithm of a positive number.
+fn log2(x: f32) -> f32 {
+    let bits = x.to_bits();
+    let exp = ((bits >> 23) & 0xff) as i32 - 127;
+    // Mantissa in [1, 2).
+    let m = f32::from_bits((bits & 0x007f_ffff) | 0x3f80_0000);
+    let p = -1.741_793_9
+        + (2.821_202_6
+            + (-1.469_956_8 + (0.447_179_55 - 0.056_570_85 * m) * m) * m)
+            * m;
+    exp as f32 + p
+}
+
+/// Approximates 2 raised to the power of `x`.
+fn exp2(x: f32) -> f32 {
+    if x < -126.0 {
+        return 0.0;
+    }
+    if x >= 128.0 {
+        return f32::INFINITY;
+    }
+    let truncated = x as i32;
+    let floor = truncated - ((truncated as f32) > x) as i32;
+    let f = x - floor as f32;
+    let p = 1.0 + f * (0.696_065_6 + f * (0.224_494_34 + f * 0.079_440_24));
+    f32::from_bits(((floor + 127) as u32) << 23) * p
+}
+
+#[allow(clippy::unnecessary_cast)]
+/// Approximates `x` raised to the power of `n`, for `x >= 0`.
+pub fn powf(x: Float, n: Float) -> Float {
+    if x == 0.0 {
+        return if n > 0.0 {
+            0.0
+        } else {
+            Float::INFINITY
+        };
+    }
+    // The approximations work on `f32` regardless of the precision of
+    // `Float`.
+    exp2(n as f32 * log2(x as f32)) as Float
+}
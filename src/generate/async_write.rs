@@ -0,0 +1,98 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::super::Pixel;
+use super::timings::{Instant, Timings};
+use super::{bmp, Generator};
+use core::convert::Infallible;
+use std::io;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::task;
+
+impl<P: Pixel> Generator<P> {
+    /// Asynchronously generates an image and writes it to `stream`,
+    /// returning how long each stage took.
+    ///
+    /// The image is still generated on the current thread, but this yields
+    /// to the executor after filling and writing each group of rows (one
+    /// row per thread used), so other tasks can run during long renders.
+    pub async fn generate_async<W>(
+        mut self,
+        mut stream: W,
+    ) -> io::Result<Timings>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut timings = Timings::default();
+        let dim = self.output_dimensions();
+        let height = self.dimensions.height;
+        let step = self.threads.get();
+
+        let mut header = Vec::new();
+        let mut push = |bytes: &[u8]| {
+            header.extend_from_slice(bytes);
+            Ok::<_, Infallible>(())
+        };
+        let Ok(()) = bmp::write_header(&mut push, dim);
+        let start = Instant::now();
+        stream.write_all(&header).await?;
+        timings.write += start.elapsed();
+
+        if !self.streaming {
+            while self.rows_filled < height && !self.interrupted() {
+                let start = Instant::now();
+                let end = self.rows_filled.saturating_add(step).min(height);
+                self.fill_until(end, |_| {});
+                timings.fill += start.elapsed();
+                task::yield_now().await;
+            }
+            self.apply_passes(&mut timings);
+        }
+
+        let mut row = Vec::with_capacity(bmp::row_size(dim.width));
+        for y in 0..dim.height {
+            if self.streaming && !self.interrupted() {
+                let start = Instant::now();
+                self.fill_next_row();
+                timings.fill += start.elapsed();
+            }
+
+            let start = Instant::now();
+            if self.streaming && y >= self.rows_filled {
+                // As in `generate_impl`, unfilled rows must be written as
+                // black explicitly.
+                row.clear();
+                row.resize(bmp::row_size(dim.width), 0);
+            } else {
+                self.encode_row(y, &mut row);
+            }
+            timings.quantize += start.elapsed();
+
+            let start = Instant::now();
+            stream.write_all(&row).await?;
+            timings.write += start.elapsed();
+            if self.streaming {
+                task::yield_now().await;
+            }
+        }
+        stream.flush().await?;
+        timings.rng_words = self.rng_words;
+        Ok(timings)
+    }
+}
@@ -0,0 +1,80 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::super::fingerprint::Hasher;
+use super::super::{Color, Dimensions};
+use alloc::vec::Vec;
+
+/// The combined size of the bitmap file header and BITMAPINFOHEADER.
+const HEADER_SIZE: u32 = 14 + 40;
+
+/// The number of bytes in a row of pixels, including padding.
+pub fn row_size(width: usize) -> usize {
+    (width * 3).div_ceil(4) * 4
+}
+
+/// Writes the bitmap file header and BITMAPINFOHEADER for an image with the
+/// given dimensions.
+pub fn write_header<F, E>(push: &mut F, dim: Dimensions) -> Result<(), E>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    let size = HEADER_SIZE + (row_size(dim.width) * dim.height) as u32;
+
+    // Write bitmap file header.
+    push(b"BM")?;
+    push(&size.to_le_bytes())?;
+    push(b"PLMG")?;
+    push(&HEADER_SIZE.to_le_bytes())?;
+
+    // Write BITMAPINFOHEADER.
+    push(&40_u32.to_le_bytes())?;
+    push(&(dim.width as u32).to_le_bytes())?;
+    push(&(dim.height as u32).wrapping_neg().to_le_bytes())?;
+    push(&1_u16.to_le_bytes())?;
+    push(&24_u16.to_le_bytes())?;
+    push(&0_u32.to_le_bytes())?;
+    push(&0_u32.to_le_bytes())?;
+    push(&96_u32.to_le_bytes())?;
+    push(&96_u32.to_le_bytes())?;
+    push(&0_u32.to_le_bytes())?;
+    push(&0_u32.to_le_bytes())?;
+    Ok(())
+}
+
+/// Appends a color to a row as a BGR pixel.
+pub fn push_pixel(row: &mut Vec<u8>, color: Color) {
+    let [red, green, blue] = color.to_rgb8();
+    row.extend_from_slice(&[blue, green, red]);
+}
+
+/// Pads a row of pixels to a multiple of four bytes.
+pub fn pad_row(row: &mut Vec<u8>) {
+    row.resize(row.len().div_ceil(4) * 4, 0);
+}
+
+/// Hashes the pixels in an encoded row of the given width, ignoring padding.
+/// Pixels are hashed in RGB order, as described by [`Fingerprint`].
+///
+/// [`Fingerprint`]: super::super::Fingerprint
+pub fn hash_row(hasher: &mut Hasher, row: &[u8], width: usize) {
+    for bgr in row[..width * 3].chunks_exact(3) {
+        hasher.push([bgr[2], bgr[1], bgr[0]]);
+    }
+}
@@ -0,0 +1,588 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::super::math;
+use super::super::pixel::{self, Pixel, MAX_CHANNELS};
+use super::super::pixmap::RawPixmap;
+use super::super::{Expr, Float, Params, Position};
+use super::approx;
+use super::kernel::Kernel;
+use super::orientation::Orientation;
+use super::sum::{FloatSum, PreciseSum, Sum};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, Ordering};
+use rand::Rng;
+use rand_chacha::ChaChaRng;
+#[cfg(feature = "std")]
+use {
+    core::sync::atomic::AtomicUsize,
+    std::{hint, thread},
+};
+
+/// The width of the strips filled by [`Filler::fill_strips`].
+pub const STRIP_WIDTH: usize = 1024;
+
+/// The number of rows filled by [`Filler::fill_strips`] before moving on to
+/// the next strip.
+const BAND_HEIGHT: usize = 64;
+
+/// When to stop filling the image early.
+#[derive(Clone, Copy, Default)]
+pub struct Stop<'a> {
+    /// Stop once this flag is set.
+    pub flag: Option<&'a AtomicBool>,
+    /// Stop once this time has passed.
+    #[cfg(feature = "std")]
+    pub deadline: Option<std::time::Instant>,
+}
+
+impl Stop<'_> {
+    /// Whether filling should stop.
+    pub fn is_set(&self) -> bool {
+        if self.flag.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+            return true;
+        }
+        #[cfg(feature = "std")]
+        if let Some(deadline) = self.deadline {
+            return std::time::Instant::now() >= deadline;
+        }
+        false
+    }
+}
+
+/// The number of 32-bit words of random data consumed by
+/// [`Filler::random_near`]: each channel needs a [`Float`] and a [`bool`].
+pub const fn words_per_pixel<P: Pixel>() -> u128 {
+    P::CHANNELS as u128 * (core::mem::size_of::<Float>() as u128 / 4 + 1)
+}
+
+/// Running sums of the pixels in each row of the spread, used to average
+/// neighboring pixels with [`Params::uniform_averaging`]. A new one must be
+/// used for each row of the image, which is then filled from left to right.
+pub struct RowSums {
+    /// For each row of the kernel, the sum of each channel of the pixels in
+    /// that row of the spread. These are [`f64`] so that repeatedly adding
+    /// and subtracting pixels doesn't lose precision.
+    sums: Vec<[f64; MAX_CHANNELS]>,
+}
+
+/// Fills pixels in an image. This holds everything needed to fill a pixel
+/// except the image data and random number generator, so it can be shared
+/// between threads.
+pub struct Filler {
+    kernel: Kernel,
+    count_power: Float,
+    random_power: Float,
+    random_max: Float,
+    /// Expressions that override `random_power` and `random_max` at each
+    /// pixel.
+    random_power_expr: Option<Expr>,
+    random_max_expr: Option<Expr>,
+    /// Used to evaluate expressions in output coordinates.
+    orientation: Orientation,
+    fast_math: bool,
+    precise_averaging: bool,
+    uniform_averaging: bool,
+    /// The largest value of a color component, which is infinite with
+    /// [`Params::extended_range`].
+    component_max: Float,
+    /// The number of rows of the image kept in memory. Row `y` of the image
+    /// is stored in row `y % window_height`.
+    window_height: usize,
+}
+
+impl Filler {
+    /// Creates a new [`Filler`] for an image that keeps `window_height` rows
+    /// in memory.
+    pub fn new(params: &Params, window_height: usize) -> Self {
+        Self {
+            kernel: Kernel::new(
+                params.spread,
+                params.distance_power,
+                params.fast_math,
+                // Uniform averaging ignores weights, so the spread keeps
+                // its usual shape.
+                params.antialias_spread && !params.uniform_averaging,
+            ),
+            count_power: params.count_power,
+            random_power: params.random_power,
+            random_max: params.random_max,
+            random_power_expr: params.expressions.random_power.clone(),
+            random_max_expr: params.expressions.random_max.clone(),
+            orientation: Orientation::new(params),
+            fast_math: params.fast_math,
+            precise_averaging: params.precise_averaging,
+            uniform_averaging: params.uniform_averaging,
+            component_max: if params.extended_range {
+                Float::INFINITY
+            } else {
+                1.0
+            },
+            window_height,
+        }
+    }
+
+    /// The row of the image data that holds row `y` of the image.
+    pub fn window_row(&self, y: usize) -> usize {
+        if y < self.window_height {
+            y
+        } else {
+            y % self.window_height
+        }
+    }
+
+    /// Calculates the average value near a pixel, accumulating the sum with
+    /// the precision of `S`.
+    ///
+    /// Each neighbor's weighted channels are added as a single SIMD vector
+    /// (see [`Sum`]). Neighbors are added one at a time in a fixed order,
+    /// so rounding, and therefore the image, is the same on every platform.
+    ///
+    /// # Safety
+    ///
+    /// `pos.x` and `pos.y` must be less than the image width and height,
+    /// respectively, and no other thread may be setting any of the pixels
+    /// that this pixel depends on.
+    unsafe fn avg_neighbor_unchecked<P: Pixel, S: Sum<P>>(
+        &self,
+        data: &RawPixmap<'_, P>,
+        pos: Position,
+    ) -> P {
+        let mut sum = S::ZERO;
+        let term = |dx: usize, y: usize, weight: Float| {
+            let neighbor = Position::new(pos.x - dx, self.window_row(y));
+            // SAFETY: `dx` and `pos.y - y` cannot be greater than `pos.x`
+            // and `pos.y`, so `neighbor` is valid, and `window_row` always
+            // returns a valid row.
+            let pixel = unsafe { data.get_unchecked(neighbor) };
+            S::term(pixel, weight)
+        };
+
+        let bounds = self.kernel.bounds();
+        let bounds = bounds.min((pos + Position::new(1, 1)).into());
+        for dy in 0..bounds.height {
+            let y = pos.y - dy;
+            let row = self.kernel.row(dy);
+            let row = &row[..row.len().min(bounds.width)];
+            // Skip the pixel we haven't filled yet.
+            let start = (dy == 0) as usize;
+            for (dx, &weight) in row.iter().enumerate().skip(start) {
+                sum += term(dx, y, weight);
+            }
+        }
+
+        self.normalize(sum.average(self.fast_math), sum.weight())
+    }
+
+    /// Rescales `pixel`, the average of neighbors with a total weight of
+    /// `weight`, so that it's as if the sum were divided by `weight` raised
+    /// to [`Params::count_power`] instead.
+    fn normalize<P: Pixel>(&self, pixel: P, weight: Float) -> P {
+        if self.count_power == 1.0 {
+            return pixel;
+        }
+        let power = 1.0 - self.count_power;
+        let scale = if self.fast_math {
+            approx::powf(weight, power)
+        } else {
+            math::powf(weight, power)
+        };
+        pixel::map(pixel, |n| n * scale)
+    }
+
+    /// The random power and maximum at `pos`. Values from expressions are
+    /// clamped to the ranges that [`Params::validate`] requires.
+    fn random_params(&self, pos: Position) -> (Float, Float) {
+        let eval = |expr: &Expr| {
+            let pos = self.orientation.output_pos(pos);
+            expr.eval(pos, self.orientation.output_dimensions())
+        };
+        let power = self.random_power_expr.as_ref().map_or(
+            self.random_power,
+            |expr| eval(expr).max(Float::MIN_POSITIVE),
+        );
+        let max = match &self.random_max_expr {
+            Some(expr) => match eval(expr) {
+                n if n >= 0.0 => n.min(Float::MAX),
+                // Negative or NaN.
+                _ => 0.0,
+            },
+            None => self.random_max,
+        };
+        (power, max)
+    }
+
+    /// Creates the [`RowSums`] for a new row of the image.
+    pub fn row_sums(&self) -> RowSums {
+        let rows = if self.uniform_averaging {
+            self.kernel.bounds().height
+        } else {
+            0
+        };
+        RowSums {
+            sums: vec![[0.0; MAX_CHANNELS]; rows],
+        }
+    }
+
+    /// Calculates the unweighted average of the pixels near a pixel,
+    /// updating the running sums in `sums`. For each row of the spread,
+    /// the pixel that enters it is added and the pixel that leaves it is
+    /// subtracted, so this takes time proportional to the spread's height.
+    ///
+    /// # Safety
+    ///
+    /// In addition to the requirements of [`Self::avg_neighbor_unchecked`],
+    /// `sums` must have been used for every other pixel in this row to the
+    /// left of `pos`, except the starting pixel, in order.
+    // `Float` may be `f64`.
+    #[allow(clippy::unnecessary_cast)]
+    unsafe fn avg_neighbor_uniform_unchecked<P: Pixel>(
+        &self,
+        data: &RawPixmap<'_, P>,
+        pos: Position,
+        sums: &mut RowSums,
+    ) -> P {
+        let mut total = [0.0; MAX_CHANNELS];
+        let mut count = 0;
+        let rows = sums.sums.len().min(pos.y + 1);
+        for (dy, sum) in sums.sums[..rows].iter_mut().enumerate() {
+            let y = self.window_row(pos.y - dy);
+            let len = self.kernel.row(dy).len();
+            let mut add = |x: usize, sign: f64| {
+                // SAFETY: `x` is at most `pos.x`, and `window_row` always
+                // returns a valid row. This pixel is to the left of `pos`
+                // or in a row above, so it's already filled.
+                let pixel = unsafe { data.get_unchecked(Position::new(x, y)) };
+                let channels = pixel.to_channels();
+                for (sum, n) in sum.iter_mut().zip(channels) {
+                    *sum += sign * n as f64;
+                }
+            };
+            // The pixel being filled isn't part of the spread.
+            let newest = if dy == 0 {
+                pos.x.checked_sub(1)
+            } else {
+                Some(pos.x)
+            };
+            if let Some(x) = newest {
+                add(x, 1.0);
+            }
+            if let Some(x) = pos.x.checked_sub(len) {
+                add(x, -1.0);
+            }
+            count += len.min(pos.x + 1) - (dy == 0) as usize;
+            for (total, sum) in total.iter_mut().zip(*sum) {
+                *total += sum;
+            }
+        }
+        let count = count as f64;
+        let average = P::from_channels(total.map(|n| (n / count) as Float));
+        self.normalize(average, count as Float)
+    }
+
+    /// Generates a random pixel at `pos` similar to `pixel`.
+    fn random_near<P, R>(&self, rng: &mut R, pos: Position, pixel: P) -> P
+    where
+        P: Pixel,
+        R: Rng + ?Sized,
+    {
+        let (power, max) = self.random_params(pos);
+        let mut component = || {
+            let n: Float = rng.gen();
+            let n = if self.fast_math {
+                approx::powf(n, power)
+            } else {
+                math::powf(n, power)
+            };
+            let n = n * max;
+            let positive: bool = rng.gen();
+            n * Float::from(positive as i8 * 2 - 1)
+        };
+        let max = self.component_max;
+        pixel::map(pixel, |n| (n + component()).clamp(0.0, max))
+    }
+
+    /// Fills a single pixel.
+    ///
+    /// # Safety
+    ///
+    /// `pos.x` and `pos.y` must be less than the image width and height,
+    /// respectively, and no other thread may be accessing this pixel or
+    /// setting any of the pixels it depends on. `sums` must have been
+    /// created with [`Self::row_sums`] and used to fill every other pixel
+    /// in this row to the left of `pos`, in order.
+    pub unsafe fn fill_pos_unchecked<P: Pixel, R: Rng + ?Sized>(
+        &self,
+        data: &RawPixmap<'_, P>,
+        rng: &mut R,
+        pos: Position,
+        sums: &mut RowSums,
+    ) {
+        // SAFETY: Checked by caller.
+        let neighbor = unsafe {
+            if self.uniform_averaging {
+                self.avg_neighbor_uniform_unchecked(data, pos, sums)
+            } else if self.precise_averaging {
+                self.avg_neighbor_unchecked::<P, PreciseSum<P>>(data, pos)
+            } else {
+                self.avg_neighbor_unchecked::<P, FloatSum<P>>(data, pos)
+            }
+        };
+        let pixel = self.random_near(rng, pos, neighbor);
+        let pos = Position::new(pos.x, self.window_row(pos.y));
+        // SAFETY: Checked by caller, and `window_row` always returns a valid
+        // row.
+        unsafe {
+            data.set_unchecked(pos, pixel);
+        }
+    }
+
+    /// Fills row `y` of an image of the given width, except the starting
+    /// pixel.
+    ///
+    /// # Safety
+    ///
+    /// `y` must be less than the image height, `width` must be the image
+    /// width, and no other thread may be accessing the pixels in the row or
+    /// setting any of the pixels they depend on.
+    pub unsafe fn fill_row_unchecked<P: Pixel, R: Rng + ?Sized>(
+        &self,
+        data: &RawPixmap<'_, P>,
+        rng: &mut R,
+        y: usize,
+        width: usize,
+    ) {
+        let mut sums = self.row_sums();
+        // Don't fill the starting pixel.
+        for x in (y == 0) as usize..width {
+            let pos = Position::new(x, y);
+            // SAFETY: Checked by caller.
+            unsafe {
+                self.fill_pos_unchecked(data, rng, pos, &mut sums);
+            }
+        }
+    }
+
+    /// Fills the given rows of the image in `data` (except the starting
+    /// pixel) a band of rows at a time. Each band is split into vertical
+    /// strips [`STRIP_WIDTH`] pixels wide, which are filled from left to
+    /// right, each from top to bottom. In very wide images, this keeps the
+    /// rows above the pixel being filled in cache, which filling entire
+    /// rows would evict. The random number generator is advanced to the
+    /// start of each row of each strip, so the result is identical to
+    /// filling the pixels in order. With [`Params::uniform_averaging`],
+    /// whose running sums span entire rows, each band is filled a row at a
+    /// time instead.
+    ///
+    /// `on_row` is called after each band with the number of rows filled so
+    /// far (including those before `rows`). No new bands are started once
+    /// `stop` is set. Returns the number of rows filled, which is
+    /// `rows.end` unless stopped early.
+    ///
+    /// # Safety
+    ///
+    /// `data` must hold the entire image (i.e., `window_height` must be the
+    /// image height). `rows` must be a subset of the image's rows, all rows
+    /// above `rows` must already be filled, and no other thread may be
+    /// accessing the image.
+    pub unsafe fn fill_strips<P: Pixel, R: FnMut(usize)>(
+        &self,
+        data: &RawPixmap<'_, P>,
+        rng: &mut ChaChaRng,
+        rows: Range<usize>,
+        stop: Stop<'_>,
+        mut on_row: R,
+    ) -> usize {
+        let width = data.width();
+        let strip_width = if self.uniform_averaging {
+            width
+        } else {
+            STRIP_WIDTH
+        };
+        let mut top = rows.start;
+        while top < rows.end {
+            if stop.is_set() {
+                break;
+            }
+            let bottom = (top + BAND_HEIGHT).min(rows.end);
+            for left in (0..width).step_by(strip_width) {
+                let right = (left + strip_width).min(width);
+                for y in top..bottom {
+                    // Don't fill the starting pixel.
+                    let start = left.max((y == 0) as usize);
+                    let index = (y * width + start) as u128;
+                    rng.set_word_pos((index - 1) * words_per_pixel::<P>());
+                    let mut sums = self.row_sums();
+                    for x in start..right {
+                        // SAFETY: `x` and `y` are less than the width and
+                        // height. Every pixel this one depends on is in a
+                        // strip to the left, or in this strip and either
+                        // this row to the left or a row above, so it's
+                        // already filled. `sums` is used for the entire
+                        // row, as with uniform averaging, there's only one
+                        // strip.
+                        unsafe {
+                            self.fill_pos_unchecked(
+                                data,
+                                rng,
+                                Position::new(x, y),
+                                &mut sums,
+                            );
+                        }
+                    }
+                }
+            }
+            top = bottom;
+            on_row(top);
+        }
+        // The starting pixel doesn't use any random numbers.
+        let index = (top * width).saturating_sub(1) as u128;
+        rng.set_word_pos(index * words_per_pixel::<P>());
+        top
+    }
+
+    /// Fills the given rows of the image in `data` (except the starting
+    /// pixel), using `threads` threads. All rows above `rows` must
+    /// already be filled. Each thread fills a row at a time, staying
+    /// behind the row above it, and the random number generator is advanced
+    /// to the start of each row so that the result is identical to filling
+    /// the pixels in order.
+    ///
+    /// `on_row` is called from the current thread with the number of rows
+    /// filled so far (including those before `rows`), in increasing order.
+    ///
+    /// No new rows are started once `stop` is set, but rows already started
+    /// are finished. Returns the number of rows filled, which is `rows.end`
+    /// unless stopped early.
+    ///
+    /// # Safety
+    ///
+    /// `data` must hold the entire image (i.e., `window_height` must be the
+    /// image height). `rows` must be a subset of the image's rows, and the
+    /// image must not be empty.
+    #[cfg(feature = "std")]
+    pub unsafe fn fill_parallel<P: Pixel, R: FnMut(usize)>(
+        &self,
+        data: &RawPixmap<'_, P>,
+        rng: &ChaChaRng,
+        rows: Range<usize>,
+        threads: usize,
+        stop: Stop<'_>,
+        mut on_row: R,
+    ) -> usize {
+        /// How many pixels are filled between progress updates.
+        const BLOCK: usize = 64;
+        /// How many times to spin while waiting before yielding.
+        const SPINS: usize = 64;
+
+        let width = data.width();
+        let height = rows.end;
+        // The number of pixels filled in each row.
+        let filled: Vec<_> = (0..height)
+            .map(|y| {
+                AtomicUsize::new(if y < rows.start {
+                    width
+                } else {
+                    0
+                })
+            })
+            .collect();
+        let next_row = AtomicUsize::new(rows.start);
+
+        // Fills rows until there are none left, calling `after_row` after
+        // each one.
+        let fill_rows = |after_row: &mut dyn FnMut()| {
+            let mut rng = rng.clone();
+            loop {
+                // Check before taking a row, as rows below it can't be
+                // filled until it is.
+                if stop.is_set() {
+                    return;
+                }
+                let y = next_row.fetch_add(1, Ordering::Relaxed);
+                if y >= height {
+                    return;
+                }
+                // Don't fill the starting pixel.
+                let start = (y == 0) as usize;
+                let index = (y * width + start) as u128;
+                rng.set_word_pos((index - 1) * words_per_pixel::<P>());
+                let mut sums = self.row_sums();
+                let mut ready = if y == 0 {
+                    width
+                } else {
+                    0
+                };
+                for x in start..width {
+                    // Wait for the row above to get past this pixel.
+                    let mut spins = 0;
+                    while ready <= x {
+                        ready = filled[y - 1].load(Ordering::Acquire);
+                        spins += 1;
+                        if spins < SPINS {
+                            hint::spin_loop();
+                        } else {
+                            thread::yield_now();
+                        }
+                    }
+                    // SAFETY: `x` and `y` are less than the width and
+                    // height. Every pixel this one depends on is in this
+                    // row to the left, or in a row above that has gotten
+                    // past this pixel, so no other thread can be setting
+                    // them.
+                    unsafe {
+                        self.fill_pos_unchecked(
+                            data,
+                            &mut rng,
+                            Position::new(x, y),
+                            &mut sums,
+                        );
+                    }
+                    if (x + 1) % BLOCK == 0 {
+                        filled[y].store(x + 1, Ordering::Release);
+                    }
+                }
+                filled[y].store(width, Ordering::Release);
+                after_row();
+            }
+        };
+
+        let mut done = rows.start;
+        let mut report = || {
+            while done < height
+                && filled[done].load(Ordering::Relaxed) == width
+            {
+                done += 1;
+                on_row(done);
+            }
+        };
+        thread::scope(|s| {
+            for _ in 1..threads {
+                s.spawn(|| fill_rows(&mut || {}));
+            }
+            // The current thread fills rows too, reporting progress as it
+            // goes.
+            fill_rows(&mut report);
+        });
+        report();
+        done
+    }
+}
@@ -0,0 +1,137 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::super::math;
+use super::super::{Dimensions, Float, Position, Spread};
+use super::approx;
+use alloc::vec::Vec;
+
+/// The weights of the neighboring pixels considered when averaging,
+/// precomputed for a particular [`Spread`] and distance power.
+pub struct Kernel {
+    bounds: Dimensions,
+    /// Row-major weights, indexed by the distance from the pixel being
+    /// filled.
+    weights: Vec<Float>,
+    /// For each row, the number of leading weights that fall within the
+    /// spread shape.
+    row_lens: Vec<usize>,
+}
+
+impl Kernel {
+    /// Creates a new [`Kernel`]. If `fast_math` is true, the weights are
+    /// calculated with faster approximations. If `antialias` is true, pixels
+    /// near the edge of a [`Spread::QuarterCircle`] are weighted by how much
+    /// of each is inside the circle, as with
+    /// [`Params::antialias_spread`](crate::Params::antialias_spread).
+    pub fn new(
+        spread: Spread,
+        distance_power: Float,
+        fast_math: bool,
+        antialias: bool,
+    ) -> Self {
+        let bounds = spread.bounds();
+        let mut weights = Vec::with_capacity(bounds.count());
+        let mut row_lens = Vec::with_capacity(bounds.height);
+        for dy in 0..bounds.height {
+            let mut len = bounds.width;
+            for dx in 0..bounds.width {
+                let dx_f = dx as Float;
+                let dy_f = dy as Float;
+                let dist = if fast_math {
+                    math::sqrt(dx_f * dx_f + dy_f * dy_f)
+                } else {
+                    let sum = math::powf(dx_f, 2.0) + math::powf(dy_f, 2.0);
+                    math::powf(sum, 0.5)
+                };
+                // The fraction of the pixel inside the spread.
+                let mut coverage = 1.0;
+                if let Spread::QuarterCircle {
+                    radius,
+                } = spread
+                {
+                    let radius = radius as Float;
+                    // Approximate the coverage by treating the edge of the
+                    // circle as a straight line through the pixel, which
+                    // gives the fraction of a pixel-wide band at `dist`
+                    // that's within `radius`.
+                    if antialias {
+                        coverage = (radius + 0.5 - dist).clamp(0.0, 1.0);
+                    } else if dist > radius {
+                        coverage = 0.0;
+                    }
+                    // Distances only increase along a row, so every
+                    // remaining pixel is outside the circle too.
+                    if coverage <= 0.0 {
+                        len = len.min(dx);
+                    }
+                }
+                let weight = if fast_math {
+                    approx::powf(dist, distance_power)
+                } else {
+                    math::powf(dist, distance_power)
+                };
+                weights.push(weight * coverage);
+            }
+            row_lens.push(len);
+        }
+        Self {
+            bounds,
+            weights,
+            row_lens,
+        }
+    }
+
+    /// The size of the bounding box that holds the kernel.
+    pub fn bounds(&self) -> Dimensions {
+        self.bounds
+    }
+
+    /// The weights of the pixels `dy` rows above the pixel being filled,
+    /// ordered by increasing horizontal distance.
+    pub fn row(&self, dy: usize) -> &[Float] {
+        let start = dy * self.bounds.width;
+        &self.weights[start..start + self.row_lens[dy]]
+    }
+}
+
+impl Spread {
+    /// The neighboring pixels considered when averaging and the weight each
+    /// one is given, for the given
+    /// [`distance_power`](crate::Params::distance_power). Each position is
+    /// the offset up and to the left of the pixel being filled, in
+    /// row-major order. Weights are calculated as if
+    /// [`fast_math`](crate::Params::fast_math) and
+    /// [`antialias_spread`](crate::Params::antialias_spread) are
+    /// disabled.
+    pub fn weights(
+        self,
+        distance_power: Float,
+    ) -> impl Iterator<Item = (Position, Float)> {
+        let kernel = Kernel::new(self, distance_power, false, false);
+        let mut weights = Vec::new();
+        for dy in 0..kernel.bounds().height {
+            // Skip the pixel being filled.
+            let start = (dy == 0) as usize;
+            let row = kernel.row(dy).iter().enumerate().skip(start);
+            weights.extend(row.map(|(dx, &w)| (Position::new(dx, dy), w)));
+        }
+        weights.into_iter()
+    }
+}
@@ -0,0 +1,122 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::super::{Corner, Dimensions, Direction, Params, Position};
+
+/// Maps positions in the image as it's filled, which always starts at the
+/// top left and proceeds a row at a time, to positions in the output, as
+/// described by [`Params::start_corner`] and [`Params::direction`].
+#[derive(Clone, Copy)]
+pub struct Orientation {
+    corner: Corner,
+    direction: Direction,
+    /// The dimensions of the output.
+    output: Dimensions,
+}
+
+impl Orientation {
+    /// Creates the [`Orientation`] described by `params`.
+    pub fn new(params: &Params) -> Self {
+        Self {
+            corner: params.start_corner,
+            direction: params.direction,
+            output: params.dimensions,
+        }
+    }
+
+    /// The orientation in which the image is already in its final form.
+    pub fn identity(output: Dimensions) -> Self {
+        Self {
+            corner: Corner::TopLeft,
+            direction: Direction::Horizontal,
+            output,
+        }
+    }
+
+    /// Whether positions are the same in the filled image and the output.
+    pub fn is_identity(self) -> bool {
+        self.corner == Corner::TopLeft
+            && self.direction == Direction::Horizontal
+    }
+
+    /// Whether each row of the filled image is the same row in the output,
+    /// so rows can be output as they're filled.
+    pub fn preserves_rows(self) -> bool {
+        self.direction == Direction::Horizontal
+            && matches!(self.corner, Corner::TopLeft | Corner::TopRight)
+    }
+
+    /// The dimensions of the output.
+    pub fn output_dimensions(self) -> Dimensions {
+        self.output
+    }
+
+    /// The dimensions of the image as it's filled.
+    pub fn fill_dimensions(self) -> Dimensions {
+        match self.direction {
+            Direction::Horizontal => self.output,
+            Direction::Vertical => {
+                Dimensions::new(self.output.height, self.output.width)
+            }
+        }
+    }
+
+    /// Flips `pos`, a position in the output, to or from the starting
+    /// corner's side of the image.
+    fn flip(self, pos: Position) -> Position {
+        let Dimensions {
+            width,
+            height,
+        } = self.output;
+        let right =
+            matches!(self.corner, Corner::TopRight | Corner::BottomRight);
+        let bottom =
+            matches!(self.corner, Corner::BottomLeft | Corner::BottomRight);
+        Position::new(
+            if right {
+                width - 1 - pos.x
+            } else {
+                pos.x
+            },
+            if bottom {
+                height - 1 - pos.y
+            } else {
+                pos.y
+            },
+        )
+    }
+
+    /// Swaps the coordinates of `pos` if filling proceeds vertically.
+    fn transpose(self, pos: Position) -> Position {
+        match self.direction {
+            Direction::Horizontal => pos,
+            Direction::Vertical => Position::new(pos.y, pos.x),
+        }
+    }
+
+    /// The position in the output of `pos`, a position in the filled image.
+    pub fn output_pos(self, pos: Position) -> Position {
+        self.flip(self.transpose(pos))
+    }
+
+    /// The position in the filled image of `pos`, a position in the output.
+    pub fn fill_pos(self, pos: Position) -> Position {
+        self.transpose(self.flip(pos))
+    }
+}
@@ -0,0 +1,60 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::super::{Color, Pixel, Pixmap, Position};
+use alloc::vec::Vec;
+
+/// The number of rows given to [`Pass::apply`] at once.
+const CHUNK_ROWS: usize = 64;
+
+/// A pass applied to the image after it's filled, like gamma correction.
+/// Passes can be added with [`Generator::pass`](super::Generator::pass).
+pub trait Pass: Send + Sync {
+    /// The name of the pass, as recorded in
+    /// [`Timings::passes`](super::Timings::passes).
+    fn name(&self) -> &'static str;
+
+    /// Applies the pass to `rows`, which holds consecutive rows of the
+    /// image, each `width` pixels long, starting with row `y`. Passes see
+    /// the image a chunk of rows at a time, so they can't depend on rows
+    /// outside the chunk.
+    fn apply(&self, rows: &mut [Color], width: usize, y: usize);
+}
+
+/// Applies `pass` to the whole image in `pixmap`.
+pub fn apply<P: Pixel>(pixmap: &mut Pixmap<P>, pass: &dyn Pass) {
+    let dim = pixmap.dimensions();
+    let mut colors = Vec::with_capacity(dim.width * CHUNK_ROWS);
+    for start in (0..dim.height).step_by(CHUNK_ROWS) {
+        let end = (start + CHUNK_ROWS).min(dim.height);
+        colors.clear();
+        for y in start..end {
+            colors.extend((0..dim.width).map(|x| {
+                let pos = Position::new(x, y);
+                // SAFETY: `x` and `y` are within the image.
+                unsafe { pixmap.get_unchecked(pos) }.to_color()
+            }));
+        }
+        pass.apply(&mut colors, dim.width, start);
+        for (i, color) in colors.iter().enumerate() {
+            let pos = Position::new(i % dim.width, start + i / dim.width);
+            pixmap.set(pos, P::from_color(*color));
+        }
+    }
+}
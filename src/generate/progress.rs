@@ -0,0 +1,70 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::super::{Dimensions, Float};
+use core::time::Duration;
+
+/// The progress of an image being generated.
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    /// The number of rows that have been filled.
+    pub rows: usize,
+    /// The total number of rows in the image.
+    pub total_rows: usize,
+    /// The number of pixels that have been filled.
+    pub pixels: usize,
+    /// The total number of pixels in the image.
+    pub total_pixels: usize,
+    /// The time elapsed since generation started.
+    pub elapsed: Duration,
+    /// The estimated time remaining, or [`None`] if nothing has been filled
+    /// yet.
+    pub eta: Option<Duration>,
+}
+
+impl Progress {
+    /// Creates a new [`Progress`] for an image with the given dimensions in
+    /// which `rows` rows have been filled in `elapsed` time.
+    pub fn new(
+        dimensions: Dimensions,
+        rows: usize,
+        elapsed: Duration,
+    ) -> Self {
+        let eta = (rows > 0).then(|| {
+            let remaining = dimensions.height - rows;
+            elapsed.mul_f64(remaining as f64 / rows as f64)
+        });
+        Self {
+            rows,
+            total_rows: dimensions.height,
+            pixels: rows * dimensions.width,
+            total_pixels: dimensions.count(),
+            elapsed,
+            eta,
+        }
+    }
+
+    /// The fraction of the image that has been filled, between 0 and 1.
+    pub fn fraction(&self) -> Float {
+        if self.total_rows == 0 {
+            return 1.0;
+        }
+        self.rows as Float / self.total_rows as Float
+    }
+}
@@ -0,0 +1,202 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::super::pixel::{Pixel, MAX_CHANNELS};
+use super::super::Float;
+use core::marker::PhantomData;
+use core::ops::AddAssign;
+#[cfg(not(feature = "f64"))]
+use wide::f32x4 as FloatX4;
+#[cfg(feature = "f64")]
+use wide::f64x4 as FloatX4;
+#[cfg(not(feature = "f64"))]
+use wide::f64x4;
+
+/// A running weighted sum of pixels of type `P`. Lanes hold the weighted
+/// channels. If `P` has fewer than [`MAX_CHANNELS`] channels, the last lane
+/// holds the sum of the weights; otherwise, it's kept separately, which is
+/// slower.
+pub trait Sum<P: Pixel>: Copy + AddAssign {
+    /// An empty sum.
+    const ZERO: Self;
+
+    /// Creates a sum containing only `pixel` with the given weight.
+    fn term(pixel: P, weight: Float) -> Self;
+
+    /// Divides the channels by the total weight. If `fast_math` is true,
+    /// this multiplies by the reciprocal instead.
+    fn average(self, fast_math: bool) -> P;
+
+    /// The total weight.
+    fn weight(self) -> Float;
+}
+
+/// Whether pixels of type `P` leave a lane free for the sum of the weights.
+const fn has_weight_lane<P: Pixel>() -> bool {
+    P::CHANNELS < MAX_CHANNELS
+}
+
+/// A sum with the same precision as [`Float`].
+pub struct FloatSum<P> {
+    lanes: FloatX4,
+    weight: Float,
+    phantom: PhantomData<fn() -> P>,
+}
+
+impl<P> Clone for FloatSum<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P> Copy for FloatSum<P> {}
+
+impl<P: Pixel> AddAssign for FloatSum<P> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.lanes += rhs.lanes;
+        if !has_weight_lane::<P>() {
+            self.weight += rhs.weight;
+        }
+    }
+}
+
+impl<P: Pixel> Sum<P> for FloatSum<P> {
+    const ZERO: Self = Self {
+        lanes: FloatX4::ZERO,
+        weight: 0.0,
+        phantom: PhantomData,
+    };
+
+    fn term(pixel: P, weight: Float) -> Self {
+        let mut lanes = pixel.to_channels();
+        if has_weight_lane::<P>() {
+            lanes[MAX_CHANNELS - 1] = 1.0;
+        }
+        Self {
+            lanes: FloatX4::new(lanes) * FloatX4::splat(weight),
+            weight,
+            phantom: PhantomData,
+        }
+    }
+
+    fn average(self, fast_math: bool) -> P {
+        let lanes: [Float; MAX_CHANNELS] = self.lanes.to_array();
+        let weight = if has_weight_lane::<P>() {
+            lanes[MAX_CHANNELS - 1]
+        } else {
+            self.weight
+        };
+        let recip = weight.recip();
+        P::from_channels(lanes.map(|n| {
+            if fast_math {
+                n * recip
+            } else {
+                n / weight
+            }
+        }))
+    }
+
+    fn weight(self) -> Float {
+        if has_weight_lane::<P>() {
+            self.lanes.to_array()[MAX_CHANNELS - 1]
+        } else {
+            self.weight
+        }
+    }
+}
+
+/// A sum that uses [`f64`] regardless of the precision of [`Float`].
+#[cfg(not(feature = "f64"))]
+pub struct PreciseSum<P> {
+    lanes: f64x4,
+    weight: f64,
+    phantom: PhantomData<fn() -> P>,
+}
+
+/// A sum that uses [`f64`] regardless of the precision of [`Float`].
+#[cfg(feature = "f64")]
+pub type PreciseSum<P> = FloatSum<P>;
+
+#[cfg(not(feature = "f64"))]
+impl<P> Clone for PreciseSum<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+#[cfg(not(feature = "f64"))]
+impl<P> Copy for PreciseSum<P> {}
+
+#[cfg(not(feature = "f64"))]
+impl<P: Pixel> AddAssign for PreciseSum<P> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.lanes += rhs.lanes;
+        if !has_weight_lane::<P>() {
+            self.weight += rhs.weight;
+        }
+    }
+}
+
+#[cfg(not(feature = "f64"))]
+impl<P: Pixel> Sum<P> for PreciseSum<P> {
+    const ZERO: Self = Self {
+        lanes: f64x4::ZERO,
+        weight: 0.0,
+        phantom: PhantomData,
+    };
+
+    fn term(pixel: P, weight: Float) -> Self {
+        let weight = f64::from(weight);
+        let mut lanes = pixel.to_channels().map(f64::from);
+        if has_weight_lane::<P>() {
+            lanes[MAX_CHANNELS - 1] = 1.0;
+        }
+        Self {
+            lanes: f64x4::new(lanes) * f64x4::splat(weight),
+            weight,
+            phantom: PhantomData,
+        }
+    }
+
+    fn average(self, fast_math: bool) -> P {
+        let lanes = self.lanes.to_array();
+        let weight = if has_weight_lane::<P>() {
+            lanes[MAX_CHANNELS - 1]
+        } else {
+            self.weight
+        };
+        let recip = weight.recip();
+        P::from_channels(lanes.map(|n| {
+            if fast_math {
+                (n * recip) as Float
+            } else {
+                (n / weight) as Float
+            }
+        }))
+    }
+
+    fn weight(self) -> Float {
+        let weight = if has_weight_lane::<P>() {
+            self.lanes.to_array()[MAX_CHANNELS - 1]
+        } else {
+            self.weight
+        };
+        weight as Float
+    }
+}
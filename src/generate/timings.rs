@@ -0,0 +1,69 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use alloc::vec::Vec;
+use core::time::Duration;
+
+#[cfg(feature = "std")]
+pub use std::time::Instant;
+
+#[cfg(not(feature = "std"))]
+/// Stand-in for [`std::time::Instant`] when the `std` feature is disabled.
+/// All measured durations are zero.
+#[derive(Clone, Copy)]
+pub struct Instant;
+
+#[cfg(not(feature = "std"))]
+impl Instant {
+    pub fn now() -> Self {
+        Self
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// How long each stage of generating an image took. Without the `std`
+/// feature, all durations are zero.
+#[derive(Clone, Debug, Default)]
+pub struct Timings {
+    /// Filling the pixels.
+    pub fill: Duration,
+    /// Each pass applied after filling, in order, along with its name. In
+    /// streaming mode, passes are applied as each row is converted, so this
+    /// is empty and the time is included in [`Self::quantize`].
+    pub passes: Vec<(&'static str, Duration)>,
+    /// Converting the pixels to bytes.
+    pub quantize: Duration,
+    /// Writing the bytes.
+    pub write: Duration,
+    /// The number of 32-bit words drawn from the random number generator
+    /// while filling, which is the only stage that uses it, if counted
+    /// with [`Generator::count_rng_draws`](super::Generator::count_rng_draws).
+    pub rng_words: Option<u64>,
+}
+
+impl Timings {
+    /// The total time taken by all stages.
+    pub fn total(&self) -> Duration {
+        let passes = self.passes.iter().map(|(_, time)| *time);
+        self.fill + passes.sum::<Duration>() + self.quantize + self.write
+    }
+}
@@ -0,0 +1,415 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! A desktop GUI for adjusting params with a live preview.
+
+use atomic::AtomicFile;
+use eframe::egui::{self, ColorImage, TextureHandle, TextureOptions};
+use plumage::{
+    Color, Dimensions, Float, Generator, ParamRanges, Params, Spread,
+};
+use rand::{thread_rng, Rng};
+use ron::ser::PrettyConfig;
+use std::io::{self, BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Shared with the CLI.
+#[path = "../shared/atomic.rs"]
+mod atomic;
+
+/// The longest side of the preview, in pixels.
+const PREVIEW_SIZE: usize = 512;
+
+/// How long to spend generating the preview per frame, so the controls
+/// stay responsive.
+const FRAME_BUDGET: Duration = Duration::from_millis(25);
+
+/// The number of CPUs, or 1 if it can't be determined.
+fn cpus() -> NonZeroUsize {
+    thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Scales down the image and spread in `params` so the image fits in the
+/// preview (see [`Params::rescale`]).
+fn preview_params(params: &Params) -> Params {
+    let dim = params.dimensions;
+    let factor = dim.width.max(dim.height).div_ceil(PREVIEW_SIZE).max(1);
+    params.clone().rescale(|n| (n / factor).max(1))
+}
+
+/// Converts colors to an image for display, applying `gamma`.
+fn color_image<I>(dim: Dimensions, colors: I, gamma: Float) -> ColorImage
+where
+    I: Iterator<Item = Color>,
+{
+    let rgb: Vec<u8> =
+        colors.flat_map(|color| color.powf(gamma).to_rgb8()).collect();
+    ColorImage::from_rgb([dim.width, dim.height], &rgb)
+}
+
+/// Generates the image at full size with `generator` and writes it and
+/// `params`, the params it was created with. Neither file is replaced
+/// unless it's written completely.
+fn save(
+    generator: Generator,
+    params: &Params,
+    path: &Path,
+    rows: &AtomicUsize,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(AtomicFile::create(path)?);
+    generator.generate_with_progress(&mut writer, |progress| {
+        rows.store(progress.rows, Ordering::Relaxed);
+    })?;
+    writer.into_inner().map_err(|e| e.into_error())?.commit()?;
+
+    let params_path = path.with_extension("params");
+    let mut writer = BufWriter::new(AtomicFile::create(&params_path)?);
+    let pretty = PrettyConfig::new().depth_limit(1);
+    ron::ser::to_writer_pretty(&mut writer, params, pretty)
+        .map_err(io::Error::other)?;
+    writeln!(writer)?;
+    writer.into_inner().map_err(|e| e.into_error())?.commit()
+}
+
+/// An image being saved in the background.
+struct Saving {
+    path: PathBuf,
+    /// The number of rows filled so far.
+    rows: Arc<AtomicUsize>,
+    total_rows: usize,
+    done: Receiver<io::Result<()>>,
+}
+
+struct App {
+    /// The full-size params being edited.
+    params: Params,
+    /// The text of the seed field, which may not be a valid seed yet.
+    seed_text: String,
+    /// Whether randomizing the params keeps the seed.
+    seed_locked: bool,
+    /// The preview being generated, and the gamma to display it with.
+    generator: Option<(Generator, Float)>,
+    texture: Option<TextureHandle>,
+    save_path: String,
+    saving: Option<Saving>,
+    status: String,
+}
+
+impl App {
+    fn new() -> Self {
+        let params = Params::builder()
+            .dimensions(Dimensions::new(1920, 1080))
+            .build();
+        let mut app = Self {
+            seed_text: plumage::seed_to_hex(&params.seed),
+            params,
+            seed_locked: false,
+            generator: None,
+            texture: None,
+            save_path: "plumage.bmp".into(),
+            saving: None,
+            status: String::new(),
+        };
+        app.restart_preview();
+        app
+    }
+
+    /// Starts generating the preview again after the params change.
+    fn restart_preview(&mut self) {
+        let params = preview_params(&self.params);
+        if let Err(e) = params.validate() {
+            self.status = format!("invalid params: {e}");
+            self.generator = None;
+            return;
+        }
+        self.status.clear();
+        let gamma = params.gamma;
+        self.generator = Some((Generator::new(params).threads(cpus()), gamma));
+    }
+
+    /// Randomizes the params other than the size, and the seed unless it's
+    /// locked.
+    fn randomize(&mut self) {
+        let mut params =
+            Params::randomize(&ParamRanges::default(), thread_rng());
+        params.dimensions = self.params.dimensions;
+        if self.seed_locked {
+            params.seed = self.params.seed;
+        }
+        self.seed_text = plumage::seed_to_hex(&params.seed);
+        self.params = params;
+    }
+
+    /// Generates more of the preview and updates the texture.
+    fn step_preview(&mut self, ctx: &egui::Context) {
+        let Some((generator, gamma)) = &mut self.generator else {
+            return;
+        };
+        let start = Instant::now();
+        // Generate a few rows at a time, so the budget isn't overshot much.
+        let rows = generator.pixmap().dimensions().height / 64 + 1;
+        let mut more = true;
+        while more && start.elapsed() < FRAME_BUDGET {
+            more = generator.step_rows(rows);
+        }
+        let gamma = *gamma;
+        let image = if more {
+            let pixmap = generator.pixmap();
+            let colors = pixmap.enumerate_pixels().map(|(_, color)| color);
+            color_image(pixmap.dimensions(), colors, gamma)
+        } else {
+            // Gamma is applied by the generator once it's done.
+            let (generator, _) = self.generator.take().unwrap();
+            let pixmap = generator.into_pixmap();
+            let colors = pixmap.enumerate_pixels().map(|(_, color)| color);
+            color_image(pixmap.dimensions(), colors, 1.0)
+        };
+        match &mut self.texture {
+            Some(texture) => texture.set(image, TextureOptions::NEAREST),
+            None => {
+                self.texture = Some(ctx.load_texture(
+                    "preview",
+                    image,
+                    TextureOptions::NEAREST,
+                ));
+            }
+        }
+        if more {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Starts saving the full-size image in the background.
+    fn start_save(&mut self) {
+        let path = PathBuf::from(&self.save_path);
+        let params = self.params.clone();
+        let generator = Generator::new(params.clone()).threads(cpus());
+        // Progress counts rows as they're filled, which are columns of the
+        // image when filling vertically.
+        let total_rows = generator.total_rows();
+        let rows = Arc::new(AtomicUsize::new(0));
+        let (sender, done) = mpsc::channel();
+        let thread_path = path.clone();
+        let thread_rows = rows.clone();
+        thread::spawn(move || {
+            let result = save(generator, &params, &thread_path, &thread_rows);
+            let _ = sender.send(result);
+        });
+        self.saving = Some(Saving {
+            path,
+            rows,
+            total_rows,
+            done,
+        });
+    }
+
+    /// Checks on the image being saved.
+    fn poll_save(&mut self, ctx: &egui::Context) {
+        let Some(saving) = &self.saving else {
+            return;
+        };
+        let Ok(result) = saving.done.try_recv() else {
+            let rows = saving.rows.load(Ordering::Relaxed);
+            self.status = format!(
+                "saving {}: {}%",
+                saving.path.display(),
+                rows * 100 / saving.total_rows.max(1),
+            );
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        self.status = match result {
+            Ok(()) => format!("saved {}", saving.path.display()),
+            Err(e) => format!("could not save {}: {e}", saving.path.display()),
+        };
+        self.saving = None;
+    }
+
+    /// Shows the controls, returning whether the params changed.
+    fn controls(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        let params = &mut self.params;
+
+        ui.heading("Image");
+        let mut width = params.dimensions.width;
+        let mut height = params.dimensions.height;
+        ui.horizontal(|ui| {
+            changed |= ui
+                .add(egui::DragValue::new(&mut width).range(1..=16384))
+                .changed();
+            ui.label("×");
+            changed |= ui
+                .add(egui::DragValue::new(&mut height).range(1..=16384))
+                .changed();
+        });
+        params.dimensions = Dimensions::new(width, height);
+
+        egui::ComboBox::from_label("Preset")
+            .selected_text("Apply…")
+            .show_ui(ui, |ui| {
+                for name in Params::preset_names() {
+                    if ui.selectable_label(false, name).clicked() {
+                        let preset = Params::preset(name).unwrap().build();
+                        params.spread = preset.spread;
+                        params.distance_power = preset.distance_power;
+                        params.random_power = preset.random_power;
+                        params.random_max = preset.random_max;
+                        params.gamma = preset.gamma;
+                        changed = true;
+                    }
+                }
+            });
+
+        ui.heading("Spread");
+        let (mut size, mut circle) = match params.spread {
+            Spread::Square {
+                width,
+            } => (width, false),
+            Spread::QuarterCircle {
+                radius,
+            } => (radius, true),
+        };
+        ui.horizontal(|ui| {
+            changed |= ui.radio_value(&mut circle, false, "Square").changed();
+            changed |= ui
+                .radio_value(&mut circle, true, "Quarter circle")
+                .changed();
+        });
+        changed |= ui
+            .add(egui::Slider::new(&mut size, 1..=32).text("size"))
+            .changed();
+        params.spread = if circle {
+            Spread::QuarterCircle {
+                radius: size,
+            }
+        } else {
+            Spread::Square {
+                width: size,
+            }
+        };
+
+        ui.heading("Colors");
+        let sliders = [
+            (&mut params.distance_power, -5.0..=0.0, "distance_power"),
+            (&mut params.count_power, 0.9..=1.1, "count_power"),
+            (&mut params.random_power, 0.5..=10.0, "random_power"),
+            (&mut params.random_max, 0.0..=0.5, "random_max"),
+            (&mut params.gamma, 0.1..=2.0, "gamma"),
+        ];
+        for (value, range, name) in sliders {
+            changed |=
+                ui.add(egui::Slider::new(value, range).text(name)).changed();
+        }
+        let mut rgb = params.start_color.to_rgb8();
+        ui.horizontal(|ui| {
+            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                params.start_color = Color::from_rgb8(rgb);
+                changed = true;
+            }
+            ui.label("start_color");
+        });
+        changed |= ui.checkbox(&mut params.fast_math, "fast_math").changed();
+        changed |= ui
+            .checkbox(&mut params.precise_averaging, "precise_averaging")
+            .changed();
+        changed |= ui
+            .checkbox(&mut params.uniform_averaging, "uniform_averaging")
+            .changed();
+        changed |= ui
+            .checkbox(&mut params.antialias_spread, "antialias_spread")
+            .changed();
+        changed |= ui
+            .checkbox(&mut params.extended_range, "extended_range")
+            .changed();
+
+        ui.heading("Seed");
+        if ui.text_edit_singleline(&mut self.seed_text).changed() {
+            if let Some(seed) = plumage::seed_from_hex(self.seed_text.trim()) {
+                params.seed = seed;
+                changed = true;
+            }
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.seed_locked, "Lock");
+            let button = egui::Button::new("New seed");
+            if ui.add_enabled(!self.seed_locked, button).clicked() {
+                thread_rng().fill(&mut params.seed);
+                self.seed_text = plumage::seed_to_hex(&params.seed);
+                changed = true;
+            }
+        });
+        if ui.button("Randomize").clicked() {
+            self.randomize();
+            changed = true;
+        }
+
+        ui.heading("Save");
+        ui.text_edit_singleline(&mut self.save_path);
+        let button = egui::Button::new("Save full size");
+        if ui.add_enabled(self.saving.is_none(), button).clicked() {
+            self.start_save();
+        }
+        ui.label(&self.status);
+        changed
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        self.poll_save(ctx);
+        egui::SidePanel::left("controls").show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                if self.controls(ui) {
+                    self.restart_preview();
+                }
+            });
+        });
+        self.step_preview(ctx);
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let Some(texture) = &self.texture else {
+                return;
+            };
+            // Fit the preview in the panel, keeping its aspect ratio.
+            let size = texture.size_vec2();
+            let scale = (ui.available_size() / size).min_elem();
+            ui.centered_and_justified(|ui| {
+                ui.image((texture.id(), size * scale));
+            });
+        });
+    }
+}
+
+fn main() -> eframe::Result {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([1100.0, 700.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "Plumage",
+        options,
+        Box::new(|_| Ok(Box::new(App::new()))),
+    )
+}
@@ -0,0 +1,219 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Known-answer vectors: params paired with the fingerprints of the images
+//! they must produce, so any build can confirm that it reproduces images
+//! bit for bit.
+
+use super::{Color, Corner, Dimensions, Direction, Expr, Expressions};
+use super::{Fingerprint, Generator, Params, Rotation, Spread};
+use core::fmt::{self, Display};
+
+/// Params and the fingerprint of the image they must produce. The images
+/// are small, so checking every answer takes well under a second.
+#[derive(Clone, Copy, Debug)]
+pub struct KnownAnswer {
+    /// A short name describing what the params exercise.
+    pub name: &'static str,
+    params: fn() -> Params,
+    /// Fingerprints when [`Float`](crate::Float) is [`f32`] and [`f64`].
+    fingerprints: [Fingerprint; 2],
+}
+
+impl KnownAnswer {
+    /// The params to generate.
+    pub fn params(&self) -> Params {
+        (self.params)()
+    }
+
+    /// The fingerprint that the image must have in this build, which
+    /// depends on whether the `f64` feature is enabled.
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.fingerprints[cfg!(feature = "f64") as usize]
+    }
+
+    /// Generates the image and checks its fingerprint.
+    pub fn check(&self) -> Result<(), KnownAnswerMismatch> {
+        let pixmap = Generator::new(self.params()).into_pixmap();
+        let actual = Fingerprint::of_pixmap(&pixmap);
+        let expected = self.fingerprint();
+        if actual == expected {
+            return Ok(());
+        }
+        Err(KnownAnswerMismatch {
+            name: self.name,
+            expected,
+            actual,
+        })
+    }
+}
+
+/// Returned by [`verify_known_answers`] when an image doesn't match its
+/// known answer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KnownAnswerMismatch {
+    /// The [`KnownAnswer::name`] of the answer.
+    pub name: &'static str,
+    pub expected: Fingerprint,
+    pub actual: Fingerprint,
+}
+
+impl Display for KnownAnswerMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "known answer `{}` has fingerprint {}, expected {}",
+            self.name, self.actual, self.expected,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KnownAnswerMismatch {}
+
+/// The value of a lowercase hex digit.
+const fn hex_digit(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        _ => panic!("invalid hex digit"),
+    }
+}
+
+/// Parses 64 hex digits at compile time.
+const fn fingerprint(hex: &str) -> Fingerprint {
+    let hex = hex.as_bytes();
+    assert!(hex.len() == 64, "fingerprint must be 64 hex digits");
+    let mut bytes = [0; 32];
+    let mut i = 0;
+    while i < 32 {
+        bytes[i] = hex_digit(hex[i * 2]) << 4 | hex_digit(hex[i * 2 + 1]);
+        i += 1;
+    }
+    Fingerprint(bytes)
+}
+
+/// Parses the fingerprints when [`Float`](crate::Float) is [`f32`] and
+/// [`f64`].
+const fn fingerprints(f32: &str, f64: &str) -> [Fingerprint; 2] {
+    [fingerprint(f32), fingerprint(f64)]
+}
+
+/// The seed used by every answer.
+const SEED: [u8; 32] = *b"plumage known-answer test seed!!";
+
+/// Starts building params for an answer, with a fixed size, seed, and start
+/// color.
+fn builder() -> super::ParamsBuilder {
+    Params::builder()
+        .dimensions(Dimensions::new(96, 64))
+        .seed(SEED)
+        .start_color(Color::from_u32(0x3c65e2))
+}
+
+/// Builds the params, which always succeeds, as the seed and start color are
+/// given.
+fn build(builder: super::ParamsBuilder) -> Params {
+    builder.build_strict().expect("seed and start color are given")
+}
+
+/// Every known answer.
+pub const KNOWN_ANSWERS: &[KnownAnswer] = &[
+    KnownAnswer {
+        name: "default",
+        params: || build(builder()),
+        fingerprints: fingerprints(
+            "6a4493fe63f3e03fa1211280dbdfd93f86c05a6dd99e5a2c96d011b9b522e495",
+            "4cd39fcfb5ae5f6daa74c8ff6fca9a61e89cd6886b809eebc9572b2fb229d558",
+        ),
+    },
+    KnownAnswer {
+        name: "quarter-circle",
+        params: || {
+            build(
+                builder()
+                    .spread(Spread::QuarterCircle {
+                        radius: 4,
+                    })
+                    .precise_averaging(true)
+                    .antialias_spread(true),
+            )
+        },
+        fingerprints: fingerprints(
+            "3f09619cfeddb1652b2a5a0b8bdad6b031a4a1f264f818466ca1d0dffa49a1b5",
+            "c244efa190df87bf542052a35def6f1b3023259323f3b8abcceea1eef2d9fcaa",
+        ),
+    },
+    KnownAnswer {
+        name: "uniform",
+        params: || {
+            build(
+                builder()
+                    .spread(Spread::Square {
+                        width: 9,
+                    })
+                    .uniform_averaging(true)
+                    .count_power(1.01),
+            )
+        },
+        fingerprints: fingerprints(
+            "79014de17b8728c66718778f52fa9d56bb595bb190c558bb8f1927942e3f6d1d",
+            "b5963755fb7cfb3ff6d2b06edae1339708974a3a9045696f1db1752ce37b6c68",
+        ),
+    },
+    KnownAnswer {
+        name: "fast-math",
+        params: || build(builder().fast_math(true)),
+        fingerprints: fingerprints(
+            "27d1661ae98bfc57a964391725be7d07a6db5629235432fefa36b31634f818c5",
+            "45a449988e433378f5828844212240c67e2cd7b1ea5d23cbc25839a043c5acbd",
+        ),
+    },
+    KnownAnswer {
+        name: "transformed",
+        params: || {
+            let gamma = "0.6 + 0.3 * x / width";
+            build(
+                builder()
+                    .start_corner(Corner::BottomRight)
+                    .direction(Direction::Vertical)
+                    .rotation(Rotation {
+                        degrees: 30.0,
+                        ..Rotation::default()
+                    })
+                    .expressions(Expressions {
+                        gamma: Some(Expr::parse(gamma).expect("valid")),
+                        ..Expressions::default()
+                    }),
+            )
+        },
+        fingerprints: fingerprints(
+            "e5c823a15d07ac814b5c3489b108a6fc827b7909a32e7867c4eef94d3e0023d2",
+            "b3701cb9205c979f4fafee8c571c5d16e41fc955422875b072e90ea9d91cac2b",
+        ),
+    },
+];
+
+/// Checks that this build reproduces every answer in [`KNOWN_ANSWERS`],
+/// returning the first mismatch, if any. A mismatch means images generated
+/// by this build differ from those generated by others, as when the
+/// platform's floating-point functions give different results.
+pub fn verify_known_answers() -> Result<(), KnownAnswerMismatch> {
+    KNOWN_ANSWERS.iter().try_for_each(KnownAnswer::check)
+}
@@ -25,19 +25,39 @@
 
 extern crate alloc;
 
+mod capabilities;
 mod color;
 mod coords;
+mod fingerprint;
 mod generate;
+mod known_answers;
+mod math;
 mod params;
+mod pixel;
 mod pixmap;
+#[cfg(feature = "wasm")]
+mod wasm;
 
-use coords::Position;
-use pixmap::Pixmap;
-
+pub use capabilities::{capabilities, Capabilities};
 pub use color::Color;
-pub use coords::Dimensions;
-pub use generate::Generator;
-pub use params::{Params, Spread};
+pub use coords::{Dimensions, ParseDimensionsError, Position};
+pub use fingerprint::Fingerprint;
+pub use generate::{Generator, Pass, Progress, Timings};
+pub use known_answers::{verify_known_answers, KnownAnswer};
+pub use known_answers::{KnownAnswerMismatch, KNOWN_ANSWERS};
+pub use params::{Border, CanonicalParams, Corner, Direction, Duotone, Expr};
+pub use params::{Params, ParamsBuilder, ParamsError, ParseExprError};
+pub use params::{Expressions, Fit, ParamRanges, ParamsHash, Repeat};
+pub use params::{seed_from_hex, seed_to_hex, Rotation, Spread};
+pub use pixel::{Gray, Lab, Pixel, Rgba, MAX_CHANNELS};
+pub use pixmap::{BlendMode, Pixmap, Storage};
 
+/// The floating-point type used throughout the crate. This is [`f64`] when
+/// the `f64` feature is enabled, and [`f32`] otherwise.
+#[cfg(not(feature = "f64"))]
 pub type Float = f32;
+/// The floating-point type used throughout the crate. This is [`f64`] when
+/// the `f64` feature is enabled, and [`f32`] otherwise.
+#[cfg(feature = "f64")]
+pub type Float = f64;
 pub type Seed = [u8; 32];
@@ -0,0 +1,76 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Floating-point functions that aren't available in `core`. These use the
+//! standard library when the `std` feature is enabled, and [`libm`]
+//! otherwise. The two aren't guaranteed to give bit-identical results, so
+//! images generated without `std` may differ slightly.
+
+use super::Float;
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("either the `std` or `libm` feature must be enabled");
+
+#[cfg(not(feature = "std"))]
+type Libm = libm::Libm<Float>;
+
+/// Raises `x` to the power of `n`.
+pub fn powf(x: Float, n: Float) -> Float {
+    #[cfg(feature = "std")]
+    let result = x.powf(n);
+    #[cfg(not(feature = "std"))]
+    let result = Libm::pow(x, n);
+    result
+}
+
+/// Calculates the square root of `x`.
+pub fn sqrt(x: Float) -> Float {
+    #[cfg(feature = "std")]
+    let result = x.sqrt();
+    #[cfg(not(feature = "std"))]
+    let result = Libm::sqrt(x);
+    result
+}
+
+/// Rounds `x` to the nearest integer, rounding half-way cases away from 0.
+pub fn round(x: Float) -> Float {
+    #[cfg(feature = "std")]
+    let result = x.round();
+    #[cfg(not(feature = "std"))]
+    let result = Libm::round(x);
+    result
+}
+
+/// Calculates the sine of `x` (in radians).
+pub fn sin(x: Float) -> Float {
+    #[cfg(feature = "std")]
+    let result = x.sin();
+    #[cfg(not(feature = "std"))]
+    let result = Libm::sin(x);
+    result
+}
+
+/// Calculates the cosine of `x` (in radians).
+pub fn cos(x: Float) -> Float {
+    #[cfg(feature = "std")]
+    let result = x.cos();
+    #[cfg(not(feature = "std"))]
+    let result = Libm::cos(x);
+    result
+}
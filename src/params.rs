@@ -17,11 +17,29 @@
  * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use super::{Color, Dimensions, Float, Seed};
+use super::{math, Color, Dimensions, Float, Seed};
+use core::num::NonZeroUsize;
+#[cfg(feature = "std")]
 use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
 
+mod builder;
+mod canonical;
+mod expr;
+mod preset;
+mod randomize;
+mod repr;
+mod schema;
 mod seed;
+mod validate;
+mod version;
+
+pub use builder::ParamsBuilder;
+pub use canonical::{CanonicalParams, ParamsHash};
+pub use expr::{Expr, Expressions, ParseExprError};
+pub use randomize::ParamRanges;
+pub use seed::{seed_from_hex, seed_to_hex};
+pub use validate::ParamsError;
 
 /// Shape of the area of neighboring pixels considered when averaging.
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
@@ -47,29 +65,334 @@ impl Spread {
             } => Dimensions::square(radius + 1),
         }
     }
+
+    /// Applies `f` to the size of the spread: the width of a square or the
+    /// radius of a quarter circle.
+    pub fn map_size(self, f: impl FnOnce(usize) -> usize) -> Self {
+        match self {
+            Self::Square {
+                width,
+            } => Self::Square {
+                width: f(width),
+            },
+            Self::QuarterCircle {
+                radius,
+            } => Self::QuarterCircle {
+                radius: f(radius),
+            },
+        }
+    }
+}
+
+/// The corner of the image that generation starts from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Corner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// The direction in which generation proceeds from the starting corner.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    /// Each row is filled before the next one.
+    #[default]
+    Horizontal,
+    /// Each column is filled before the next one.
+    Vertical,
+}
+
+/// How [`Rotation`] sizes the rotated image, and [`Border`] makes room for
+/// the border.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Fit {
+    /// Keep the original dimensions, cutting off the corners of the rotated
+    /// image, or drawing the border over the edges of the image.
+    #[default]
+    Crop,
+    /// Grow the image to hold the entire rotated image, or to surround the
+    /// image with the border.
+    Expand,
+}
+
+/// A rotation of the finished image, which allows the streaks to point in
+/// any direction. Areas outside the rotated image are black.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rotation {
+    /// The angle to rotate by, counterclockwise.
+    #[serde(default)]
+    pub degrees: Float,
+    #[serde(default)]
+    pub fit: Fit,
+}
+
+impl Rotation {
+    /// Whether the rotation leaves the image unchanged.
+    pub fn is_identity(self) -> bool {
+        self.degrees % 360.0 == 0.0
+    }
+
+    /// The sine and cosine of the angle.
+    pub(crate) fn sin_cos(self) -> (Float, Float) {
+        let radians = self.degrees.to_radians();
+        (math::sin(radians), math::cos(radians))
+    }
+
+    /// The dimensions of an image of size `dim` after rotating it.
+    pub fn output_dimensions(self, dim: Dimensions) -> Dimensions {
+        if self.fit == Fit::Crop || self.is_identity() {
+            return dim;
+        }
+        let (sin, cos) = self.sin_cos();
+        let (sin, cos) = (sin.abs(), cos.abs());
+        let (w, h) = (dim.width as Float, dim.height as Float);
+        // Round so that, e.g., rotating by 90 degrees doesn't add a pixel
+        // due to imprecision.
+        Dimensions::new(
+            math::round(w * cos + h * sin) as usize,
+            math::round(w * sin + h * cos) as usize,
+        )
+    }
+}
+
+/// How many times the finished image is repeated in each direction, which
+/// produces a larger image made of copies of the original side by side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Repeat {
+    /// The number of copies across.
+    pub columns: NonZeroUsize,
+    /// The number of copies down.
+    pub rows: NonZeroUsize,
+}
+
+impl Repeat {
+    /// Whether the image is left as a single copy.
+    pub fn is_identity(self) -> bool {
+        self.columns.get() == 1 && self.rows.get() == 1
+    }
+
+    /// The dimensions of an image of size `dim` after repeating it. These
+    /// saturate at [`usize::MAX`] rather than overflowing.
+    pub fn output_dimensions(self, dim: Dimensions) -> Dimensions {
+        Dimensions::new(
+            dim.width.saturating_mul(self.columns.get()),
+            dim.height.saturating_mul(self.rows.get()),
+        )
+    }
+}
+
+/// A border drawn around the finished image, either in a solid color or
+/// fading between two colors.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Border {
+    /// The thickness of the border in pixels. Zero draws no border.
+    pub width: usize,
+    /// The color of the border, or of its outer edge if
+    /// [`Self::inner_color`] is given.
+    pub color: Color,
+    /// The color of the inner edge of the border. The border fades to this
+    /// from [`Self::color`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inner_color: Option<Color>,
+    pub fit: Fit,
+}
+
+impl Border {
+    /// Whether the border leaves the image unchanged.
+    pub fn is_identity(self) -> bool {
+        self.width == 0
+    }
+
+    /// The dimensions of an image of size `dim` after adding the border.
+    /// These saturate at [`usize::MAX`] rather than overflowing.
+    pub fn output_dimensions(self, dim: Dimensions) -> Dimensions {
+        if self.fit == Fit::Crop {
+            return dim;
+        }
+        let added = self.width.saturating_mul(2);
+        Dimensions::new(
+            dim.width.saturating_add(added),
+            dim.height.saturating_add(added),
+        )
+    }
+
+    /// The color of the border `dist` pixels in from the outer edge.
+    pub(crate) fn color_at(self, dist: usize) -> Color {
+        match self.inner_color {
+            Some(inner) => {
+                let last = self.width.saturating_sub(1).max(1);
+                let t = dist as Float / last as Float;
+                self.color.lerp(inner, t)
+            }
+            None => self.color,
+        }
+    }
+}
+
+/// Maps the brightness of the finished image onto two or three inks, as in
+/// a duotone or tritone print. Dark pixels take [`Self::shadows`], bright
+/// pixels take [`Self::highlights`], and pixels in between blend them,
+/// passing through [`Self::midtones`] if given.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Duotone {
+    pub shadows: Color,
+    /// The color of pixels halfway between dark and bright, which makes a
+    /// tritone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub midtones: Option<Color>,
+    pub highlights: Color,
+}
+
+impl Duotone {
+    /// The ink color for a pixel of color `color`, based on its
+    /// [luminance](Color::luminance).
+    pub fn map(self, color: Color) -> Color {
+        let t = color.luminance().clamp(0.0, 1.0);
+        match self.midtones {
+            Some(mid) if t < 0.5 => self.shadows.lerp(mid, t * 2.0),
+            Some(mid) => mid.lerp(self.highlights, t * 2.0 - 1.0),
+            None => self.shadows.lerp(self.highlights, t),
+        }
+    }
+
+    /// The shadow, midtone (if any), and highlight colors.
+    pub fn colors(self) -> impl Iterator<Item = Color> {
+        [Some(self.shadows), self.midtones, Some(self.highlights)]
+            .into_iter()
+            .flatten()
+    }
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            color: Color::BLACK,
+            inner_color: None,
+            fit: Fit::Crop,
+        }
+    }
+}
+
+impl Default for Repeat {
+    fn default() -> Self {
+        Self {
+            columns: NonZeroUsize::MIN,
+            rows: NonZeroUsize::MIN,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "repr::ParamsRepr", into = "repr::ParamsRepr")]
 pub struct Params {
-    #[serde(default = "Params::default_dimensions")]
+    /// The version of the params format these params were read from. Older
+    /// params are migrated when deserialized, and params are always
+    /// serialized with [`Params::VERSION`].
+    pub version: u32,
     pub dimensions: Dimensions,
-    #[serde(default = "Params::default_spread")]
     pub spread: Spread,
-    #[serde(default = "Params::default_distance_power")]
     pub distance_power: Float,
-    #[serde(default = "Params::default_random_power")]
+    /// The power to which the total weight of the neighboring pixels is
+    /// raised before dividing by it when averaging. The default of 1 gives
+    /// a true average; larger values darken pixels with more (or more
+    /// heavily weighted) neighbors, and smaller values brighten them.
+    pub count_power: Float,
     pub random_power: Float,
-    #[serde(default = "Params::default_random_max")]
     pub random_max: Float,
-    #[serde(default = "Params::default_gamma")]
     pub gamma: Float,
-    #[serde(default = "Params::default_start_color")]
+    /// The color of the first pixel. Without the `std` feature, this must
+    /// be specified when deserializing.
     pub start_color: Color,
-    #[serde(default = "Params::default_seed", with = "seed")]
+    /// The seed for the random number generator. Without the `std` feature,
+    /// this must be specified when deserializing.
     pub seed: Seed,
+    /// Use faster approximations of floating-point functions. This speeds up
+    /// generation, but the output will not be bit-identical to an image
+    /// generated without this option, so it is best suited for previews.
+    pub fast_math: bool,
+    /// Accumulate the weighted sums used when averaging neighboring pixels
+    /// in [`f64`], even when [`Float`] is [`f32`]. This avoids banding
+    /// artifacts with large spreads and small weights, at some cost in
+    /// speed.
+    pub precise_averaging: bool,
+    /// Weight neighboring pixels equally, ignoring
+    /// [`Self::distance_power`]. The pixels in each row of the spread are
+    /// then kept as a running sum, so filling a pixel takes time
+    /// proportional to the spread size rather than its square, which makes
+    /// large spreads practical.
+    pub uniform_averaging: bool,
+    /// Weight pixels near the edge of a [`Spread::QuarterCircle`] by how
+    /// much of each is inside the circle, rather than including or
+    /// excluding them entirely. This removes ringing artifacts at small
+    /// radii. Has no effect with [`Self::uniform_averaging`], which ignores
+    /// weights.
+    pub antialias_spread: bool,
+    /// Let color components rise above 1 while filling, rather than
+    /// clamping them, so [`Self::random_max`] can drift colors past the
+    /// displayable range. Components are still kept from falling below 0,
+    /// and are clamped only when the image is written. Has no effect with
+    /// [`Storage::Compact`](crate::Storage::Compact), which stores 8-bit
+    /// components.
+    pub extended_range: bool,
+    /// The corner of the image that generation starts from, which is where
+    /// [`Self::start_color`] appears.
+    pub start_corner: Corner,
+    /// The direction in which generation proceeds from
+    /// [`Self::start_corner`]. Together, these determine the direction of
+    /// the streaks in the image.
+    pub direction: Direction,
+    /// A rotation applied to the finished image, before any passes added
+    /// with [`Generator::pass`](crate::Generator::pass).
+    pub rotation: Rotation,
+    /// Repeats the image after it's rotated, before any passes.
+    pub repeat: Repeat,
+    /// A border drawn around the image after it's repeated, before any
+    /// passes.
+    pub border: Border,
+    /// Maps the image onto two or three inks after gamma correction, before
+    /// it's rotated.
+    pub duotone: Option<Duotone>,
+    /// The maximum number of threads used to generate the image, or
+    /// [`None`] to let the caller decide (by default, one). This doesn't
+    /// affect the output.
+    pub threads: Option<NonZeroUsize>,
+    /// Expressions that vary [`Self::random_power`], [`Self::random_max`],
+    /// and [`Self::gamma`] across the image. In params files, these are
+    /// given as strings in place of the numbers.
+    pub expressions: Expressions,
 }
 
 impl Params {
+    /// Creates a [`ParamsBuilder`].
+    pub fn builder() -> ParamsBuilder {
+        ParamsBuilder::new()
+    }
+
+    /// The dimensions of the generated image, which differ from
+    /// [`Self::dimensions`] when [`Self::rotation`] or [`Self::border`]
+    /// expands the image, or [`Self::repeat`] repeats it.
+    pub fn output_dimensions(&self) -> Dimensions {
+        let dim = self.rotation.output_dimensions(self.dimensions);
+        let dim = self.repeat.output_dimensions(dim);
+        self.border.output_dimensions(dim)
+    }
+
+    /// Applies `scale` to the image dimensions and the size of the spread,
+    /// as when rendering the params at a different resolution, like a
+    /// smaller preview. The seed and start color are kept, so only the
+    /// resolution of the image changes.
+    pub fn rescale(mut self, scale: impl Fn(usize) -> usize) -> Self {
+        let dim = self.dimensions;
+        self.dimensions = Dimensions::new(scale(dim.width), scale(dim.height));
+        self.spread = self.spread.map_size(scale);
+        self
+    }
+
     fn default_dimensions() -> Dimensions {
         Dimensions::new(3840, 2160)
     }
@@ -84,6 +407,10 @@ impl Params {
         -1.75
     }
 
+    fn default_count_power() -> Float {
+        1.0
+    }
+
     fn default_random_power() -> Float {
         3.5
     }
@@ -96,10 +423,12 @@ impl Params {
         0.75
     }
 
+    #[cfg(feature = "std")]
     fn default_start_color() -> Color {
         Color::random(thread_rng())
     }
 
+    #[cfg(feature = "std")]
     fn default_seed() -> Seed {
         let mut seed = Seed::default();
         thread_rng().fill(&mut seed);
@@ -0,0 +1,265 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{Border, Color, Corner, Dimensions, Direction, Duotone};
+use super::{Expressions, Float};
+use super::{Params, ParamsError, Repeat, Rotation, Seed, Spread};
+use core::num::NonZeroUsize;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::Rng;
+
+/// Builds [`Params`]. Fields that aren't specified are given their default
+/// values, or random values in the case of the start color and seed.
+#[derive(Clone, Debug, Default)]
+pub struct ParamsBuilder {
+    dimensions: Option<Dimensions>,
+    spread: Option<Spread>,
+    distance_power: Option<Float>,
+    count_power: Option<Float>,
+    random_power: Option<Float>,
+    random_max: Option<Float>,
+    gamma: Option<Float>,
+    start_color: Option<Color>,
+    seed: Option<Seed>,
+    fast_math: bool,
+    precise_averaging: bool,
+    uniform_averaging: bool,
+    antialias_spread: bool,
+    extended_range: bool,
+    start_corner: Corner,
+    direction: Direction,
+    rotation: Rotation,
+    repeat: Repeat,
+    border: Border,
+    duotone: Option<Duotone>,
+    threads: Option<NonZeroUsize>,
+    expressions: Expressions,
+}
+
+impl ParamsBuilder {
+    /// Creates a new [`ParamsBuilder`] with no fields specified.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets [`Params::dimensions`].
+    pub fn dimensions(mut self, dimensions: Dimensions) -> Self {
+        self.dimensions = Some(dimensions);
+        self
+    }
+
+    /// Sets [`Params::spread`].
+    pub fn spread(mut self, spread: Spread) -> Self {
+        self.spread = Some(spread);
+        self
+    }
+
+    /// Sets [`Params::distance_power`].
+    pub fn distance_power(mut self, distance_power: Float) -> Self {
+        self.distance_power = Some(distance_power);
+        self
+    }
+
+    /// Sets [`Params::count_power`].
+    pub fn count_power(mut self, count_power: Float) -> Self {
+        self.count_power = Some(count_power);
+        self
+    }
+
+    /// Sets [`Params::random_power`].
+    pub fn random_power(mut self, random_power: Float) -> Self {
+        self.random_power = Some(random_power);
+        self
+    }
+
+    /// Sets [`Params::random_max`].
+    pub fn random_max(mut self, random_max: Float) -> Self {
+        self.random_max = Some(random_max);
+        self
+    }
+
+    /// Sets [`Params::gamma`].
+    pub fn gamma(mut self, gamma: Float) -> Self {
+        self.gamma = Some(gamma);
+        self
+    }
+
+    /// Sets [`Params::start_color`].
+    pub fn start_color(mut self, start_color: Color) -> Self {
+        self.start_color = Some(start_color);
+        self
+    }
+
+    /// Sets [`Params::seed`].
+    pub fn seed(mut self, seed: Seed) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets [`Params::fast_math`].
+    pub fn fast_math(mut self, fast_math: bool) -> Self {
+        self.fast_math = fast_math;
+        self
+    }
+
+    /// Sets [`Params::precise_averaging`].
+    pub fn precise_averaging(mut self, precise_averaging: bool) -> Self {
+        self.precise_averaging = precise_averaging;
+        self
+    }
+
+    /// Sets [`Params::uniform_averaging`].
+    pub fn uniform_averaging(mut self, uniform_averaging: bool) -> Self {
+        self.uniform_averaging = uniform_averaging;
+        self
+    }
+
+    /// Sets [`Params::antialias_spread`].
+    pub fn antialias_spread(mut self, antialias_spread: bool) -> Self {
+        self.antialias_spread = antialias_spread;
+        self
+    }
+
+    /// Sets [`Params::extended_range`].
+    pub fn extended_range(mut self, extended_range: bool) -> Self {
+        self.extended_range = extended_range;
+        self
+    }
+
+    /// Sets [`Params::start_corner`].
+    pub fn start_corner(mut self, start_corner: Corner) -> Self {
+        self.start_corner = start_corner;
+        self
+    }
+
+    /// Sets [`Params::direction`].
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Sets [`Params::rotation`].
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets [`Params::repeat`].
+    pub fn repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Sets [`Params::border`].
+    pub fn border(mut self, border: Border) -> Self {
+        self.border = border;
+        self
+    }
+
+    /// Sets [`Params::duotone`].
+    pub fn duotone(mut self, duotone: Duotone) -> Self {
+        self.duotone = Some(duotone);
+        self
+    }
+
+    /// Sets [`Params::threads`].
+    pub fn threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Sets [`Params::expressions`].
+    pub fn expressions(mut self, expressions: Expressions) -> Self {
+        self.expressions = expressions;
+        self
+    }
+
+    #[cfg(feature = "std")]
+    /// Builds the [`Params`]. If the start color or seed weren't specified,
+    /// they're generated with [`thread_rng`].
+    pub fn build(self) -> Params {
+        self.build_with_rng(thread_rng())
+    }
+
+    /// Builds the [`Params`], returning an error if the start color or seed
+    /// weren't specified. This guarantees that the params fully determine
+    /// the image, which is useful for archival and reproducible pipelines.
+    pub fn build_strict(self) -> Result<Params, ParamsError> {
+        let Some(start_color) = self.start_color else {
+            return Err(ParamsError::Unspecified("start_color"));
+        };
+        let Some(seed) = self.seed else {
+            return Err(ParamsError::Unspecified("seed"));
+        };
+        Ok(self.build_with(start_color, seed))
+    }
+
+    /// Builds the [`Params`]. If the start color or seed weren't specified,
+    /// they're generated with `rng`.
+    pub fn build_with_rng<R: Rng>(self, mut rng: R) -> Params {
+        let start_color =
+            self.start_color.unwrap_or_else(|| Color::random(&mut rng));
+        let seed = self.seed.unwrap_or_else(|| {
+            let mut seed = Seed::default();
+            rng.fill(&mut seed);
+            seed
+        });
+        self.build_with(start_color, seed)
+    }
+
+    /// Builds the [`Params`] with the given start color and seed.
+    fn build_with(self, start_color: Color, seed: Seed) -> Params {
+        Params {
+            version: Params::VERSION,
+            dimensions: self
+                .dimensions
+                .unwrap_or_else(Params::default_dimensions),
+            spread: self.spread.unwrap_or_else(Params::default_spread),
+            distance_power: self
+                .distance_power
+                .unwrap_or_else(Params::default_distance_power),
+            count_power: self
+                .count_power
+                .unwrap_or_else(Params::default_count_power),
+            random_power: self
+                .random_power
+                .unwrap_or_else(Params::default_random_power),
+            random_max: self
+                .random_max
+                .unwrap_or_else(Params::default_random_max),
+            gamma: self.gamma.unwrap_or_else(Params::default_gamma),
+            start_color,
+            seed,
+            fast_math: self.fast_math,
+            precise_averaging: self.precise_averaging,
+            uniform_averaging: self.uniform_averaging,
+            antialias_spread: self.antialias_spread,
+            extended_range: self.extended_range,
+            start_corner: self.start_corner,
+            direction: self.direction,
+            rotation: self.rotation,
+            repeat: self.repeat,
+            border: self.border,
+            duotone: self.duotone,
+            threads: self.threads,
+            expressions: self.expressions,
+        }
+    }
+}
@@ -0,0 +1,232 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! A normalized serialization of [`Params`] for comparing params and keying
+//! caches.
+
+use super::seed::seed_to_hex;
+use super::{Border, Color, Duotone, Expr, Float, Params, Rotation, Spread};
+use alloc::string::String;
+use core::fmt::{self, Display, Write};
+
+/// A hash of the [canonical form](Params::canonicalize) of params. Params
+/// with the same hash generate the same image.
+///
+/// The hash is the [BLAKE3](https://github.com/BLAKE3-team/BLAKE3) hash of
+/// the canonical text, and it's displayed as 64 hex digits.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ParamsHash(pub [u8; 32]);
+
+impl Display for ParamsHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// The canonical form of params, returned by [`Params::canonicalize`].
+/// Displaying it gives the canonical text.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CanonicalParams {
+    text: String,
+    hash: ParamsHash,
+}
+
+impl CanonicalParams {
+    /// The canonical text, which is itself valid params in RON.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The hash of the canonical text.
+    pub fn hash(&self) -> ParamsHash {
+        self.hash
+    }
+}
+
+impl Display for CanonicalParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Formats a number, writing negative zero as zero. Floats are displayed
+/// with the fewest digits that read back as the same number, so the text
+/// is exact.
+fn number(n: Float) -> impl Display {
+    n + 0.0
+}
+
+/// Writes a field that may be an expression.
+fn write_value(w: &mut String, n: Float, expr: Option<&Expr>) -> fmt::Result {
+    match expr {
+        Some(expr) => write!(w, "\"{expr}\""),
+        None => write!(w, "{}", number(n)),
+    }
+}
+
+/// Writes a color by its exact components.
+fn write_color(w: &mut String, color: Color) -> fmt::Result {
+    write!(
+        w,
+        "(red:{},green:{},blue:{})",
+        number(color.red),
+        number(color.green),
+        number(color.blue),
+    )
+}
+
+impl Params {
+    /// Normalizes the params and serializes them in a stable form, along
+    /// with a hash of that form. Two params have the same canonical form if
+    /// they differ only in ways that can't change the image, so this can
+    /// compare params for equality and key caches of generated images.
+    ///
+    /// Normalizing leaves out [`Self::threads`], writes the current
+    /// [`Self::VERSION`], gives expressions in place of the numbers they
+    /// override, and resets fields that have no effect, like
+    /// [`Self::distance_power`] with [`Self::uniform_averaging`] or the
+    /// color of a border with no width. Expressions are compared by their
+    /// text.
+    ///
+    /// The canonical text is compact RON that reads back as the normalized
+    /// params. It won't change between versions of Plumage unless the params
+    /// format does.
+    pub fn canonicalize(&self) -> CanonicalParams {
+        let text = self.normalized().canonical_text();
+        let hash = ParamsHash(*blake3::hash(text.as_bytes()).as_bytes());
+        CanonicalParams {
+            text,
+            hash,
+        }
+    }
+
+    /// A copy of the params with fields that don't affect the image reset.
+    fn normalized(&self) -> Self {
+        let mut params = self.clone();
+        params.version = Self::VERSION;
+        params.threads = None;
+        // Weights are ignored with uniform averaging, and only circular
+        // spreads are antialiased.
+        if params.uniform_averaging {
+            params.distance_power = Self::default_distance_power();
+            params.precise_averaging = false;
+            params.antialias_spread = false;
+        }
+        if matches!(params.spread, Spread::Square { .. }) {
+            params.antialias_spread = false;
+        }
+        if params.rotation.is_identity() {
+            params.rotation = Rotation::default();
+        }
+        if params.border.is_identity() {
+            params.border = Border::default();
+        }
+        // A border that fades to its own color is solid.
+        let components = |c: Color| [c.red, c.green, c.blue];
+        let border = params.border;
+        if border.inner_color.map(components) == Some(components(border.color))
+        {
+            params.border.inner_color = None;
+        }
+        params
+    }
+
+    /// Serializes the params in their canonical form.
+    fn canonical_text(&self) -> String {
+        let mut text = String::new();
+        self.write_canonical(&mut text).expect("writing to a string failed");
+        text
+    }
+
+    /// Writes the fields in the order they're declared.
+    fn write_canonical(&self, w: &mut String) -> fmt::Result {
+        let exprs = &self.expressions;
+        let dim = self.dimensions;
+        write!(w, "(version:{}", self.version)?;
+        write!(w, ",dimensions:(width:{},height:{})", dim.width, dim.height)?;
+        match self.spread {
+            Spread::Square {
+                width,
+            } => write!(w, ",spread:Square(width:{width})")?,
+            Spread::QuarterCircle {
+                radius,
+            } => write!(w, ",spread:QuarterCircle(radius:{radius})")?,
+        }
+        write!(w, ",distance_power:{}", number(self.distance_power))?;
+        write!(w, ",count_power:{}", number(self.count_power))?;
+        w.push_str(",random_power:");
+        write_value(w, self.random_power, exprs.random_power.as_ref())?;
+        w.push_str(",random_max:");
+        write_value(w, self.random_max, exprs.random_max.as_ref())?;
+        w.push_str(",gamma:");
+        write_value(w, self.gamma, exprs.gamma.as_ref())?;
+        w.push_str(",start_color:");
+        write_color(w, self.start_color)?;
+        write!(w, ",seed:\"{}\"", seed_to_hex(&self.seed))?;
+        write!(w, ",fast_math:{}", self.fast_math)?;
+        write!(w, ",precise_averaging:{}", self.precise_averaging)?;
+        write!(w, ",uniform_averaging:{}", self.uniform_averaging)?;
+        write!(w, ",antialias_spread:{}", self.antialias_spread)?;
+        write!(w, ",extended_range:{}", self.extended_range)?;
+        write!(w, ",start_corner:{:?}", self.start_corner)?;
+        write!(w, ",direction:{:?}", self.direction)?;
+        let rotation = self.rotation;
+        write!(
+            w,
+            ",rotation:(degrees:{},fit:{:?})",
+            number(rotation.degrees),
+            rotation.fit,
+        )?;
+        let repeat = self.repeat;
+        write!(
+            w,
+            ",repeat:(columns:{},rows:{})",
+            repeat.columns, repeat.rows,
+        )?;
+        let border = self.border;
+        write!(w, ",border:(width:{},color:", border.width)?;
+        write_color(w, border.color)?;
+        if let Some(inner) = border.inner_color {
+            w.push_str(",inner_color:Some(");
+            write_color(w, inner)?;
+            w.push(')');
+        }
+        write!(w, ",fit:{:?})", border.fit)?;
+        if let Some(duotone) = self.duotone {
+            write_duotone(w, duotone)?;
+        }
+        w.push(')');
+        Ok(())
+    }
+}
+
+/// Writes the `duotone` field.
+fn write_duotone(w: &mut String, duotone: Duotone) -> fmt::Result {
+    w.push_str(",duotone:Some((shadows:");
+    write_color(w, duotone.shadows)?;
+    if let Some(mid) = duotone.midtones {
+        w.push_str(",midtones:Some(");
+        write_color(w, mid)?;
+        w.push(')');
+    }
+    w.push_str(",highlights:");
+    write_color(w, duotone.highlights)?;
+    w.push_str("))");
+    Ok(())
+}
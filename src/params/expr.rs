@@ -0,0 +1,450 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Expressions that vary params across the image, evaluated per pixel by a
+//! small interpreter.
+
+use super::super::{math, Dimensions, Float, Position};
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+use core::str::FromStr;
+
+/// The maximum nesting depth of an expression, and of the tree it's parsed
+/// into, which keeps parsing, evaluation, and dropping from overflowing the
+/// stack.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Copy, Debug)]
+enum Var {
+    X,
+    Y,
+    Width,
+    Height,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Func {
+    Abs,
+    Sqrt,
+    Sin,
+    Cos,
+    Min,
+    Max,
+    Pow,
+}
+
+impl Func {
+    /// Looks up a function and the number of arguments it takes.
+    fn from_name(name: &str) -> Option<(Self, usize)> {
+        Some(match name {
+            "abs" => (Self::Abs, 1),
+            "sqrt" => (Self::Sqrt, 1),
+            "sin" => (Self::Sin, 1),
+            "cos" => (Self::Cos, 1),
+            "min" => (Self::Min, 2),
+            "max" => (Self::Max, 2),
+            "pow" => (Self::Pow, 2),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Number(Float),
+    Var(Var),
+    Neg(Box<Node>),
+    Binary(Op, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+/// The values of the variables in an expression.
+struct Vars {
+    x: Float,
+    y: Float,
+    width: Float,
+    height: Float,
+}
+
+impl Node {
+    fn eval(&self, vars: &Vars) -> Float {
+        match self {
+            Self::Number(n) => *n,
+            Self::Var(Var::X) => vars.x,
+            Self::Var(Var::Y) => vars.y,
+            Self::Var(Var::Width) => vars.width,
+            Self::Var(Var::Height) => vars.height,
+            Self::Neg(node) => -node.eval(vars),
+            Self::Binary(op, a, b) => {
+                let (a, b) = (a.eval(vars), b.eval(vars));
+                match op {
+                    Op::Add => a + b,
+                    Op::Sub => a - b,
+                    Op::Mul => a * b,
+                    Op::Div => a / b,
+                    Op::Pow => math::powf(a, b),
+                }
+            }
+            Self::Call(func, args) => {
+                let arg = |i: usize| args[i].eval(vars);
+                match func {
+                    Func::Abs => arg(0).abs(),
+                    Func::Sqrt => math::sqrt(arg(0)),
+                    Func::Sin => math::sin(arg(0)),
+                    Func::Cos => math::cos(arg(0)),
+                    Func::Min => arg(0).min(arg(1)),
+                    Func::Max => arg(0).max(arg(1)),
+                    Func::Pow => math::powf(arg(0), arg(1)),
+                }
+            }
+        }
+    }
+}
+
+/// An error returned when parsing an [`Expr`].
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseExprError {
+    /// The expression ended early.
+    UnexpectedEnd,
+    /// The given character wasn't expected.
+    UnexpectedChar(char),
+    /// A number couldn't be parsed.
+    InvalidNumber(String),
+    /// The name isn't a variable or function.
+    UnknownName(String),
+    /// The function was called with the wrong number of arguments.
+    WrongArgCount {
+        name: String,
+        expected: usize,
+    },
+    /// The expression is nested too deeply.
+    TooDeep,
+}
+
+impl Display for ParseExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            Self::UnexpectedChar(c) => write!(f, "unexpected `{c}`"),
+            Self::InvalidNumber(n) => write!(f, "invalid number: {n}"),
+            Self::UnknownName(name) => write!(f, "unknown name: {name}"),
+            Self::WrongArgCount {
+                name,
+                expected,
+            } => write!(f, "`{name}` takes {expected} argument(s)"),
+            Self::TooDeep => write!(f, "expression is nested too deeply"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseExprError {}
+
+/// A parsed node, along with the depth of the tree it's the root of.
+type Parsed = (Node, usize);
+
+/// Pairs `node` with its depth, given the greatest depth of its children,
+/// failing if it's too deep.
+fn parsed(node: Node, child_depth: usize) -> Result<Parsed, ParseExprError> {
+    let depth = child_depth + 1;
+    if depth > MAX_DEPTH {
+        return Err(ParseExprError::TooDeep);
+    }
+    Ok((node, depth))
+}
+
+/// A recursive-descent parser for expressions.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// Skips whitespace and returns the next character, without consuming
+    /// it.
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+        self.text[self.pos..].chars().next()
+    }
+
+    /// Consumes the next character if it's `c`.
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += c.len_utf8();
+        }
+        found
+    }
+
+    /// Consumes the next character, which must be `c`.
+    fn expect(&mut self, c: char) -> Result<(), ParseExprError> {
+        if self.eat(c) {
+            return Ok(());
+        }
+        Err(match self.peek() {
+            Some(c) => ParseExprError::UnexpectedChar(c),
+            None => ParseExprError::UnexpectedEnd,
+        })
+    }
+
+    /// Consumes characters while `f` returns true, returning them.
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &str {
+        let start = self.pos;
+        let rest = &self.text[start..];
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Enters a nested part of the expression, failing if it's nested too
+    /// deeply. [`Self::depth`] must be decremented when leaving it.
+    fn enter(&mut self) -> Result<(), ParseExprError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ParseExprError::TooDeep);
+        }
+        Ok(())
+    }
+
+    /// expr := term (("+" | "-") term)*
+    fn expr(&mut self) -> Result<Parsed, ParseExprError> {
+        self.enter()?;
+        let (mut node, mut depth) = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                break;
+            };
+            let (rhs, rhs_depth) = self.term()?;
+            let binary = Node::Binary(op, Box::new(node), Box::new(rhs));
+            (node, depth) = parsed(binary, depth.max(rhs_depth))?;
+        }
+        self.depth -= 1;
+        Ok((node, depth))
+    }
+
+    /// term := unary (("*" | "/") unary)*
+    fn term(&mut self) -> Result<Parsed, ParseExprError> {
+        let (mut node, mut depth) = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else {
+                break;
+            };
+            let (rhs, rhs_depth) = self.unary()?;
+            let binary = Node::Binary(op, Box::new(node), Box::new(rhs));
+            (node, depth) = parsed(binary, depth.max(rhs_depth))?;
+        }
+        Ok((node, depth))
+    }
+
+    /// unary := "-" unary | power
+    fn unary(&mut self) -> Result<Parsed, ParseExprError> {
+        if !self.eat('-') {
+            return self.power();
+        }
+        self.enter()?;
+        let (node, depth) = self.unary()?;
+        self.depth -= 1;
+        parsed(Node::Neg(Box::new(node)), depth)
+    }
+
+    /// power := atom ("^" unary)?
+    fn power(&mut self) -> Result<Parsed, ParseExprError> {
+        let (base, base_depth) = self.atom()?;
+        if !self.eat('^') {
+            return Ok((base, base_depth));
+        }
+        self.enter()?;
+        let (exp, exp_depth) = self.unary()?;
+        self.depth -= 1;
+        let binary = Node::Binary(Op::Pow, Box::new(base), Box::new(exp));
+        parsed(binary, base_depth.max(exp_depth))
+    }
+
+    /// atom := number | variable | function "(" expr ("," expr)* ")"
+    ///       | "(" expr ")"
+    fn atom(&mut self) -> Result<Parsed, ParseExprError> {
+        let Some(c) = self.peek() else {
+            return Err(ParseExprError::UnexpectedEnd);
+        };
+        if self.eat('(') {
+            let parsed = self.expr()?;
+            self.expect(')')?;
+            return Ok(parsed);
+        }
+        if c.is_ascii_digit() || c == '.' {
+            let text = self.take_while(|c| c.is_ascii_digit() || c == '.');
+            return text
+                .parse()
+                .map(|n| (Node::Number(n), 1))
+                .map_err(|_| ParseExprError::InvalidNumber(text.into()));
+        }
+        if !c.is_ascii_alphabetic() {
+            return Err(ParseExprError::UnexpectedChar(c));
+        }
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        let var = match name {
+            "x" => Some(Var::X),
+            "y" => Some(Var::Y),
+            "width" => Some(Var::Width),
+            "height" => Some(Var::Height),
+            _ => None,
+        };
+        if let Some(var) = var {
+            return Ok((Node::Var(var), 1));
+        }
+        let Some((func, arity)) = Func::from_name(name) else {
+            return Err(ParseExprError::UnknownName(name.into()));
+        };
+        let name = name.to_string();
+        self.expect('(')?;
+        let mut args = Vec::with_capacity(arity);
+        let mut depth = 0;
+        loop {
+            let (arg, arg_depth) = self.expr()?;
+            args.push(arg);
+            depth = depth.max(arg_depth);
+            if !self.eat(',') {
+                break;
+            }
+        }
+        self.expect(')')?;
+        if args.len() != arity {
+            return Err(ParseExprError::WrongArgCount {
+                name,
+                expected: arity,
+            });
+        }
+        parsed(Node::Call(func, args), depth)
+    }
+}
+
+/// An expression that gives a value for each pixel in the image, like
+/// `"0.02 + 0.08 * y / height"`.
+///
+/// Expressions can use numbers, the variables `x`, `y`, `width`, and
+/// `height`, the operators `+`, `-`, `*`, `/`, and `^` (exponentiation),
+/// parentheses, and the functions `abs`, `sqrt`, `sin`, `cos`, `min`,
+/// `max`, and `pow`.
+#[derive(Clone, Debug)]
+pub struct Expr {
+    source: String,
+    root: Node,
+}
+
+impl Expr {
+    /// Parses an expression.
+    pub fn parse(text: &str) -> Result<Self, ParseExprError> {
+        let mut parser = Parser {
+            text,
+            pos: 0,
+            depth: 0,
+        };
+        let (root, _) = parser.expr()?;
+        if let Some(c) = parser.peek() {
+            return Err(ParseExprError::UnexpectedChar(c));
+        }
+        Ok(Self {
+            source: text.into(),
+            root,
+        })
+    }
+
+    /// Evaluates the expression at `pos` in an image with dimensions `dim`.
+    pub fn eval(&self, pos: Position, dim: Dimensions) -> Float {
+        self.root.eval(&Vars {
+            x: pos.x as Float,
+            y: pos.y as Float,
+            width: dim.width as Float,
+            height: dim.height as Float,
+        })
+    }
+
+    /// The text the expression was parsed from.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ParseExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Expressions that vary params across the image. Each expression
+/// overrides the number in the corresponding field of
+/// [`Params`](super::Params), which is set to the expression's value at
+/// the top-left pixel when deserialized.
+///
+/// [`Params::distance_power`](super::Params::distance_power) can't vary,
+/// as the weights of neighboring pixels are computed once per image.
+#[derive(Clone, Debug, Default)]
+pub struct Expressions {
+    pub random_power: Option<Expr>,
+    pub random_max: Option<Expr>,
+    pub gamma: Option<Expr>,
+}
+
+impl Expressions {
+    /// Whether no expressions are given.
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// The given expressions, along with the names of their fields.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &Expr)> {
+        [
+            ("random_power", &self.random_power),
+            ("random_max", &self.random_max),
+            ("gamma", &self.gamma),
+        ]
+        .into_iter()
+        .filter_map(|(name, expr)| Some((name, expr.as_ref()?)))
+    }
+}
@@ -0,0 +1,98 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{Float, Params, ParamsBuilder, Spread};
+
+/// A named set of params.
+struct Preset {
+    name: &'static str,
+    spread: Spread,
+    distance_power: Float,
+    random_power: Float,
+    random_max: Float,
+    gamma: Float,
+}
+
+static PRESETS: [Preset; 4] = [
+    Preset {
+        name: "classic",
+        spread: Spread::Square {
+            width: 5,
+        },
+        distance_power: -1.75,
+        random_power: 3.5,
+        random_max: 0.05,
+        gamma: 0.75,
+    },
+    Preset {
+        name: "fine-silk",
+        spread: Spread::QuarterCircle {
+            radius: 8,
+        },
+        distance_power: -1.25,
+        random_power: 4.5,
+        random_max: 0.03,
+        gamma: 0.8,
+    },
+    Preset {
+        name: "storm",
+        spread: Spread::Square {
+            width: 3,
+        },
+        distance_power: -2.5,
+        random_power: 2.0,
+        random_max: 0.12,
+        gamma: 0.65,
+    },
+    Preset {
+        name: "pastel-drift",
+        spread: Spread::QuarterCircle {
+            radius: 6,
+        },
+        distance_power: -1.5,
+        random_power: 5.0,
+        random_max: 0.04,
+        gamma: 0.5,
+    },
+];
+
+impl Params {
+    /// The names of the built-in presets, for use with [`Self::preset`].
+    pub fn preset_names() -> impl Iterator<Item = &'static str> {
+        PRESETS.iter().map(|preset| preset.name)
+    }
+
+    /// Gets a built-in preset by name, or [`None`] if there's no preset
+    /// with that name. See [`Self::preset_names`].
+    ///
+    /// Presets set [`Self::spread`], [`Self::distance_power`],
+    /// [`Self::random_power`], [`Self::random_max`], and [`Self::gamma`].
+    /// Other fields can be set with the returned builder.
+    pub fn preset(name: &str) -> Option<ParamsBuilder> {
+        let preset = PRESETS.iter().find(|preset| preset.name == name)?;
+        Some(
+            ParamsBuilder::new()
+                .spread(preset.spread)
+                .distance_power(preset.distance_power)
+                .random_power(preset.random_power)
+                .random_max(preset.random_max)
+                .gamma(preset.gamma),
+        )
+    }
+}
@@ -0,0 +1,115 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{Float, Params, ParamsBuilder, Spread};
+use core::ops::RangeInclusive;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// The ranges from which [`Params::randomize`] samples each field. When
+/// deserializing, missing fields have their default ranges.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParamRanges {
+    /// The width or radius of the spread. The shape is chosen at random.
+    pub spread: RangeInclusive<usize>,
+    pub distance_power: RangeInclusive<Float>,
+    pub random_power: RangeInclusive<Float>,
+    pub random_max: RangeInclusive<Float>,
+    pub gamma: RangeInclusive<Float>,
+}
+
+impl Default for ParamRanges {
+    /// Ranges that generally produce good-looking images.
+    fn default() -> Self {
+        Self {
+            spread: 2..=10,
+            distance_power: -3.0..=-0.5,
+            random_power: 1.5..=6.0,
+            random_max: 0.01..=0.15,
+            gamma: 0.4..=1.2,
+        }
+    }
+}
+
+impl Params {
+    /// Creates params whose numeric fields are sampled from `ranges` with
+    /// `rng`. The start color and seed are also random, and other fields
+    /// have their default values. The returned params are fully concrete,
+    /// so they can be serialized to reproduce the image.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if any range is empty.
+    pub fn randomize<R: Rng>(ranges: &ParamRanges, mut rng: R) -> Self {
+        let size = rng.gen_range(ranges.spread.clone());
+        let spread = if rng.gen() {
+            Spread::Square {
+                width: size,
+            }
+        } else {
+            Spread::QuarterCircle {
+                radius: size,
+            }
+        };
+        ParamsBuilder::new()
+            .spread(spread)
+            .distance_power(rng.gen_range(ranges.distance_power.clone()))
+            .random_power(rng.gen_range(ranges.random_power.clone()))
+            .random_max(rng.gen_range(ranges.random_max.clone()))
+            .gamma(rng.gen_range(ranges.gamma.clone()))
+            .build_with_rng(rng)
+    }
+    /// Creates a slightly different copy of the params, for exploring
+    /// variations of an image. Each numeric field is scaled by a random
+    /// factor between `1 - rate` and `1 + rate`, and the size of the spread
+    /// changes by one with probability `rate`. The start color, seed, and
+    /// other fields are kept, so each mutation can be recorded and the
+    /// process repeated on the most promising results.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `rate` isn't between 0 and 1.
+    pub fn mutate<R: Rng>(&self, rate: Float, mut rng: R) -> Self {
+        assert!((0.0..=1.0).contains(&rate), "rate must be between 0 and 1");
+        let mut scale = |n: Float| n * (1.0 + rng.gen_range(-rate..=rate));
+        let mut params = self.clone();
+        params.distance_power = scale(params.distance_power);
+        params.random_power = scale(params.random_power);
+        params.random_max = scale(params.random_max);
+        params.gamma = scale(params.gamma);
+
+        let size = match &mut params.spread {
+            Spread::Square {
+                width,
+            } => width,
+            Spread::QuarterCircle {
+                radius,
+            } => radius,
+        };
+        if rng.gen::<Float>() < rate {
+            *size = if rng.gen() {
+                size.saturating_add(1)
+            } else {
+                size.saturating_sub(1).max(1)
+            };
+        }
+        params
+    }
+}
@@ -0,0 +1,248 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The serialized form of [`Params`], in which [`Params::random_power`],
+//! [`Params::random_max`], and [`Params::gamma`] may be either numbers or
+//! [expressions](super::expr).
+
+use super::expr::{Expr, Expressions};
+use super::super::Position;
+use super::{seed, version, Border, Color, Corner, Dimensions, Direction};
+use super::{Duotone, Float};
+use super::{Params, Repeat, Rotation, Seed, Spread};
+use core::fmt;
+use core::num::NonZeroUsize;
+use serde::de::{Error, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A field that's either a number or an expression.
+#[derive(Clone)]
+pub enum Value {
+    Number(Float),
+    Expr(Expr),
+}
+
+impl Value {
+    /// Converts the value to a number and an optional expression. The
+    /// number is the expression's value at the first pixel.
+    fn split(self, dim: Dimensions) -> (Float, Option<Expr>) {
+        match self {
+            Self::Number(n) => (n, None),
+            Self::Expr(expr) => {
+                (expr.eval(Position::ZERO, dim), Some(expr))
+            }
+        }
+    }
+
+    fn join(n: Float, expr: Option<Expr>) -> Self {
+        expr.map_or(Self::Number(n), Self::Expr)
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Number(n) => n.serialize(serializer),
+            Self::Expr(expr) => serializer.serialize_str(expr.as_str()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl Visitor<'_> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a number or an expression string")
+    }
+
+    // `Float` may be `f64`.
+    #[allow(clippy::unnecessary_cast)]
+    fn visit_f32<E: Error>(self, v: f32) -> Result<Value, E> {
+        Ok(Value::Number(v as Float))
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    fn visit_f64<E: Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Number(v as Float))
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Number(v as Float))
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(v as Float))
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Value, E> {
+        Expr::parse(v)
+            .map(Value::Expr)
+            .map_err(|e| E::custom(format_args!("invalid expression: {e}")))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Params")]
+pub struct ParamsRepr {
+    #[serde(default = "version::legacy", with = "version")]
+    version: u32,
+    #[serde(default = "Params::default_dimensions")]
+    dimensions: Dimensions,
+    #[serde(default = "Params::default_spread")]
+    spread: Spread,
+    #[serde(default = "Params::default_distance_power")]
+    distance_power: Float,
+    #[serde(default = "Params::default_count_power")]
+    count_power: Float,
+    #[serde(default = "default_random_power")]
+    random_power: Value,
+    #[serde(default = "default_random_max")]
+    random_max: Value,
+    #[serde(default = "default_gamma")]
+    gamma: Value,
+    #[cfg_attr(
+        feature = "std",
+        serde(default = "Params::default_start_color")
+    )]
+    start_color: Color,
+    #[cfg_attr(feature = "std", serde(default = "Params::default_seed"))]
+    #[serde(with = "seed")]
+    seed: Seed,
+    #[serde(default)]
+    fast_math: bool,
+    #[serde(default)]
+    precise_averaging: bool,
+    #[serde(default)]
+    uniform_averaging: bool,
+    #[serde(default)]
+    antialias_spread: bool,
+    #[serde(default)]
+    extended_range: bool,
+    #[serde(default)]
+    start_corner: Corner,
+    #[serde(default)]
+    direction: Direction,
+    #[serde(default)]
+    rotation: Rotation,
+    #[serde(default)]
+    repeat: Repeat,
+    #[serde(default)]
+    border: Border,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duotone: Option<Duotone>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    threads: Option<NonZeroUsize>,
+}
+
+fn default_random_power() -> Value {
+    Value::Number(Params::default_random_power())
+}
+
+fn default_random_max() -> Value {
+    Value::Number(Params::default_random_max())
+}
+
+fn default_gamma() -> Value {
+    Value::Number(Params::default_gamma())
+}
+
+impl From<ParamsRepr> for Params {
+    fn from(repr: ParamsRepr) -> Self {
+        let dim = repr.dimensions;
+        let (random_power, random_power_expr) = repr.random_power.split(dim);
+        let (random_max, random_max_expr) = repr.random_max.split(dim);
+        let (gamma, gamma_expr) = repr.gamma.split(dim);
+        Self {
+            version: repr.version,
+            dimensions: repr.dimensions,
+            spread: repr.spread,
+            distance_power: repr.distance_power,
+            count_power: repr.count_power,
+            random_power,
+            random_max,
+            gamma,
+            start_color: repr.start_color,
+            seed: repr.seed,
+            fast_math: repr.fast_math,
+            precise_averaging: repr.precise_averaging,
+            uniform_averaging: repr.uniform_averaging,
+            antialias_spread: repr.antialias_spread,
+            extended_range: repr.extended_range,
+            start_corner: repr.start_corner,
+            direction: repr.direction,
+            rotation: repr.rotation,
+            repeat: repr.repeat,
+            border: repr.border,
+            duotone: repr.duotone,
+            threads: repr.threads,
+            expressions: Expressions {
+                random_power: random_power_expr,
+                random_max: random_max_expr,
+                gamma: gamma_expr,
+            },
+        }
+    }
+}
+
+impl From<Params> for ParamsRepr {
+    fn from(params: Params) -> Self {
+        let exprs = params.expressions;
+        Self {
+            version: params.version,
+            dimensions: params.dimensions,
+            spread: params.spread,
+            distance_power: params.distance_power,
+            count_power: params.count_power,
+            random_power: Value::join(
+                params.random_power,
+                exprs.random_power,
+            ),
+            random_max: Value::join(params.random_max, exprs.random_max),
+            gamma: Value::join(params.gamma, exprs.gamma),
+            start_color: params.start_color,
+            seed: params.seed,
+            fast_math: params.fast_math,
+            precise_averaging: params.precise_averaging,
+            uniform_averaging: params.uniform_averaging,
+            antialias_spread: params.antialias_spread,
+            extended_range: params.extended_range,
+            start_corner: params.start_corner,
+            direction: params.direction,
+            rotation: params.rotation,
+            repeat: params.repeat,
+            border: params.border,
+            duotone: params.duotone,
+            threads: params.threads,
+        }
+    }
+}
@@ -0,0 +1,323 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{Params, Spread};
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Display;
+
+/// Describes a single number field.
+fn number(description: &str, default: impl Display) -> String {
+    format!(
+        r#"{{
+      "description": "{description}",
+      "type": "number",
+      "default": {default}"#,
+    )
+}
+
+/// Describes a number field that may also be an expression. Range keywords
+/// apply only to numbers, so they don't constrain expressions.
+fn number_or_expr(description: &str, default: impl Display) -> String {
+    let note = "May also be an expression of x, y, width, and height, \
+                like \\\"0.5 + y / height\\\".";
+    format!(
+        r#"{{
+      "description": "{description} {note}",
+      "type": ["number", "string"],
+      "default": {default}"#,
+    )
+}
+
+impl Params {
+    /// A [JSON Schema](https://json-schema.org/) describing [`Params`] as
+    /// they're serialized, including default values and valid ranges. This
+    /// allows editors and other front-ends to offer validated forms for
+    /// params files.
+    ///
+    /// Some constraints, like the maximum image size, depend on more than
+    /// one field and can't be expressed in the schema; use
+    /// [`Params::validate`] to check them.
+    pub fn json_schema() -> String {
+        let dim = Self::default_dimensions();
+        let spread = match Self::default_spread() {
+            Spread::Square {
+                width,
+            } => format!(r#"{{ "Square": {{ "width": {width} }} }}"#),
+            Spread::QuarterCircle {
+                radius,
+            } => {
+                format!(r#"{{ "QuarterCircle": {{ "radius": {radius} }} }}"#)
+            }
+        };
+        let distance_power = number(
+            "The power to which the distance to each neighboring pixel is \
+             raised to get its weight when averaging.",
+            Self::default_distance_power(),
+        );
+        let count_power = number(
+            "The power to which the total weight of the neighboring pixels \
+             is raised before dividing by it. 1 gives a true average.",
+            Self::default_count_power(),
+        );
+        let random_power = number_or_expr(
+            "The power to which random adjustments are raised. Higher \
+             values make large adjustments rarer.",
+            Self::default_random_power(),
+        );
+        let random_max = number_or_expr(
+            "The maximum amount by which each component is randomly \
+             adjusted.",
+            Self::default_random_max(),
+        );
+        let gamma = number_or_expr(
+            "The gamma correction applied to the finished image.",
+            Self::default_gamma(),
+        );
+        format!(
+            r##"{{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "title": "Plumage params",
+  "type": "object",
+  "properties": {{
+    "version": {{
+      "description": "The version of the params format.",
+      "type": "integer",
+      "minimum": 0,
+      "maximum": {version}
+    }},
+    "dimensions": {{
+      "description": "The size of the image.",
+      "oneOf": [
+        {{
+          "type": "object",
+          "properties": {{
+            "width": {{ "type": "integer", "minimum": 1 }},
+            "height": {{ "type": "integer", "minimum": 1 }}
+          }},
+          "required": ["width", "height"],
+          "additionalProperties": false
+        }},
+        {{
+          "type": "array",
+          "prefixItems": [
+            {{ "type": "integer", "minimum": 1 }},
+            {{ "type": "integer", "minimum": 1 }}
+          ],
+          "items": false,
+          "minItems": 2
+        }},
+        {{
+          "type": "string",
+          "pattern": "{dim_pattern}"
+        }}
+      ],
+      "default": {{ "width": {width}, "height": {height} }}
+    }},
+    "spread": {{
+      "description": "The area of neighboring pixels that are averaged.",
+      "oneOf": [
+        {{
+          "type": "object",
+          "properties": {{
+            "Square": {{
+              "type": "object",
+              "properties": {{
+                "width": {{ "type": "integer", "minimum": 1 }}
+              }},
+              "required": ["width"],
+              "additionalProperties": false
+            }}
+          }},
+          "required": ["Square"],
+          "additionalProperties": false
+        }},
+        {{
+          "type": "object",
+          "properties": {{
+            "QuarterCircle": {{
+              "type": "object",
+              "properties": {{
+                "radius": {{ "type": "integer", "minimum": 1 }}
+              }},
+              "required": ["radius"],
+              "additionalProperties": false
+            }}
+          }},
+          "required": ["QuarterCircle"],
+          "additionalProperties": false
+        }}
+      ],
+      "default": {spread}
+    }},
+    "distance_power": {distance_power}
+    }},
+    "count_power": {count_power}
+    }},
+    "random_power": {random_power},
+      "exclusiveMinimum": 0
+    }},
+    "random_max": {random_max},
+      "minimum": 0
+    }},
+    "gamma": {gamma},
+      "exclusiveMinimum": 0
+    }},
+    "start_color": {{
+      "description": "The color of the first pixel. Random by default.",
+      "$ref": "#/$defs/color"
+    }},
+    "seed": {{
+      "description": "The random seed, as 64 hex digits. Random by default.",
+      "type": "string",
+      "pattern": "^[0-9a-fA-F]{{64}}$"
+    }},
+    "fast_math": {{
+      "description": "Use faster, approximate math.",
+      "type": "boolean",
+      "default": false
+    }},
+    "precise_averaging": {{
+      "description": "Average in 64-bit floats to avoid banding.",
+      "type": "boolean",
+      "default": false
+    }},
+    "uniform_averaging": {{
+      "description": "Weight neighbors equally. Much faster.",
+      "type": "boolean",
+      "default": false
+    }},
+    "antialias_spread": {{
+      "description": "Smooth the edge of quarter-circle spreads.",
+      "type": "boolean",
+      "default": false
+    }},
+    "extended_range": {{
+      "description": "Let colors brighten past white while filling.",
+      "type": "boolean",
+      "default": false
+    }},
+    "start_corner": {{
+      "description": "The corner that generation starts from.",
+      "enum": ["TopLeft", "TopRight", "BottomLeft", "BottomRight"],
+      "default": "TopLeft"
+    }},
+    "direction": {{
+      "description": "Whether generation fills rows or columns first.",
+      "enum": ["Horizontal", "Vertical"],
+      "default": "Horizontal"
+    }},
+    "rotation": {{
+      "description": "Rotates the finished image counterclockwise.",
+      "type": "object",
+      "properties": {{
+        "degrees": {{ "type": "number", "default": 0 }},
+        "fit": {{ "enum": ["Crop", "Expand"], "default": "Crop" }}
+      }},
+      "additionalProperties": false
+    }},
+    "repeat": {{
+      "description": "Repeats the finished image side by side.",
+      "type": "object",
+      "properties": {{
+        "columns": {{ "type": "integer", "minimum": 1, "default": 1 }},
+        "rows": {{ "type": "integer", "minimum": 1, "default": 1 }}
+      }},
+      "additionalProperties": false
+    }},
+    "border": {{
+      "description": "Draws a border around the finished image.",
+      "type": "object",
+      "properties": {{
+        "width": {{ "type": "integer", "minimum": 0, "default": 0 }},
+        "color": {{ "$ref": "#/$defs/color" }},
+        "inner_color": {{
+          "description": "Fades the border to this color at its inner edge.",
+          "oneOf": [{{ "$ref": "#/$defs/color" }}, {{ "type": "null" }}]
+        }},
+        "fit": {{ "enum": ["Crop", "Expand"], "default": "Crop" }}
+      }},
+      "additionalProperties": false
+    }},
+    "duotone": {{
+      "description": "Maps brightness onto two or three inks.",
+      "oneOf": [
+        {{
+          "type": "object",
+          "properties": {{
+            "shadows": {{ "$ref": "#/$defs/color" }},
+            "midtones": {{
+              "description": "Makes a tritone.",
+              "oneOf": [{{ "$ref": "#/$defs/color" }}, {{ "type": "null" }}]
+            }},
+            "highlights": {{ "$ref": "#/$defs/color" }}
+          }},
+          "required": ["shadows", "highlights"],
+          "additionalProperties": false
+        }},
+        {{ "type": "null" }}
+      ]
+    }},
+    "threads": {{
+      "description": "The maximum number of threads to use.",
+      "type": "integer",
+      "minimum": 1
+    }}
+  }},
+  "additionalProperties": false,
+  "$defs": {{
+    "color": {{
+      "oneOf": [
+        {{ "type": "string" }},
+        {{
+          "type": "object",
+          "properties": {{
+            "red": {{ "$ref": "#/$defs/component" }},
+            "green": {{ "$ref": "#/$defs/component" }},
+            "blue": {{ "$ref": "#/$defs/component" }}
+          }},
+          "required": ["red", "green", "blue"],
+          "additionalProperties": false
+        }},
+        {{
+          "type": "array",
+          "prefixItems": [
+            {{ "$ref": "#/$defs/component" }},
+            {{ "$ref": "#/$defs/component" }},
+            {{ "$ref": "#/$defs/component" }}
+          ],
+          "items": false,
+          "minItems": 3
+        }}
+      ]
+    }},
+    "component": {{ "type": "number", "minimum": 0, "maximum": 1 }}
+  }}
+}}
+"##,
+            dim_pattern = concat!(
+                "^([0-9]+[xX][0-9]+",
+                "|(720|1080|1440|2160|4320)[pP]|[48][kK])$",
+            ),
+            version = Self::VERSION,
+            width = dim.width,
+            height = dim.height,
+        )
+    }
+}
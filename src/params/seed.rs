@@ -17,23 +17,94 @@
  * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
  */
 
+//! Seeds are serialized as 64-character hex strings. Byte sequences and the
+//! base64 strings written by older versions are also accepted.
+
 use super::Seed;
+use alloc::string::String;
 use core::fmt;
-use serde::de::{Error, Visitor};
+use serde::de::{Error, SeqAccess, Unexpected, Visitor};
 use serde::{Deserializer, Serializer};
 
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Encodes a seed as 64 lowercase hex digits.
+fn encode_hex(seed: &Seed) -> [u8; 64] {
+    let mut hex = [0; 64];
+    for (byte, digits) in seed.iter().zip(hex.chunks_exact_mut(2)) {
+        digits[0] = HEX_DIGITS[usize::from(byte >> 4)];
+        digits[1] = HEX_DIGITS[usize::from(byte & 0xf)];
+    }
+    hex
+}
+
+/// Formats a seed as 64 lowercase hex digits, as it's written in params
+/// files.
+pub fn seed_to_hex(seed: &Seed) -> String {
+    // The digits are all ASCII.
+    encode_hex(seed).into_iter().map(char::from).collect()
+}
+
+/// Parses a seed written as exactly 64 hex digits, as in params files.
+/// Anything else, including signs and whitespace, is rejected.
+pub fn seed_from_hex(hex: &str) -> Option<Seed> {
+    let digit = |c: u8| (c as char).to_digit(16).map(|d| d as u8);
+    let mut seed = Seed::default();
+    if hex.len() != seed.len() * 2 {
+        return None;
+    }
+    for (byte, pair) in seed.iter_mut().zip(hex.as_bytes().chunks_exact(2))
+    {
+        *byte = (digit(pair[0])? << 4) | digit(pair[1])?;
+    }
+    Some(seed)
+}
+
 pub fn serialize<S>(seed: &Seed, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    serializer.serialize_bytes(seed)
+    let hex = encode_hex(seed);
+    // `hex` contains only ASCII characters.
+    serializer.serialize_str(core::str::from_utf8(&hex).unwrap())
 }
 
 pub fn deserialize<'de, D>(deserializer: D) -> Result<Seed, D::Error>
 where
     D: Deserializer<'de>,
 {
-    deserializer.deserialize_bytes(SeedVisitor)
+    deserializer.deserialize_any(SeedVisitor)
+}
+
+/// Decodes a padded base64 string using the standard alphabet.
+fn decode_base64(s: &str) -> Option<Seed> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let mut seed = Seed::default();
+    // 32 bytes encode to 43 characters, plus one padding character.
+    let s = s.strip_suffix('=')?;
+    if s.len() != (seed.len() * 4).div_ceil(3) {
+        return None;
+    }
+    let mut bits = 0_u32;
+    let mut len = 0;
+    let mut bytes = seed.iter_mut();
+    for c in s.bytes() {
+        bits = (bits << 6) | u32::from(sextet(c)?);
+        len += 6;
+        if len >= 8 {
+            len -= 8;
+            *bytes.next()? = (bits >> len) as u8;
+        }
+    }
+    // Leftover bits must be zero for the encoding to be canonical.
+    (bits & ((1 << len) - 1) == 0).then_some(seed)
 }
 
 struct SeedVisitor;
@@ -42,7 +113,17 @@ impl<'de> Visitor<'de> for SeedVisitor {
     type Value = Seed;
 
     fn expecting(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(fmt, "a sequence of {} bytes", Seed::default().len())
+        let len = Seed::default().len();
+        write!(fmt, "a {}-character hex string or {len} bytes", len * 2)
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        seed_from_hex(s)
+            .or_else(|| decode_base64(s))
+            .ok_or_else(|| E::invalid_value(Unexpected::Str(s), &self))
     }
 
     fn visit_bytes<E>(self, bytes: &[u8]) -> Result<Self::Value, E>
@@ -56,4 +137,20 @@ impl<'de> Visitor<'de> for SeedVisitor {
         seed.copy_from_slice(bytes);
         Ok(seed)
     }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut seed = Seed::default();
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(A::Error::invalid_length(seed.len() + 1, &self));
+        }
+        Ok(seed)
+    }
 }
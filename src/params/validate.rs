@@ -0,0 +1,216 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::super::math;
+use super::super::Position;
+use super::{Color, Duotone, Expr, Float, Params, Spread};
+use core::fmt::{self, Display};
+
+/// An error returned by [`Params::validate`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamsError {
+    /// The image width or height is zero.
+    ZeroDimensions,
+    /// The image is too large to be stored as a bitmap.
+    TooLarge,
+    /// The spread doesn't include any neighboring pixels.
+    EmptySpread,
+    /// The spread is too large to be represented.
+    SpreadTooLarge,
+    /// The given field is NaN or infinite.
+    NotFinite(&'static str),
+    /// The given field is negative, but must not be.
+    Negative(&'static str),
+    /// The given field is zero or negative, but must be positive.
+    NotPositive(&'static str),
+    /// A component of the start color is not between 0 and 1.
+    StartColorOutOfRange,
+    /// A component of a border color is not between 0 and 1.
+    BorderColorOutOfRange,
+    /// A component of a duotone color is not between 0 and 1.
+    DuotoneColorOutOfRange,
+    /// The distance power is so large that the weights of distant pixels
+    /// overflow.
+    WeightOverflow,
+    /// The given field wasn't specified, but would otherwise be random.
+    /// Returned by [`ParamsBuilder::build_strict`](super::ParamsBuilder).
+    Unspecified(&'static str),
+}
+
+impl Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroDimensions => {
+                write!(f, "image dimensions must be nonzero")
+            }
+            Self::TooLarge => write!(f, "image is too large"),
+            Self::EmptySpread => {
+                write!(f, "spread must include at least one other pixel")
+            }
+            Self::SpreadTooLarge => write!(f, "spread is too large"),
+            Self::NotFinite(field) => write!(f, "`{field}` must be finite"),
+            Self::Negative(field) => {
+                write!(f, "`{field}` must not be negative")
+            }
+            Self::NotPositive(field) => {
+                write!(f, "`{field}` must be positive")
+            }
+            Self::StartColorOutOfRange => {
+                write!(f, "`start_color` components must be between 0 and 1")
+            }
+            Self::BorderColorOutOfRange => {
+                write!(f, "`border` color components must be between 0 and 1")
+            }
+            Self::DuotoneColorOutOfRange => write!(
+                f,
+                "`duotone` color components must be between 0 and 1",
+            ),
+            Self::WeightOverflow => {
+                write!(f, "`distance_power` is too large for this spread")
+            }
+            Self::Unspecified(field) => {
+                write!(f, "`{field}` must be specified in strict mode")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParamsError {}
+
+/// Checks the ranges of the fields that can be given as expressions.
+fn check_ranges(
+    random_power: Float,
+    random_max: Float,
+    gamma: Float,
+) -> Result<(), ParamsError> {
+    if random_max < 0.0 {
+        return Err(ParamsError::Negative("random_max"));
+    }
+    if random_power <= 0.0 {
+        return Err(ParamsError::NotPositive("random_power"));
+    }
+    if gamma <= 0.0 {
+        return Err(ParamsError::NotPositive("gamma"));
+    }
+    Ok(())
+}
+
+impl Params {
+    /// Checks that the params will produce a valid image.
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        let dim = self.dimensions;
+        if dim.width == 0 || dim.height == 0 {
+            return Err(ParamsError::ZeroDimensions);
+        }
+        if !self.rotation.degrees.is_finite() {
+            return Err(ParamsError::NotFinite("rotation"));
+        }
+        // The bitmap file size, including padding, must fit in a `u32`.
+        let output = self.output_dimensions();
+        (output.width.checked_mul(3))
+            .map(|n| n.div_ceil(4) * 4)
+            .and_then(|row| row.checked_mul(output.height))
+            .and_then(|n| n.checked_add(14 + 40))
+            .filter(|&n| u32::try_from(n).is_ok())
+            .ok_or(ParamsError::TooLarge)?;
+
+        let size = match self.spread {
+            Spread::Square {
+                width,
+            } => width,
+            Spread::QuarterCircle {
+                radius,
+            } => radius,
+        };
+        if size == 0 {
+            return Err(ParamsError::EmptySpread);
+        }
+        let bounds = (size.checked_add(1))
+            .and_then(|n| n.checked_mul(n))
+            .ok_or(ParamsError::SpreadTooLarge)?;
+
+        let fields = [
+            ("distance_power", self.distance_power),
+            ("count_power", self.count_power),
+            ("random_power", self.random_power),
+            ("random_max", self.random_max),
+            ("gamma", self.gamma),
+        ];
+        for (field, value) in fields {
+            if !value.is_finite() {
+                return Err(ParamsError::NotFinite(field));
+            }
+        }
+        check_ranges(self.random_power, self.random_max, self.gamma)?;
+
+        // Expressions are checked at the corners and center of the image.
+        // Elsewhere, their values are clamped to the valid ranges.
+        let (w, h) = (dim.width - 1, dim.height - 1);
+        let points = [(0, 0), (w, 0), (0, h), (w, h), (w / 2, h / 2)];
+        let exprs = &self.expressions;
+        for (x, y) in points.into_iter().filter(|_| !exprs.is_empty()) {
+            let pos = Position::new(x, y);
+            let eval = |field, expr: &Option<Expr>, n: Float| {
+                let n = expr.as_ref().map_or(n, |e| e.eval(pos, dim));
+                if n.is_finite() {
+                    Ok(n)
+                } else {
+                    Err(ParamsError::NotFinite(field))
+                }
+            };
+            check_ranges(
+                eval("random_power", &exprs.random_power, self.random_power)?,
+                eval("random_max", &exprs.random_max, self.random_max)?,
+                eval("gamma", &exprs.gamma, self.gamma)?,
+            )?;
+        }
+
+        let in_range = |color: Color| {
+            [color.red, color.green, color.blue]
+                .iter()
+                .all(|n| (0.0..=1.0).contains(n))
+        };
+        if !in_range(self.start_color) {
+            return Err(ParamsError::StartColorOutOfRange);
+        }
+        let border = self.border;
+        if !border.inner_color.into_iter().chain([border.color]).all(in_range)
+        {
+            return Err(ParamsError::BorderColorOutOfRange);
+        }
+        if !self.duotone.into_iter().flat_map(Duotone::colors).all(in_range) {
+            return Err(ParamsError::DuotoneColorOutOfRange);
+        }
+
+        // Weights aren't used with uniform averaging.
+        if self.uniform_averaging {
+            return Ok(());
+        }
+        // The farthest pixel has the largest weight when `distance_power` is
+        // positive. The sum of all weights must be finite.
+        let max_dist = (size as Float) * math::sqrt(2.0);
+        let max_weight = math::powf(max_dist, self.distance_power.max(0.0));
+        if !(max_weight * bounds as Float).is_finite() {
+            return Err(ParamsError::WeightOverflow);
+        }
+        Ok(())
+    }
+}
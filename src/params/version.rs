@@ -0,0 +1,83 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The version of the params format. Params written before versioning was
+//! added have no version and are treated as version 0. Older params are
+//! migrated as they're deserialized:
+//!
+//! * Version 0 wrote seeds as base64 strings and start colors as their
+//!   components. Both are still accepted by the current format, so no
+//!   changes are needed.
+
+use super::Params;
+use core::fmt;
+use serde::de::{Error, Unexpected, Visitor};
+use serde::{Deserializer, Serializer};
+
+impl Params {
+    /// The current version of the params format.
+    pub const VERSION: u32 = 1;
+}
+
+/// The version of params without a `version` field.
+pub fn legacy() -> u32 {
+    0
+}
+
+/// Always writes the current version, as params are serialized in the
+/// current format regardless of where they came from.
+pub fn serialize<S>(_: &u32, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u32(Params::VERSION)
+}
+
+/// Reads the version, failing if the params are from a newer version of
+/// Plumage.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_u32(VersionVisitor)
+}
+
+struct VersionVisitor;
+
+impl Visitor<'_> for VersionVisitor {
+    type Value = u32;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a params version no greater than {}", Params::VERSION)
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<u32, E> {
+        match u32::try_from(v) {
+            Ok(version) if version <= Params::VERSION => Ok(version),
+            _ => Err(E::invalid_value(Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<u32, E> {
+        match u64::try_from(v) {
+            Ok(v) => self.visit_u64(v),
+            Err(_) => Err(E::invalid_value(Unexpected::Signed(v), &self)),
+        }
+    }
+}
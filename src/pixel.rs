@@ -0,0 +1,385 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{math, Color, Float};
+use core::array;
+use core::fmt::Debug;
+
+/// The maximum number of channels in a [`Pixel`].
+pub const MAX_CHANNELS: usize = 4;
+
+/// A type of pixel that can be stored in a [`Pixmap`](crate::Pixmap) and
+/// generated by a [`Generator`](crate::Generator).
+///
+/// A pixel consists of up to [`MAX_CHANNELS`] channels, each between 0 and
+/// 1. The generation algorithm treats every channel the same way.
+pub trait Pixel: Copy + Debug + Send + Sync + 'static {
+    /// The number of channels, between 1 and [`MAX_CHANNELS`].
+    const CHANNELS: usize;
+
+    /// A pixel whose channels are all 0.
+    const ZERO: Self;
+
+    /// How the pixel is stored with
+    /// [`Storage::Compact`](crate::Storage::Compact).
+    type Compact: Copy + Send + Sync + 'static;
+
+    /// The channels of the pixel. Channels past [`Self::CHANNELS`] are 0.
+    fn to_channels(self) -> [Float; MAX_CHANNELS];
+
+    /// Creates a pixel from its channels. Channels past [`Self::CHANNELS`]
+    /// are ignored.
+    fn from_channels(channels: [Float; MAX_CHANNELS]) -> Self;
+
+    /// Converts the pixel to its compact form.
+    fn to_compact(self) -> Self::Compact;
+
+    /// Converts the pixel from its compact form.
+    fn from_compact(compact: Self::Compact) -> Self;
+
+    /// Converts the pixel to a color, for output.
+    fn to_color(self) -> Color;
+
+    /// Converts a color, like [`Params::start_color`](crate::Params), to a
+    /// pixel.
+    fn from_color(color: Color) -> Self;
+}
+
+/// Calls a function on each channel of a pixel.
+pub(crate) fn map<P, F>(pixel: P, mut f: F) -> P
+where
+    P: Pixel,
+    F: FnMut(Float) -> Float,
+{
+    let mut channels = pixel.to_channels();
+    for n in &mut channels[..P::CHANNELS] {
+        *n = f(*n);
+    }
+    P::from_channels(channels)
+}
+
+/// Linearly interpolates between two pixels, as with [`Color::lerp`].
+pub(crate) fn lerp<P: Pixel>(a: P, b: P, t: Float) -> P {
+    let (a, b) = (a.to_channels(), b.to_channels());
+    P::from_channels(array::from_fn(|i| a[i] + (b[i] - a[i]) * t))
+}
+
+impl Pixel for Color {
+    const CHANNELS: usize = 3;
+    const ZERO: Self = Self::BLACK;
+    type Compact = [u8; 3];
+
+    fn to_channels(self) -> [Float; MAX_CHANNELS] {
+        [self.red, self.green, self.blue, 0.0]
+    }
+
+    fn from_channels([red, green, blue, _]: [Float; MAX_CHANNELS]) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+        }
+    }
+
+    fn to_compact(self) -> [u8; 3] {
+        self.to_rgb8()
+    }
+
+    fn from_compact(compact: [u8; 3]) -> Self {
+        Self::from_rgb8(compact)
+    }
+
+    fn to_color(self) -> Color {
+        self
+    }
+
+    fn from_color(color: Color) -> Self {
+        color
+    }
+}
+
+/// A grayscale pixel. The value is between 0 (black) and 1 (white).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(transparent)]
+pub struct Gray(pub Float);
+
+// SAFETY: `Gray` is `repr(transparent)` around a `Float`, which is `Pod`.
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Zeroable for Gray {}
+
+// SAFETY: See above.
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Pod for Gray {}
+
+impl Pixel for Gray {
+    const CHANNELS: usize = 1;
+    const ZERO: Self = Self(0.0);
+    type Compact = u8;
+
+    fn to_channels(self) -> [Float; MAX_CHANNELS] {
+        [self.0, 0.0, 0.0, 0.0]
+    }
+
+    fn from_channels([value, ..]: [Float; MAX_CHANNELS]) -> Self {
+        Self(value)
+    }
+
+    fn to_compact(self) -> u8 {
+        Color::from(self).to_rgb8()[0]
+    }
+
+    fn from_compact(compact: u8) -> Self {
+        Self(Float::from(compact) / 255.0)
+    }
+
+    fn to_color(self) -> Color {
+        self.into()
+    }
+
+    /// Uses the color's [luminance](Color::luminance).
+    fn from_color(color: Color) -> Self {
+        Self(color.luminance())
+    }
+}
+
+impl From<Gray> for Color {
+    fn from(gray: Gray) -> Self {
+        Self {
+            red: gray.0,
+            green: gray.0,
+            blue: gray.0,
+        }
+    }
+}
+
+/// A color with an alpha channel, which is generated like the other
+/// channels, so the opacity varies smoothly across the image. All channels
+/// are between 0 and 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct Rgba {
+    pub red: Float,
+    pub green: Float,
+    pub blue: Float,
+    pub alpha: Float,
+}
+
+// SAFETY: `Rgba` is `repr(C)` and consists of four `Float`s, which are
+// `Pod`, with no padding.
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Zeroable for Rgba {}
+
+// SAFETY: See above.
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Pod for Rgba {}
+
+impl Pixel for Rgba {
+    const CHANNELS: usize = 4;
+    const ZERO: Self = Self {
+        red: 0.0,
+        green: 0.0,
+        blue: 0.0,
+        alpha: 0.0,
+    };
+    type Compact = [u8; 4];
+
+    fn to_channels(self) -> [Float; MAX_CHANNELS] {
+        [self.red, self.green, self.blue, self.alpha]
+    }
+
+    fn from_channels(
+        [red, green, blue, alpha]: [Float; MAX_CHANNELS],
+    ) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+
+    fn to_compact(self) -> [u8; 4] {
+        let [red, green, blue] = self.to_color().to_rgb8();
+        [red, green, blue, Gray(self.alpha).to_compact()]
+    }
+
+    fn from_compact([red, green, blue, alpha]: [u8; 4]) -> Self {
+        let color = Color::from_rgb8([red, green, blue]);
+        Self {
+            alpha: Gray::from_compact(alpha).0,
+            ..Self::from_color(color)
+        }
+    }
+
+    /// Drops the alpha channel, as images are written without one. Use
+    /// [`Generator::pixmap`](crate::Generator::pixmap) to read it.
+    fn to_color(self) -> Color {
+        Color {
+            red: self.red,
+            green: self.green,
+            blue: self.blue,
+        }
+    }
+
+    /// Gives the color an alpha of 1 (opaque).
+    fn from_color(color: Color) -> Self {
+        Self {
+            red: color.red,
+            green: color.green,
+            blue: color.blue,
+            alpha: 1.0,
+        }
+    }
+}
+
+/// A color in the CIELAB color space with a D65 white point, so that colors
+/// are blended perceptually. It's converted to sRGB when the image is
+/// written.
+///
+/// The channels are scaled to be between 0 and 1: `l` is L\* / 100, and `a`
+/// and `b` are a\* and b\* / 256, plus 0.5. Like every channel, they're all
+/// affected by gamma correction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct Lab {
+    pub l: Float,
+    pub a: Float,
+    pub b: Float,
+}
+
+// SAFETY: `Lab` is `repr(C)` and consists of three `Float`s, which are
+// `Pod`, with no padding.
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Zeroable for Lab {}
+
+// SAFETY: See above.
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Pod for Lab {}
+
+/// The D65 white point in CIE XYZ.
+const WHITE: [Float; 3] = [0.950_47, 1.0, 1.088_83];
+
+/// The threshold of the linear segment of the CIELAB transfer function.
+const DELTA: Float = 6.0 / 29.0;
+
+/// Converts an sRGB component to linear light.
+fn srgb_to_linear(n: Float) -> Float {
+    if n <= 0.040_45 {
+        n / 12.92
+    } else {
+        math::powf((n + 0.055) / 1.055, 2.4)
+    }
+}
+
+/// Converts a linear-light component to sRGB.
+fn linear_to_srgb(n: Float) -> Float {
+    if n <= 0.003_130_8 {
+        n * 12.92
+    } else {
+        1.055 * math::powf(n, 1.0 / 2.4) - 0.055
+    }
+}
+
+/// The CIELAB transfer function.
+fn lab_f(t: Float) -> Float {
+    if t > DELTA * DELTA * DELTA {
+        math::powf(t, 1.0 / 3.0)
+    } else {
+        t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+    }
+}
+
+/// The inverse of [`lab_f`].
+fn lab_f_inv(t: Float) -> Float {
+    if t > DELTA {
+        t * t * t
+    } else {
+        3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+    }
+}
+
+impl Pixel for Lab {
+    const CHANNELS: usize = 3;
+    const ZERO: Self = Self {
+        l: 0.0,
+        a: 0.0,
+        b: 0.0,
+    };
+    type Compact = [u8; 3];
+
+    fn to_channels(self) -> [Float; MAX_CHANNELS] {
+        [self.l, self.a, self.b, 0.0]
+    }
+
+    fn from_channels([l, a, b, _]: [Float; MAX_CHANNELS]) -> Self {
+        Self {
+            l,
+            a,
+            b,
+        }
+    }
+
+    /// Stores the scaled channels directly, without converting to sRGB.
+    fn to_compact(self) -> [u8; 3] {
+        [self.l, self.a, self.b].map(|n| Gray(n).to_compact())
+    }
+
+    fn from_compact([l, a, b]: [u8; 3]) -> Self {
+        let [l, a, b] = [l, a, b].map(|n| Gray::from_compact(n).0);
+        Self {
+            l,
+            a,
+            b,
+        }
+    }
+
+    /// Converts the color to sRGB. Colors outside the sRGB gamut are
+    /// clamped when written.
+    fn to_color(self) -> Color {
+        let fy = (self.l * 100.0 + 16.0) / 116.0;
+        let fx = fy + (self.a - 0.5) * 256.0 / 500.0;
+        let fz = fy - (self.b - 0.5) * 256.0 / 200.0;
+        let [x, y, z] = [fx, fy, fz].map(lab_f_inv);
+        let [x, y, z] = [x * WHITE[0], y * WHITE[1], z * WHITE[2]];
+        let red = 3.240_454 * x - 1.537_139 * y - 0.498_531 * z;
+        let green = -0.969_266 * x + 1.876_011 * y + 0.041_556 * z;
+        let blue = 0.055_643 * x - 0.204_026 * y + 1.057_225 * z;
+        Color {
+            red: linear_to_srgb(red),
+            green: linear_to_srgb(green),
+            blue: linear_to_srgb(blue),
+        }
+    }
+
+    /// Converts the color from sRGB.
+    fn from_color(color: Color) -> Self {
+        let [red, green, blue] =
+            [color.red, color.green, color.blue].map(srgb_to_linear);
+        let x = 0.412_456 * red + 0.357_576 * green + 0.180_438 * blue;
+        let y = 0.212_673 * red + 0.715_152 * green + 0.072_175 * blue;
+        let z = 0.019_334 * red + 0.119_192 * green + 0.950_304 * blue;
+        let [fx, fy, fz] =
+            [x / WHITE[0], y / WHITE[1], z / WHITE[2]].map(lab_f);
+        Self {
+            l: (116.0 * fy - 16.0) / 100.0,
+            a: 500.0 * (fx - fy) / 256.0 + 0.5,
+            b: 200.0 * (fy - fz) / 256.0 + 0.5,
+        }
+    }
+}
@@ -17,48 +17,392 @@
  * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use super::{Color, Dimensions, Float, Position};
+use super::pixel::{self, Pixel};
+use super::{Border, Color, Dimensions, Fit, Float, Position, Repeat};
+use super::Rotation;
 use alloc::boxed::Box;
+use alloc::vec;
 use alloc::vec::Vec;
-use core::ops::{Index, IndexMut};
+use core::array;
+use core::marker::PhantomData;
+use core::slice::{ChunksExact, ChunksExactMut};
+#[cfg(feature = "rayon")]
+use rayon::slice::{
+    ChunksExact as ParChunksExact, ChunksExactMut as ParChunksExactMut,
+    ParallelSlice, ParallelSliceMut,
+};
 
-/// A two-dimensional array of pixels.
-pub struct Pixmap {
+#[cfg(feature = "image")]
+mod image;
+#[cfg(feature = "ndarray")]
+mod ndarray;
+
+/// How a [`Pixmap`] stores its pixels.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Storage {
+    /// Each pixel is stored as-is, with each channel as a [`Float`].
+    #[default]
+    Float,
+    /// Each pixel is stored in its [compact form](Pixel::Compact), usually
+    /// with each channel as a single byte, and converted to and from a
+    /// [`Float`] when accessed. This uses a fraction of the memory
+    /// of [`Storage::Float`], but the loss of precision affects the output.
+    Compact,
+}
+
+/// How [`Pixmap::blit_with`] combines each source pixel with the pixel it's
+/// drawn over.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BlendMode {
+    /// Replace the destination pixel.
+    #[default]
+    Replace,
+    /// Interpolate between the destination and source pixels, where 0 keeps
+    /// the destination and 1 replaces it.
+    Mix(Float),
+    /// Add the channels, clamping each to at most 1.
+    Add,
+    /// Multiply the channels.
+    Multiply,
+}
+
+impl BlendMode {
+    /// Combines `src` with `dest`.
+    fn blend<P: Pixel>(self, dest: P, src: P) -> P {
+        let zip = |f: fn(Float, Float) -> Float| {
+            let (dest, src) = (dest.to_channels(), src.to_channels());
+            P::from_channels(array::from_fn(|i| f(dest[i], src[i])))
+        };
+        match self {
+            Self::Replace => src,
+            Self::Mix(alpha) => pixel::lerp(dest, src, alpha),
+            Self::Add => zip(|a, b| (a + b).min(1.0)),
+            Self::Multiply => zip(|a, b| a * b),
+        }
+    }
+}
+
+enum Data<P: Pixel> {
+    Float(Vec<P>),
+    Compact(Vec<P::Compact>),
+}
+
+/// A two-dimensional array of pixels. By default, each pixel is a [`Color`],
+/// but any [`Pixel`] type can be used.
+pub struct Pixmap<P: Pixel = Color> {
     dimensions: Dimensions,
-    data: Vec<Color>,
+    data: Data<P>,
 }
 
 impl Pixmap {
     /// Creates a new [`Pixmap`].
     pub fn new(dimensions: Dimensions) -> Self {
-        let mut data = Vec::new();
-        data.resize(dimensions.count(), Color::BLACK);
+        Self::with_storage(dimensions, Storage::Float)
+    }
+
+    /// Creates a new [`Pixmap`] that stores its pixels as described by
+    /// `storage`.
+    pub fn with_storage(dimensions: Dimensions, storage: Storage) -> Self {
+        Self::new_generic(dimensions, storage)
+    }
+}
+
+impl<P: Pixel> Pixmap<P> {
+    /// Creates a new [`Pixmap`] with any pixel type, storing its pixels as
+    /// described by `storage`. Every pixel is initially [`Pixel::ZERO`].
+    pub fn new_generic(dimensions: Dimensions, storage: Storage) -> Self {
+        let count = dimensions.count();
+        let data = match storage {
+            Storage::Float => Data::Float(vec![P::ZERO; count]),
+            Storage::Compact => {
+                Data::Compact(vec![P::ZERO.to_compact(); count])
+            }
+        };
         Self {
             dimensions,
             data,
         }
     }
 
+    /// Creates a [`Pixmap`] from existing pixel data in row-major order,
+    /// using [`Storage::Float`]. Returns [`None`] if the length of `data`
+    /// doesn't match `dimensions`.
+    pub fn from_vec(dimensions: Dimensions, data: Vec<P>) -> Option<Self> {
+        let count = dimensions.width.checked_mul(dimensions.height)?;
+        if data.len() != count {
+            return None;
+        }
+        Some(Self {
+            dimensions,
+            data: Data::Float(data),
+        })
+    }
+
+    /// Creates a [`Pixmap`] by calling `f` with the position of each pixel,
+    /// in row-major order, using [`Storage::Float`].
+    pub fn from_fn<F>(dimensions: Dimensions, mut f: F) -> Self
+    where
+        F: FnMut(Position) -> P,
+    {
+        let mut data = Vec::with_capacity(dimensions.count());
+        dimensions.for_each(|pos| data.push(f(pos)));
+        Self {
+            dimensions,
+            data: Data::Float(data),
+        }
+    }
+
     /// The dimensions of the image.
     pub fn dimensions(&self) -> Dimensions {
         self.dimensions
     }
 
-    #[allow(dead_code)]
-    /// The raw pixel data as an immutable reference.
-    pub fn data(&self) -> &[Color] {
-        &self.data
+    /// How the pixels are stored.
+    pub fn storage(&self) -> Storage {
+        match &self.data {
+            Data::Float(_) => Storage::Float,
+            Data::Compact(_) => Storage::Compact,
+        }
+    }
+
+    /// The raw pixel data as an immutable reference, or [`None`] if the
+    /// pixmap doesn't use [`Storage::Float`].
+    pub fn data(&self) -> Option<&[P]> {
+        match &self.data {
+            Data::Float(data) => Some(data),
+            Data::Compact(_) => None,
+        }
+    }
+
+    /// The raw pixel data as a mutable reference, or [`None`] if the pixmap
+    /// doesn't use [`Storage::Float`].
+    pub fn data_mut(&mut self) -> Option<&mut [P]> {
+        match &mut self.data {
+            Data::Float(data) => Some(data),
+            Data::Compact(_) => None,
+        }
+    }
+
+    /// Takes ownership of the raw pixel data, converting it to
+    /// [`Storage::Float`] if necessary.
+    pub fn into_data(self) -> Box<[P]> {
+        match self.data {
+            Data::Float(data) => data.into_boxed_slice(),
+            Data::Compact(data) => {
+                data.into_iter().map(P::from_compact).collect()
+            }
+        }
+    }
+
+    /// An iterator over the rows of the image, or [`None`] if the pixmap
+    /// doesn't use [`Storage::Float`].
+    pub fn rows(&self) -> Option<ChunksExact<'_, P>> {
+        let width = self.dimensions.width.max(1);
+        Some(self.data()?.chunks_exact(width))
+    }
+
+    /// A mutable iterator over the rows of the image, or [`None`] if the
+    /// pixmap doesn't use [`Storage::Float`].
+    pub fn rows_mut(&mut self) -> Option<ChunksExactMut<'_, P>> {
+        let width = self.dimensions.width.max(1);
+        Some(self.data_mut()?.chunks_exact_mut(width))
+    }
+
+    #[cfg(feature = "rayon")]
+    /// A parallel iterator over the rows of the image, or [`None`] if the
+    /// pixmap doesn't use [`Storage::Float`].
+    pub fn par_rows(&self) -> Option<ParChunksExact<'_, P>> {
+        let width = self.dimensions.width.max(1);
+        Some(self.data()?.par_chunks_exact(width))
+    }
+
+    #[cfg(feature = "rayon")]
+    /// A parallel mutable iterator over the rows of the image, or [`None`]
+    /// if the pixmap doesn't use [`Storage::Float`].
+    pub fn par_rows_mut(&mut self) -> Option<ParChunksExactMut<'_, P>> {
+        let width = self.dimensions.width.max(1);
+        Some(self.data_mut()?.par_chunks_exact_mut(width))
+    }
+
+    /// An iterator over every pixel in the image and its position, in
+    /// row-major order. Unlike [`Self::rows`], this works with any storage.
+    pub fn enumerate_pixels(
+        &self,
+    ) -> impl Iterator<Item = (Position, P)> + '_ {
+        let dim = self.dimensions;
+        (0..dim.height).flat_map(move |y| {
+            (0..dim.width).map(move |x| {
+                let pos = Position::new(x, y);
+                // SAFETY: `x` and `y` are less than the width and height.
+                (pos, unsafe { self.get_unchecked(pos) })
+            })
+        })
+    }
+
+    /// Calls `f` with the position of each pixel and a mutable reference to
+    /// its value, in row-major order. This works with any storage.
+    pub fn for_each_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Position, &mut P),
+    {
+        let dim = self.dimensions;
+        if let Some(data) = self.data_mut() {
+            let mut pos = Position::ZERO;
+            for pixel in data {
+                f(pos, pixel);
+                pos.x += 1;
+                if pos.x == dim.width {
+                    pos.x = 0;
+                    pos.y += 1;
+                }
+            }
+            return;
+        }
+        dim.for_each(|pos| {
+            // SAFETY: `Dimensions::for_each` yields only valid positions.
+            unsafe {
+                let mut pixel = self.get_unchecked(pos);
+                f(pos, &mut pixel);
+                self.set_unchecked(pos, pixel);
+            }
+        });
+    }
+
+    /// Replaces each pixel with the result of calling `f` with its position
+    /// and value, in row-major order.
+    pub fn map<F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(Position, P) -> P,
+    {
+        self.for_each_mut(|pos, pixel| *pixel = f(pos, *pixel));
+        self
+    }
+
+    /// Draws `src` onto the image with its top-left corner at `dest`,
+    /// replacing the pixels underneath. Parts of `src` that fall outside the
+    /// image are ignored.
+    pub fn blit(&mut self, src: &Pixmap<P>, dest: Position) {
+        self.blit_with(src, dest, BlendMode::Replace);
+    }
+
+    /// Like [`Self::blit`], but combines each pixel of `src` with the pixel
+    /// underneath as described by `mode`.
+    pub fn blit_with(
+        &mut self,
+        src: &Pixmap<P>,
+        dest: Position,
+        mode: BlendMode,
+    ) {
+        let dim = self.dimensions;
+        let visible = Dimensions::new(
+            dim.width.saturating_sub(dest.x),
+            dim.height.saturating_sub(dest.y),
+        )
+        .min(src.dimensions);
+        visible.for_each(|pos| {
+            let dest = dest + pos;
+            // SAFETY: `pos` is within `visible`, which is clipped to both
+            // `src` and the part of this image at or after `dest`.
+            unsafe {
+                let pixel = mode.blend(
+                    self.get_unchecked(dest),
+                    src.get_unchecked(pos),
+                );
+                self.set_unchecked(dest, pixel);
+            }
+        });
+    }
+
+    /// Samples the image at a fractional position using bilinear
+    /// interpolation. Pixel centers lie at integer coordinates, and
+    /// positions outside the image are clamped to the nearest edge.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the image is empty.
+    pub fn sample(&self, x: Float, y: Float) -> P {
+        let dim = self.dimensions;
+        assert!(dim.width > 0 && dim.height > 0, "empty image");
+        // Returns the integer and fractional parts of a clamped coordinate.
+        let split = |n: Float, len: usize| {
+            let n = n.max(0.0).min((len - 1) as Float);
+            let i = n as usize;
+            (i, (i + 1).min(len - 1), n - i as Float)
+        };
+        let (x0, x1, fx) = split(x, dim.width);
+        let (y0, y1, fy) = split(y, dim.height);
+        // SAFETY: All coordinates were clamped to the image bounds.
+        let [c00, c10, c01, c11] = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)]
+            .map(|(x, y)| unsafe { self.get_unchecked(Position::new(x, y)) });
+        pixel::lerp(pixel::lerp(c00, c10, fx), pixel::lerp(c01, c11, fx), fy)
+    }
+
+    /// Rotates the image about its center, sampling with bilinear
+    /// interpolation. The result has the dimensions given by
+    /// [`Rotation::output_dimensions`], and areas outside the rotated image
+    /// are black.
+    pub fn rotate(&self, rotation: Rotation) -> Self {
+        let src = self.dimensions;
+        let dim = rotation.output_dimensions(src);
+        let mut rotated = Self::new_generic(dim, self.storage());
+        if src.count() == 0 {
+            return rotated;
+        }
+        let (sin, cos) = rotation.sin_cos();
+        let center = |dim: Dimensions| {
+            let (w, h) = (dim.width as Float, dim.height as Float);
+            ((w - 1.0) / 2.0, (h - 1.0) / 2.0)
+        };
+        let (src_cx, src_cy) = center(src);
+        let (cx, cy) = center(dim);
+        let (w, h) = (src.width as Float, src.height as Float);
+        rotated.for_each_mut(|pos, pixel| {
+            let dx = pos.x as Float - cx;
+            let dy = pos.y as Float - cy;
+            // The source position is found by rotating back, which is
+            // clockwise on screen, as y points down.
+            let x = src_cx + dx * cos - dy * sin;
+            let y = src_cy + dx * sin + dy * cos;
+            if (-0.5..w - 0.5).contains(&x) && (-0.5..h - 0.5).contains(&y) {
+                *pixel = self.sample(x, y);
+            }
+        });
+        rotated
     }
 
-    /// The raw pixel data as an immutable reference.
-    pub fn data_mut(&mut self) -> &mut [Color] {
-        &mut self.data
+    /// Repeats the image side by side, [`Repeat::columns`] times across and
+    /// [`Repeat::rows`] times down.
+    pub fn repeat(&self, repeat: Repeat) -> Self {
+        let src = self.dimensions;
+        let dim = repeat.output_dimensions(src);
+        let mut repeated = Self::new_generic(dim, self.storage());
+        for row in 0..repeat.rows.get() {
+            for column in 0..repeat.columns.get() {
+                let dest = Position::new(column * src.width, row * src.height);
+                repeated.blit(self, dest);
+            }
+        }
+        repeated
     }
 
-    #[allow(dead_code)]
-    /// Takes ownership of the raw pixel data.
-    pub fn into_data(self) -> Box<[Color]> {
-        self.data.into_boxed_slice()
+    /// Draws `border` around the image, first growing the image to the
+    /// dimensions given by [`Border::output_dimensions`].
+    pub fn add_border(&mut self, border: Border) {
+        if border.fit == Fit::Expand {
+            let dim = border.output_dimensions(self.dimensions);
+            let mut image = Self::new_generic(dim, self.storage());
+            image.blit(self, Position::new(border.width, border.width));
+            *self = image;
+        }
+        let dim = self.dimensions;
+        self.for_each_mut(|pos, pixel| {
+            // The distance to the nearest edge.
+            let dist = (pos.x.min(dim.width - 1 - pos.x))
+                .min(pos.y.min(dim.height - 1 - pos.y));
+            if dist < border.width {
+                *pixel = P::from_color(border.color_at(dist));
+            }
+        });
     }
 
     /// Calculates the index into the internal array for the given position.
@@ -66,73 +410,163 @@ impl Pixmap {
         pos.y * self.dimensions.width + pos.x
     }
 
-    /// Gets the pixel at `pos` without bounds checking.
+    /// Whether `pos` is inside the image.
+    fn contains(&self, pos: Position) -> bool {
+        pos.x < self.dimensions.width && pos.y < self.dimensions.height
+    }
+
+    /// Gets the pixel at `pos`, or [`None`] if `pos` is outside the image.
+    pub fn get(&self, pos: Position) -> Option<P> {
+        // SAFETY: We just checked that `pos` is valid.
+        self.contains(pos).then(|| unsafe { self.get_unchecked(pos) })
+    }
+
+    /// Gets a mutable reference to the pixel at `pos`, or [`None`] if `pos`
+    /// is outside the image or the pixmap doesn't use [`Storage::Float`].
+    pub fn get_mut(&mut self, pos: Position) -> Option<&mut P> {
+        if !self.contains(pos) {
+            return None;
+        }
+        let index = self.pos_index(pos);
+        self.data_mut()?.get_mut(index)
+    }
+
+    /// Sets the pixel at `pos`.
     ///
-    /// # Safety
+    /// # Panics
     ///
-    /// `pos.x` and `pos.y` must be less than the image width and height,
-    /// respectively.
-    pub unsafe fn get_unchecked(&self, pos: Position) -> Color {
-        // SAFETY: Checked by caller.
-        unsafe { *self.data.get_unchecked(self.pos_index(pos)) }
+    /// This method panics if `pos` is outside the image.
+    pub fn set(&mut self, pos: Position, pixel: P) {
+        assert!(self.contains(pos), "bad position");
+        // SAFETY: We just checked that `pos` is valid.
+        unsafe {
+            self.set_unchecked(pos, pixel);
+        }
     }
 
-    /// Mutably gets the pixel at `pos` without bounds checking.
+    /// Gets the pixel at `pos` without bounds checking.
     ///
     /// # Safety
     ///
     /// `pos.x` and `pos.y` must be less than the image width and height,
     /// respectively.
-    pub unsafe fn get_unchecked_mut(&mut self, pos: Position) -> &mut Color {
+    pub unsafe fn get_unchecked(&self, pos: Position) -> P {
         let index = self.pos_index(pos);
-        // SAFETY: Checked by caller.
-        unsafe { self.data.get_unchecked_mut(index) }
+        match &self.data {
+            // SAFETY: Checked by caller.
+            Data::Float(data) => unsafe { *data.get_unchecked(index) },
+            Data::Compact(data) => {
+                // SAFETY: Checked by caller.
+                P::from_compact(unsafe { *data.get_unchecked(index) })
+            }
+        }
     }
 
-    /// Converts the pixmap to a BMP-style BGR pixel array.
+    /// Sets the pixel at `pos` without bounds checking.
     ///
     /// # Safety
     ///
-    /// All color components in the image must be between 0 and 1.
-    pub unsafe fn to_bgr_unchecked(&self) -> Vec<u8> {
-        let row_size = (self.dimensions.width * 3 + 3) / 4 * 4;
-        let padding_len = row_size - (self.dimensions.width * 3);
-        let padding_arr = [0_u8; 4];
-        let padding = &padding_arr[..padding_len];
-
-        let total = row_size * self.dimensions.height;
-        let mut bgr = Vec::with_capacity(total);
-        let mut i = 0;
-        for color in &self.data {
-            i += 1;
-            if i == self.dimensions.width {
-                bgr.extend_from_slice(padding);
-                i = 0;
+    /// `pos.x` and `pos.y` must be less than the image width and height,
+    /// respectively.
+    pub unsafe fn set_unchecked(&mut self, pos: Position, pixel: P) {
+        let index = self.pos_index(pos);
+        match &mut self.data {
+            Data::Float(data) => {
+                // SAFETY: Checked by caller.
+                *unsafe { data.get_unchecked_mut(index) } = pixel;
             }
-
-            let conv = |n: Float| {
+            Data::Compact(data) => {
                 // SAFETY: Checked by caller.
-                unsafe { (n * 255.0).round().to_int_unchecked() }
-            };
-            bgr.push(conv(color.blue));
-            bgr.push(conv(color.green));
-            bgr.push(conv(color.red));
+                *unsafe { data.get_unchecked_mut(index) } = pixel.to_compact();
+            }
+        }
+    }
+
+    /// Gets a [`RawPixmap`] that allows pixels to be accessed from multiple
+    /// threads at once.
+    pub(crate) fn raw(&mut self) -> RawPixmap<'_, P> {
+        let data = match &mut self.data {
+            Data::Float(data) => RawData::Float(data.as_mut_ptr()),
+            Data::Compact(data) => RawData::Compact(data.as_mut_ptr()),
+        };
+        RawPixmap {
+            width: self.dimensions.width,
+            data,
+            phantom: PhantomData,
         }
-        bgr
     }
 }
 
-impl Index<Position> for Pixmap {
-    type Output = Color;
+enum RawData<P: Pixel> {
+    Float(*mut P),
+    Compact(*mut P::Compact),
+}
 
-    fn index(&self, pos: Position) -> &Self::Output {
-        &self.data[self.pos_index(pos)]
+impl<P: Pixel> Clone for RawData<P> {
+    fn clone(&self) -> Self {
+        *self
     }
 }
 
-impl IndexMut<Position> for Pixmap {
-    fn index_mut(&mut self, pos: Position) -> &mut Self::Output {
-        let index = self.pos_index(pos);
-        &mut self.data[index]
+impl<P: Pixel> Copy for RawData<P> {}
+
+/// A mutable view of a [`Pixmap`]'s pixels that can be shared between
+/// threads. Unlike with [`Pixmap`], pixels can be set through a shared
+/// reference, so the caller is responsible for preventing data races.
+pub struct RawPixmap<'a, P: Pixel> {
+    width: usize,
+    data: RawData<P>,
+    phantom: PhantomData<&'a mut Pixmap<P>>,
+}
+
+// SAFETY: `RawPixmap` is a mutable borrow of a `Pixmap`, which is `Send`.
+unsafe impl<P: Pixel> Send for RawPixmap<'_, P> {}
+
+// SAFETY: All methods that access pixels through a shared reference are
+// unsafe and require the caller to prevent data races.
+unsafe impl<P: Pixel> Sync for RawPixmap<'_, P> {}
+
+impl<P: Pixel> RawPixmap<'_, P> {
+    /// The width of the image.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Gets the pixel at `pos` without bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// `pos.x` and `pos.y` must be less than the image width and height,
+    /// respectively, and no other thread may be setting the pixel at the
+    /// same time.
+    pub unsafe fn get_unchecked(&self, pos: Position) -> P {
+        let index = pos.y * self.width + pos.x;
+        match self.data {
+            // SAFETY: Checked by caller.
+            RawData::Float(data) => unsafe { *data.add(index) },
+            RawData::Compact(data) => {
+                // SAFETY: Checked by caller.
+                P::from_compact(unsafe { *data.add(index) })
+            }
+        }
+    }
+
+    /// Sets the pixel at `pos` without bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// `pos.x` and `pos.y` must be less than the image width and height,
+    /// respectively, and no other thread may be accessing the pixel at the
+    /// same time.
+    pub unsafe fn set_unchecked(&self, pos: Position, pixel: P) {
+        let index = pos.y * self.width + pos.x;
+        match self.data {
+            // SAFETY: Checked by caller.
+            RawData::Float(data) => unsafe { *data.add(index) = pixel },
+            // SAFETY: Checked by caller.
+            RawData::Compact(data) => unsafe {
+                *data.add(index) = pixel.to_compact();
+            },
+        }
     }
 }
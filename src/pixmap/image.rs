@@ -0,0 +1,115 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Conversions between [`Pixmap`] and the [`image`] crate's types.
+
+use super::{Pixel, Pixmap};
+use crate::{Color, Dimensions, Float};
+use image::{DynamicImage, Rgb, Rgb32FImage, RgbImage};
+
+/// Converts image dimensions to `u32`, as the `image` crate requires.
+fn image_size(dim: Dimensions) -> (u32, u32) {
+    let convert = |n: usize| u32::try_from(n).expect("image is too large");
+    (convert(dim.width), convert(dim.height))
+}
+
+/// # Panics
+///
+/// This conversion panics if the width or height of the pixmap doesn't fit
+/// in a [`u32`].
+impl<P: Pixel> From<&Pixmap<P>> for RgbImage {
+    fn from(pixmap: &Pixmap<P>) -> Self {
+        let (width, height) = image_size(pixmap.dimensions());
+        let mut image = Self::new(width, height);
+        for (pos, pixel) in pixmap.enumerate_pixels() {
+            let rgb = Rgb(pixel.to_color().to_rgb8());
+            image.put_pixel(pos.x as u32, pos.y as u32, rgb);
+        }
+        image
+    }
+}
+
+/// # Panics
+///
+/// This conversion panics if the width or height of the pixmap doesn't fit
+/// in a [`u32`].
+impl<P: Pixel> From<Pixmap<P>> for RgbImage {
+    fn from(pixmap: Pixmap<P>) -> Self {
+        Self::from(&pixmap)
+    }
+}
+
+/// # Panics
+///
+/// This conversion panics if the width or height of the pixmap doesn't fit
+/// in a [`u32`].
+impl<P: Pixel> From<&Pixmap<P>> for Rgb32FImage {
+    fn from(pixmap: &Pixmap<P>) -> Self {
+        let (width, height) = image_size(pixmap.dimensions());
+        let mut image = Self::new(width, height);
+        for (pos, pixel) in pixmap.enumerate_pixels() {
+            let Color {
+                red,
+                green,
+                blue,
+            } = pixel.to_color();
+            let rgb = Rgb([red, green, blue].map(|n| n as _));
+            image.put_pixel(pos.x as u32, pos.y as u32, rgb);
+        }
+        image
+    }
+}
+
+/// # Panics
+///
+/// This conversion panics if the width or height of the pixmap doesn't fit
+/// in a [`u32`].
+impl<P: Pixel> From<Pixmap<P>> for Rgb32FImage {
+    fn from(pixmap: Pixmap<P>) -> Self {
+        Self::from(&pixmap)
+    }
+}
+
+impl<P: Pixel> Pixmap<P> {
+    /// Creates a [`Pixmap`] from an image loaded with the [`image`] crate,
+    /// using [`Storage::Float`](super::Storage::Float). Alpha is ignored,
+    /// and each color is converted with [`Pixel::from_color`].
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let image = image.to_rgb32f();
+        let dimensions = Dimensions::new(
+            image.width() as usize,
+            image.height() as usize,
+        );
+        Self::from_fn(dimensions, |pos| {
+            let (x, y) = (pos.x as u32, pos.y as u32);
+            let Rgb([red, green, blue]) = *image.get_pixel(x, y);
+            P::from_color(Color {
+                red: red as Float,
+                green: green as Float,
+                blue: blue as Float,
+            })
+        })
+    }
+}
+
+impl<P: Pixel> From<&DynamicImage> for Pixmap<P> {
+    fn from(image: &DynamicImage) -> Self {
+        Self::from_image(image)
+    }
+}
@@ -0,0 +1,58 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Conversions between [`Pixmap`] and [`ndarray`] arrays.
+
+use super::{Pixel, Pixmap};
+use crate::Dimensions;
+use ndarray::{ArrayView2, ArrayViewMut2};
+
+impl<P: Pixel> Pixmap<P> {
+    /// The image as a two-dimensional array of pixels, indexed by
+    /// `[y, x]`, or [`None`] if the pixmap doesn't use
+    /// [`Storage::Float`](super::Storage::Float).
+    pub fn as_array_view(&self) -> Option<ArrayView2<'_, P>> {
+        let dim = self.dimensions;
+        let shape = (dim.height, dim.width);
+        // The data holds exactly `width * height` pixels in row-major order.
+        Some(ArrayView2::from_shape(shape, self.data()?).unwrap())
+    }
+
+    /// The image as a mutable two-dimensional array of pixels, indexed by
+    /// `[y, x]`, or [`None`] if the pixmap doesn't use
+    /// [`Storage::Float`](super::Storage::Float).
+    pub fn as_array_view_mut(&mut self) -> Option<ArrayViewMut2<'_, P>> {
+        let dim = self.dimensions;
+        let shape = (dim.height, dim.width);
+        // The data holds exactly `width * height` pixels in row-major order.
+        Some(ArrayViewMut2::from_shape(shape, self.data_mut()?).unwrap())
+    }
+
+    /// Creates a [`Pixmap`] from a two-dimensional array of pixels indexed
+    /// by `[y, x]`, using [`Storage::Float`](super::Storage::Float). The
+    /// array can have any memory layout.
+    pub fn from_array(array: ArrayView2<'_, P>) -> Self {
+        let (height, width) = array.dim();
+        let mut pixels = array.iter().copied();
+        // `iter` yields the pixels in row-major order.
+        Self::from_fn(Dimensions::new(width, height), |_| {
+            pixels.next().unwrap()
+        })
+    }
+}
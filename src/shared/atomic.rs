@@ -0,0 +1,112 @@
+/*
+ * Copyright (C) 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The temporary paths of files that haven't been committed or dropped yet.
+static PENDING: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Locks [`PENDING`], ignoring poisoning, as the list is always valid.
+fn pending() -> MutexGuard<'static, Vec<PathBuf>> {
+    PENDING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Removes `temp` from [`PENDING`].
+fn unregister(temp: &Path) {
+    let mut pending = pending();
+    if let Some(i) = pending.iter().position(|p| p == temp) {
+        pending.swap_remove(i);
+    }
+}
+
+/// Removes the temporary files of all uncommitted [`AtomicFile`]s.
+///
+/// Exiting with [`process::exit`] doesn't run destructors, so this must be
+/// called first to avoid leaving temporary files behind.
+#[allow(dead_code)] // The GUI never exits early.
+pub fn remove_pending() {
+    for temp in pending().drain(..) {
+        let _ = fs::remove_file(temp);
+    }
+}
+
+/// A file that's written to a temporary path in its destination directory
+/// and moved to its final path only once it's complete, so that failed or
+/// interrupted runs don't leave behind truncated files.
+///
+/// If the file is dropped without calling [`Self::commit`], or the process
+/// exits after calling [`remove_pending`], the temporary file is removed.
+pub struct AtomicFile {
+    file: File,
+    temp: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    /// Creates a temporary file that will be moved to `path`.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        // Hidden, and unique among concurrent runs.
+        let mut name = OsString::from(".");
+        name.push(path.file_name().unwrap_or_default());
+        name.push(format!(".{}.tmp", process::id()));
+        let temp = path.with_file_name(name);
+        let file = File::create(&temp)?;
+        pending().push(temp.clone());
+        Ok(Self {
+            file,
+            temp,
+            path: path.to_owned(),
+            committed: false,
+        })
+    }
+
+    /// Moves the file to its final path.
+    pub fn commit(mut self) -> io::Result<()> {
+        self.file.sync_all()?;
+        fs::rename(&self.temp, &self.path)?;
+        self.committed = true;
+        unregister(&self.temp);
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp);
+            unregister(&self.temp);
+        }
+    }
+}
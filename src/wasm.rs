@@ -0,0 +1,139 @@
+/*
+ * Copyright (C) 2024 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of Plumage.
+ *
+ * Plumage is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Plumage is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with Plumage. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! WebAssembly bindings, enabled with the `wasm` feature. Build them for
+//! the browser with something like:
+//!
+//! ```bash
+//! cargo rustc --lib --release --target wasm32-unknown-unknown \
+//!     --no-default-features --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/plumage.wasm
+//! ```
+//!
+//! Then, from JavaScript:
+//!
+//! ```js
+//! const params = resolveParams({ dimensions: "640x480", gamma: 0.6 });
+//! const pixels = generate(params, (done) => console.log(done));
+//! const { width, height } = outputDimensions(params);
+//! const image = new ImageData(pixels, width, height);
+//! ```
+
+use super::{Generator, Params};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use js_sys::{Function, Math, Object, Reflect, Uint8ClampedArray};
+use wasm_bindgen::prelude::*;
+
+/// How many times progress is reported while generating an image.
+const PROGRESS_STEPS: usize = 100;
+
+/// Returns `digits` random hex digits.
+fn random_hex(digits: usize) -> String {
+    (0..digits)
+        .map(|_| {
+            let n = (Math::random() * 16.0) as u32;
+            char::from_digit(n.min(15), 16).unwrap()
+        })
+        .collect()
+}
+
+/// Converts a JavaScript object to params, filling in a random start color
+/// and seed if they're missing.
+fn params_from_js(params: &JsValue) -> Result<Params, JsError> {
+    let object: &Object = params
+        .dyn_ref()
+        .ok_or_else(|| JsError::new("params must be an object"))?;
+    // Copy the object so the caller's isn't modified.
+    let object = Object::assign(&Object::new(), object);
+    let random = [
+        ("start_color", format!("#{}", random_hex(6))),
+        ("seed", random_hex(64)),
+    ];
+    for (field, value) in random {
+        let field = JsValue::from_str(field);
+        let missing = Reflect::get(&object, &field)
+            .map_or(true, |value| value.is_undefined());
+        if missing {
+            let value = JsValue::from_str(&value);
+            Reflect::set(&object, &field, &value).map_err(|_| {
+                JsError::new("could not set default params")
+            })?;
+        }
+    }
+    let params: Params = serde_wasm_bindgen::from_value(object.into())?;
+    params
+        .validate()
+        .map_err(|e| JsError::new(&format!("invalid params: {e}")))?;
+    Ok(params)
+}
+
+/// Fills in the defaults for any missing fields in `params`, including a
+/// random start color and seed, and returns the complete params as an
+/// object. Passing the result to `generate` always produces the same image.
+#[wasm_bindgen(js_name = resolveParams)]
+pub fn resolve_params(params: JsValue) -> Result<JsValue, JsError> {
+    let params = params_from_js(&params)?;
+    Ok(serde_wasm_bindgen::to_value(&params)?)
+}
+
+/// Returns the dimensions of the image that `generate` produces for
+/// `params`, as an object with `width` and `height`. These differ from
+/// `params.dimensions` when the image is repeated, or a border or rotation
+/// expands it.
+#[wasm_bindgen(js_name = outputDimensions)]
+pub fn output_dimensions(params: JsValue) -> Result<JsValue, JsError> {
+    let params = params_from_js(&params)?;
+    Ok(serde_wasm_bindgen::to_value(&params.output_dimensions())?)
+}
+
+/// Generates an image and returns its pixels as RGBA bytes, which can be
+/// passed directly to the `ImageData` constructor along with the size from
+/// `outputDimensions`. Missing params are filled in as with
+/// `resolveParams`. If `progress` is given, it's called periodically with
+/// the fraction of the image that's been generated, from 0 to 1.
+#[wasm_bindgen]
+pub fn generate(
+    params: JsValue,
+    progress: Option<Function>,
+) -> Result<Uint8ClampedArray, JsError> {
+    let params = params_from_js(&params)?;
+    let dim = params.output_dimensions();
+    let mut generator = Generator::new(params);
+    if let Some(progress) = &progress {
+        // Rows are counted as they're filled, which may be columns of the
+        // output.
+        let total = generator.total_rows();
+        let step = total.div_ceil(PROGRESS_STEPS).max(1);
+        loop {
+            let more = generator.step_rows(step);
+            let done = generator.rows_filled() as f64 / total as f64;
+            // Errors thrown by the callback are ignored.
+            let _ = progress.call1(&JsValue::NULL, &JsValue::from(done));
+            if !more {
+                break;
+            }
+        }
+    }
+    let mut pixels = vec![0; dim.count() * 4];
+    generator.generate_into_rgba(&mut pixels, dim.width * 4);
+    Ok(Uint8ClampedArray::from(&pixels[..]))
+}